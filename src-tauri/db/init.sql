@@ -1,3 +1,4 @@
+-- 初始表结构，之后的表结构变更见 src/database.rs 中的 MIGRATIONS
 -- 卡牌模板
 CREATE TABLE IF NOT EXISTS templates (
     template_id INTEGER PRIMARY KEY AUTOINCREMENT,
@@ -0,0 +1,27 @@
+//! 牌组统计性能测试
+//!
+//! 在临时目录生成合成卡片集合，比较主页牌组统计在不同查询方式和有无索引时的耗时：
+//!
+//! ```sh
+//! cargo run --release --features benchmark --example deck_overview_benchmark -- [牌组数] [卡片数]
+//! ```
+//!
+//! 默认生成 50 个牌组、100000 张卡片
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let deck_count = args.next().and_then(|arg| arg.parse().ok()).unwrap_or(50);
+    let card_count = args
+        .next()
+        .and_then(|arg| arg.parse().ok())
+        .unwrap_or(100_000);
+    let db_path = std::env::temp_dir().join("echocard_benchmark.db");
+
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    if let Err(e) = runtime.block_on(echocard_lib::benchmark::run_deck_overview_benchmark(
+        &db_path, deck_count, card_count,
+    )) {
+        eprintln!("性能测试失败: {}", e);
+        std::process::exit(1);
+    }
+}
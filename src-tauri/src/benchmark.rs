@@ -0,0 +1,217 @@
+//! 大量卡片时的牌组统计性能测试
+//!
+//! 生成包含指定数量牌组和卡片的合成卡片集合，测量主页牌组统计的耗时，
+//! 并与逐个牌组执行三次 COUNT 查询的旧方式以及没有索引的情况对比。
+//! 运行方式见 `examples/deck_overview_benchmark.rs`
+
+use crate::controller::card_controller::merge_template_fields;
+use crate::controller::deck_controller::{create_deck, get_decks};
+use crate::controller::template_controller::create_template;
+use crate::database::initialize_database;
+use crate::models::Template;
+use chrono::{Duration, Local, Utc};
+use sqlx::{Result, SqlitePool};
+use std::fs;
+use std::path::Path;
+use std::time::Instant;
+
+/// 每种查询方式重复执行的次数，结果取平均值
+const BENCHMARK_RUNS: u32 = 20;
+
+/// 每张已复习卡片的复习记录数
+const REVIEWS_PER_CARD: i64 = 3;
+
+/// 迁移中为牌组统计添加的索引
+const DECK_OVERVIEW_INDEXES: &[&str] = &["idx_cards_deck_due", "idx_cards_last_review"];
+
+/// 生成合成卡片集合
+///
+/// 卡片平均分配到各牌组，约三成为新卡片，其余卡片的到期时间分布在过去 30 天到未来 60 天之间，
+/// 每张已复习卡片带有若干复习记录。卡片状态由卡片序号决定，每次生成的集合相同
+pub async fn generate_collection(
+    pool: &SqlitePool,
+    deck_count: u32,
+    card_count: u32,
+) -> Result<()> {
+    let template_id = create_template(
+        pool,
+        &Template {
+            template_id: 0,
+            template_name: "性能测试卡片".to_string(),
+            template_fields: vec![("正面".to_string(), true), ("反面".to_string(), false)],
+        },
+    )
+    .await?;
+
+    let mut deck_ids = Vec::with_capacity(deck_count as usize);
+    for index in 0..deck_count {
+        deck_ids.push(create_deck(pool, &format!("性能测试牌组 {}", index + 1)).await?);
+    }
+
+    let now = Utc::now();
+    let mut tx = pool.begin().await?;
+    for index in 0..card_count as i64 {
+        let deck_id = deck_ids[index as usize % deck_ids.len()];
+        let fields =
+            merge_template_fields(vec![format!("问题 {}", index), format!("答案 {}", index)]);
+        if index / deck_ids.len() as i64 % 10 < 3 {
+            sqlx::query(
+                "INSERT INTO cards
+                (deck_id, template_id, template_fields, due,
+                stability, difficulty, scheduled_days, last_review, modified_at)
+                VALUES (?, ?, ?, ?, NULL, NULL, 0, NULL, ?)",
+            )
+            .bind(deck_id)
+            .bind(template_id)
+            .bind(fields)
+            .bind(now)
+            .bind(now)
+            .execute(&mut *tx)
+            .await?;
+            continue;
+        }
+
+        let scheduled_days = index * 7919 % 60 + 1;
+        let due = now + Duration::days(index * 104729 % 90 - 30);
+        let last_review = due - Duration::days(scheduled_days);
+        let card_id = sqlx::query(
+            "INSERT INTO cards
+            (deck_id, template_id, template_fields, due,
+            stability, difficulty, scheduled_days, last_review, modified_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(deck_id)
+        .bind(template_id)
+        .bind(fields)
+        .bind(due)
+        .bind(scheduled_days as f32)
+        .bind(5.0f32)
+        .bind(scheduled_days)
+        .bind(last_review)
+        .bind(last_review)
+        .execute(&mut *tx)
+        .await?
+        .last_insert_rowid();
+
+        for review in 0..REVIEWS_PER_CARD {
+            sqlx::query("INSERT INTO reviews (card_id, review_date, rating) VALUES (?, ?, ?)")
+                .bind(card_id)
+                .bind(last_review - Duration::days(review * scheduled_days))
+                .bind(3)
+                .execute(&mut *tx)
+                .await?;
+        }
+    }
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// 逐个牌组执行三次 COUNT 查询统计卡片数量，作为对比的旧方式
+async fn count_decks_one_by_one(pool: &SqlitePool) -> Result<u32> {
+    let today_local = Local::now().date_naive();
+    let today_start_utc = today_local
+        .and_hms_opt(0, 0, 0)
+        .unwrap()
+        .and_local_timezone(Local)
+        .unwrap()
+        .with_timezone(&Utc);
+    let today_end_utc = today_local
+        .and_hms_opt(23, 59, 59)
+        .unwrap()
+        .and_local_timezone(Local)
+        .unwrap()
+        .with_timezone(&Utc);
+
+    let deck_ids: Vec<(i64,)> = sqlx::query_as("SELECT deck_id FROM decks")
+        .fetch_all(pool)
+        .await?;
+    let mut total = 0;
+    for (deck_id,) in deck_ids {
+        let (tolearn,): (i64,) =
+            sqlx::query_as("SELECT COUNT(*) FROM cards WHERE deck_id = ? AND last_review IS NULL")
+                .bind(deck_id)
+                .fetch_one(pool)
+                .await?;
+        let (learning,): (i64,) = sqlx::query_as(
+            "SELECT COUNT(*) FROM cards
+            WHERE deck_id = ? AND last_review >= ?
+            AND last_review <= ? AND due >= ? AND due <= ?",
+        )
+        .bind(deck_id)
+        .bind(today_start_utc)
+        .bind(today_end_utc)
+        .bind(today_start_utc)
+        .bind(today_end_utc)
+        .fetch_one(pool)
+        .await?;
+        let (toreview,): (i64,) = sqlx::query_as(
+            "SELECT COUNT(*) FROM cards WHERE deck_id = ? AND last_review < ? AND due <= ?",
+        )
+        .bind(deck_id)
+        .bind(today_start_utc)
+        .bind(today_end_utc)
+        .fetch_one(pool)
+        .await?;
+        total += (tolearn + learning + toreview) as u32;
+    }
+    Ok(total)
+}
+
+/// 分别测量分组聚合查询和逐个牌组查询的平均耗时（毫秒）
+async fn measure_deck_overview(pool: &SqlitePool) -> Result<(f64, f64)> {
+    let started = Instant::now();
+    for _ in 0..BENCHMARK_RUNS {
+        get_decks(pool).await?;
+    }
+    let grouped_ms = started.elapsed().as_secs_f64() * 1000.0 / BENCHMARK_RUNS as f64;
+
+    let started = Instant::now();
+    for _ in 0..BENCHMARK_RUNS {
+        count_decks_one_by_one(pool).await?;
+    }
+    let one_by_one_ms = started.elapsed().as_secs_f64() * 1000.0 / BENCHMARK_RUNS as f64;
+
+    Ok((grouped_ms, one_by_one_ms))
+}
+
+/// 运行牌组统计性能测试
+///
+/// 在 `db_path` 创建新的数据库并生成合成卡片集合，已有的文件会被覆盖。
+/// 先在有索引时测量，再删除索引测量一次，结果输出到控制台
+pub async fn run_deck_overview_benchmark(
+    db_path: &Path,
+    deck_count: u32,
+    card_count: u32,
+) -> Result<()> {
+    if db_path.exists() {
+        fs::remove_file(db_path)?;
+    }
+    let pool = initialize_database(&format!("sqlite://{}", db_path.display())).await?;
+
+    println!("生成 {} 个牌组、{} 张卡片...", deck_count, card_count);
+    let started = Instant::now();
+    generate_collection(&pool, deck_count.max(1), card_count).await?;
+    sqlx::query("ANALYZE").execute(&pool).await?;
+    println!("生成完成，用时 {:.1} 秒", started.elapsed().as_secs_f64());
+
+    let (grouped_ms, one_by_one_ms) = measure_deck_overview(&pool).await?;
+    println!(
+        "有索引：分组查询 {:.2} ms，逐个牌组查询 {:.2} ms",
+        grouped_ms, one_by_one_ms
+    );
+
+    for index in DECK_OVERVIEW_INDEXES {
+        sqlx::query(&format!("DROP INDEX IF EXISTS {}", index))
+            .execute(&pool)
+            .await?;
+    }
+    let (grouped_ms, one_by_one_ms) = measure_deck_overview(&pool).await?;
+    println!(
+        "无索引：分组查询 {:.2} ms，逐个牌组查询 {:.2} ms",
+        grouped_ms, one_by_one_ms
+    );
+
+    pool.close().await;
+    Ok(())
+}
@@ -1,3 +1,6 @@
 pub mod cardedit;
 pub mod cardmemo;
-pub mod settings;
\ No newline at end of file
+pub mod media;
+pub mod profile;
+pub mod settings;
+pub mod statistics;
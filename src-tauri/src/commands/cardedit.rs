@@ -1,48 +1,74 @@
+use chrono::Utc;
+
+use crate::controller::browser_controller::browse_cards;
 use crate::controller::card_controller::{
-    create_card, delete_card_by_id, get_card_by_filter, update_card_fields,
+    annotate_cards, create_card, delete_card_by_id, get_card_by_filter, get_cards_by_ids,
+    sort_cards, update_card_fields,
 };
 use crate::controller::deck_controller::create_deck;
+use crate::controller::error_message;
+use crate::controller::render_controller::{get_template_format, update_template_format};
 use crate::controller::template_controller::{
-    create_template, get_all_templates, get_template_fields,
+    change_cards_template, create_template, delete_template_by_id, get_all_templates, get_template,
+    get_template_fields, get_template_renderers, get_template_usage, set_template_renderer,
+    update_template_field,
 };
 use crate::models::Template;
-use crate::models::{Card, TemplateField};
+use crate::models::{
+    Card, CardBrowserPage, CardBrowserQuery, CardSortOrder, FieldType, TemplateConfig,
+    TemplateConversion, TemplateField, TemplateFormat, TemplateInfo, TemplateUsage,
+};
 use crate::AppState;
-use serde::{Deserialize, Serialize};
-use std::fs;
-use tauri::path::BaseDirectory;
-use tauri::Manager;
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct TemplateConfig {
-    templates: Vec<TemplateInfo>,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-struct TemplateInfo {
-    templateName: String,
-    className: String,
-    importPath: String,
-}
 
+/// 筛选卡片
+///
+/// 返回的卡片带有当前可提取性和逾期天数，`sort_order` 为空时按到期时间排序
 #[tauri::command]
 pub async fn card_filter(
     state: tauri::State<'_, AppState>,
     template_ids: Vec<u32>,
     deck_ids: Vec<u32>,
     status_bit_filter: u8,
+    sort_order: Option<CardSortOrder>,
 ) -> Result<Vec<Card>, String> {
-    let mut cards = get_card_by_filter(&state.pool, template_ids, deck_ids, status_bit_filter)
+    let mut cards = get_card_by_filter(&state.pool(), template_ids, deck_ids, status_bit_filter)
         .await
         .map_err(|e| e.to_string())?;
-    cards.sort_by(|a, b| a.due.cmp(&b.due));
+    let config = state.config().get();
+    annotate_cards(&mut cards, &config.fsrs_params, Utc::now());
+    sort_cards(&mut cards, sort_order.unwrap_or_default());
 
     Ok(cards)
 }
 
+/// 分页浏览卡片
+///
+/// 按筛选条件和排序方式在数据库中分页，返回当前页卡片列表需要的列以及符合条件的卡片总数
+#[tauri::command]
+pub async fn card_browser_page(
+    state: tauri::State<'_, AppState>,
+    query: CardBrowserQuery,
+) -> Result<CardBrowserPage, String> {
+    let config = state.config().get();
+    browse_cards(&state.pool(), &query, &config.fsrs_params, Utc::now())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 获取单张卡片的完整内容，带有当前可提取性和逾期天数
+#[tauri::command]
+pub async fn card_detail(state: tauri::State<'_, AppState>, card_id: u32) -> Result<Card, String> {
+    let mut cards = get_cards_by_ids(&state.pool(), &[card_id])
+        .await
+        .map_err(|e| e.to_string())?;
+    let config = state.config().get();
+    annotate_cards(&mut cards, &config.fsrs_params, Utc::now());
+    cards.pop().ok_or_else(|| "卡片不存在".to_string())
+}
+
 #[tauri::command]
 pub async fn template_display(state: tauri::State<'_, AppState>) -> Result<Vec<Template>, String> {
-    let templates = get_all_templates(&state.pool)
+    let templates = get_all_templates(&state.pool())
         .await
         .map_err(|e| e.to_string())?;
 
@@ -56,9 +82,9 @@ pub async fn add_card(
     template_id: u32,
     template_fields: Vec<String>,
 ) -> Result<u32, String> {
-    let card_id = create_card(&state.pool, deck_id, template_id, template_fields)
+    let card_id = create_card(&state.pool(), deck_id, template_id, template_fields)
         .await
-        .map_err(|e| e.to_string())?;
+        .map_err(error_message)?;
 
     Ok(card_id)
 }
@@ -68,7 +94,7 @@ pub async fn get_fields(
     state: tauri::State<'_, AppState>,
     template_id: u32,
 ) -> Result<Vec<TemplateField>, String> {
-    let fields = get_template_fields(&state.pool, template_id)
+    let fields = get_template_fields(&state.pool(), template_id)
         .await
         .map_err(|e| e.to_string())?;
 
@@ -81,9 +107,9 @@ pub async fn update_card_content(
     card_id: u32,
     template_fields: Vec<String>,
 ) -> Result<(), String> {
-    update_card_fields(&state.pool, card_id, template_fields)
+    update_card_fields(&state.pool(), card_id, template_fields)
         .await
-        .map_err(|e| e.to_string())?;
+        .map_err(error_message)?;
 
     Ok(())
 }
@@ -92,81 +118,172 @@ pub async fn update_card_content(
 pub async fn add_template(
     state: tauri::State<'_, AppState>,
     template: Template,
+) -> Result<u32, String> {
+    let template_id = create_template(&state.pool(), &template)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(template_id)
+}
+
+/// 更新模板字段的类型和校验约束
+///
+/// 设置字段类型、是否必填以及内容需要匹配的正则表达式，之后添加或修改卡片时按这些约束校验
+#[tauri::command]
+pub async fn set_template_field(
+    state: tauri::State<'_, AppState>,
+    template_id: u32,
+    field_id: u32,
+    field_type: FieldType,
+    required: bool,
+    pattern: Option<String>,
 ) -> Result<(), String> {
-    create_template(&state.pool, &template)
+    update_template_field(
+        &state.pool(),
+        template_id,
+        field_id,
+        field_type,
+        required,
+        pattern,
+    )
+    .await
+    .map_err(error_message)?;
+
+    Ok(())
+}
+
+/// 获取模板的卡片格式
+///
+/// 返回模板的正面、背面格式与 CSS，未设置格式时返回根据字段生成的默认格式
+#[tauri::command]
+pub async fn template_format(
+    state: tauri::State<'_, AppState>,
+    template_id: u32,
+) -> Result<TemplateFormat, String> {
+    let format = get_template_format(&state.pool(), template_id)
         .await
         .map_err(|e| e.to_string())?;
 
+    Ok(format)
+}
+
+/// 设置模板的卡片格式
+///
+/// 格式支持 {{字段}}、{{#字段}}…{{/字段}}、{{^字段}}…{{/字段}}，背面可使用 {{FrontSide}}
+#[tauri::command]
+pub async fn set_template_format(
+    state: tauri::State<'_, AppState>,
+    format: TemplateFormat,
+) -> Result<(), String> {
+    update_template_format(&state.pool(), &format)
+        .await
+        .map_err(error_message)?;
+
     Ok(())
 }
 
-/// 读取模板配置文件
+/// 读取模板渲染配置
 ///
-/// 读取 templateConfig.json 文件中的内容并返回
+/// 返回数据库中每个模板在前端使用的模板类、导入路径和渲染选项
 #[tauri::command]
-pub async fn get_template_config(handle: tauri::AppHandle) -> Result<TemplateConfig, String> {
-    let config_path = handle
-        .path()
-        .resolve("templateConfig.json", BaseDirectory::Resource)
+pub async fn get_template_config(
+    state: tauri::State<'_, AppState>,
+) -> Result<TemplateConfig, String> {
+    let config = get_template_renderers(&state.pool())
+        .await
         .map_err(|e| e.to_string())?;
-    let file = fs::File::open(config_path).map_err(|e| format!("打开模板配置文件失败: {}", e))?;
-    let config: TemplateConfig =
-        serde_json::from_reader(file).map_err(|e| format!("解析模板配置文件失败: {}", e))?;
+
     Ok(config)
 }
 
-/// 添加模板配置
+/// 添加模板渲染配置
 ///
-/// 向 templateConfig.json 文件中添加新的模板配置
+/// 为指定模板设置前端使用的模板类、导入路径和渲染选项，已有配置时覆盖原配置
 #[tauri::command]
 pub async fn add_template_config(
-    handle: tauri::AppHandle,
-    template_name: String,
+    state: tauri::State<'_, AppState>,
+    template_id: u32,
     class_name: String,
     import_path: String,
+    options: Option<serde_json::Value>,
 ) -> Result<(), String> {
-    let config_path = handle
-        .path()
-        .resolve("templateConfig.json", BaseDirectory::Resource)
-        .map_err(|e| e.to_string())?;
+    let renderer = TemplateInfo {
+        template_id,
+        template_name: String::new(),
+        class_name,
+        import_path,
+        options: options.unwrap_or_default(),
+    };
 
-    // 读取现有配置
-    let config_content =
-        fs::read_to_string(&config_path).map_err(|e| format!("读取模板配置文件失败: {}", e))?;
-    let mut config: TemplateConfig = serde_json::from_str(&config_content)
-        .map_err(|e| format!("解析模板配置文件失败: {}", e))?;
-
-    // 检查是否存在同名模板
-    if config
-        .templates
-        .iter()
-        .any(|t| t.templateName == template_name)
-    {
-        return Err(format!("模板名称 '{}' 已存在", template_name));
-    }
-
-    // 添加新模板
-    config.templates.push(TemplateInfo {
-        templateName: template_name,
-        className: class_name,
-        importPath: import_path,
-    });
-
-    // 写回文件
-    let updated_content =
-        serde_json::to_string_pretty(&config).map_err(|e| format!("序列化模板配置失败: {}", e))?;
-
-    fs::write(&config_path, updated_content).map_err(|e| format!("写入模板配置文件失败: {}", e))?;
+    set_template_renderer(&state.pool(), &renderer)
+        .await
+        .map_err(error_message)?;
 
     Ok(())
 }
 
+/// 查询模板使用情况
+///
+/// 返回使用该模板的卡片数量和牌组数量
+#[tauri::command]
+pub async fn template_usage(
+    state: tauri::State<'_, AppState>,
+    template_id: u32,
+) -> Result<TemplateUsage, String> {
+    let usage = get_template_usage(&state.pool(), template_id)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(usage)
+}
+
+/// 删除模板
+///
+/// 仅当模板未被卡片使用，或提供了 `conversion` 将其卡片转换到其他模板时才允许删除，
+/// 模板的字段和渲染配置一并删除，返回被转换的卡片数量
+#[tauri::command]
+pub async fn delete_template(
+    state: tauri::State<'_, AppState>,
+    template_id: u32,
+    conversion: Option<TemplateConversion>,
+) -> Result<u32, String> {
+    get_template(&state.pool(), template_id)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("模板 {} 不存在", template_id))?;
+
+    let converted = delete_template_by_id(&state.pool(), template_id, conversion.as_ref())
+        .await
+        .map_err(error_message)?;
+
+    state.loaded_template.lock().unwrap().remove(&template_id);
+
+    Ok(converted)
+}
+
+/// 更换卡片模板
+///
+/// 按字段映射将一组使用相同模板的卡片转换到目标模板，保留卡片的记忆状态和复习记录，
+/// 返回被转换的卡片数量
+#[tauri::command]
+pub async fn change_card_template(
+    state: tauri::State<'_, AppState>,
+    card_ids: Vec<u32>,
+    conversion: TemplateConversion,
+) -> Result<u32, String> {
+    let converted = change_cards_template(&state.pool(), &card_ids, &conversion)
+        .await
+        .map_err(error_message)?;
+
+    Ok(converted)
+}
+
 /// 删除卡片
 ///
 /// 根据卡片ID删除指定的卡片及其相关复习记录
 #[tauri::command]
 pub async fn delete_card(state: tauri::State<'_, AppState>, card_id: u32) -> Result<(), String> {
-    delete_card_by_id(&state.pool, card_id)
+    delete_card_by_id(&state.pool(), card_id)
         .await
         .map_err(|e| e.to_string())?;
 
@@ -175,7 +292,7 @@ pub async fn delete_card(state: tauri::State<'_, AppState>, card_id: u32) -> Res
 
 #[tauri::command]
 pub async fn add_deck(state: tauri::State<'_, AppState>, deck_name: String) -> Result<(), String> {
-    create_deck(&state.pool, &deck_name)
+    create_deck(&state.pool(), &deck_name)
         .await
         .map_err(|e| e.to_string())?;
 
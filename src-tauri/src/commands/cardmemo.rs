@@ -1,38 +1,37 @@
-use chrono::Duration;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use chrono::Utc;
 use fsrs::FSRS;
-use serde::{Deserialize, Serialize};
 
 use crate::controller::card_controller::{
-    get_card_count_learned_today, get_cards_by_page, update_card_state,
+    annotate_cards, get_card_count_learned_today, get_cards_by_ids,
+};
+use crate::controller::deck_controller::{
+    delete_deck_by_id, get_deck_queue_options, get_decks, update_deck_queue_options,
+};
+use crate::controller::error_message;
+use crate::controller::render_controller::render_card_by_id;
+use crate::controller::session_controller::{
+    create_study_session, delete_study_session, get_latest_study_session_id, get_session_cards,
+    get_session_deck_ids, load_study_session, rated_state, record_answer, save_study_session,
 };
-use crate::controller::deck_controller::{delete_deck_by_id, get_decks};
-use crate::controller::review_controller::create_review;
 use crate::controller::template_controller::{get_template, parse_template};
-use crate::models::Card;
 use crate::models::Deck;
+use crate::models::QueueOptions;
+use crate::models::RenderedCard;
 use crate::models::Template;
+use crate::models::{StudyCard, StudyMode, StudyProgress};
 use crate::AppState;
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct NextIntervals {
-    pub again: u32,
-    pub hard: u32,
-    pub good: u32,
-    pub easy: u32,
-}
-
 #[tauri::command]
 pub async fn decks_display(state: tauri::State<'_, AppState>) -> Result<Vec<Deck>, String> {
-    let decks = get_decks(&state.pool).await.map_err(|e| e.to_string())?;
+    let decks = get_decks(&state.pool()).await.map_err(|e| e.to_string())?;
     Ok(decks)
 }
 
 #[tauri::command]
 pub async fn card_count_learned_today(state: tauri::State<'_, AppState>) -> Result<u32, String> {
-    let count = get_card_count_learned_today(&state.pool)
+    let count = get_card_count_learned_today(&state.pool())
         .await
         .map_err(|e| e.to_string())?;
     Ok(count)
@@ -40,40 +39,295 @@ pub async fn card_count_learned_today(state: tauri::State<'_, AppState>) -> Resu
 
 #[tauri::command]
 pub async fn delete_deck(state: tauri::State<'_, AppState>, deck_id: u32) -> Result<(), String> {
-    delete_deck_by_id(&state.pool, deck_id)
+    delete_deck_by_id(&state.pool(), deck_id)
         .await
         .map_err(|e| e.to_string())?;
     Ok(())
 }
 
+/// 获取牌组的学习队列选项
 #[tauri::command]
-pub async fn get_next_card(
+pub async fn deck_queue_options(
     state: tauri::State<'_, AppState>,
     deck_id: u32,
-    page_size: u32,
-) -> Result<Vec<Card>, String> {
-    let cards = get_cards_by_page(&state.pool, deck_id, page_size)
+) -> Result<QueueOptions, String> {
+    get_deck_queue_options(&state.pool(), deck_id)
         .await
-        .map_err(|e| e.to_string())?;
+        .map_err(|e| e.to_string())
+}
+
+/// 设置牌组的学习队列选项
+#[tauri::command]
+pub async fn set_deck_queue_options(
+    state: tauri::State<'_, AppState>,
+    deck_id: u32,
+    options: QueueOptions,
+) -> Result<(), String> {
+    update_deck_queue_options(&state.pool(), deck_id, &options)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 加载学习会话中卡片用到的模板到缓存
+async fn cache_session_templates(state: &AppState, template_ids: HashSet<u32>) {
     let mut templates: HashMap<u32, Template> = HashMap::new();
-    for card in cards.iter() {
-        if templates.contains_key(&card.template_id) {
-            continue;
-        }
-        let template = match parse_template(&state.pool, card.template_id).await {
+    for template_id in template_ids {
+        let template = match parse_template(&state.pool(), template_id).await {
             Ok(template) => template,
             Err(e) => {
                 println!("Error loading template: {}", e);
                 continue;
             }
         };
-        templates.insert(card.template_id, template);
+        templates.insert(template_id, template);
+    }
+    state.loaded_template.lock().unwrap().extend(templates);
+}
+
+/// 开始学习牌组
+///
+/// `deck_ids` 为要一起学习的牌组，为空时学习所有牌组。`mode` 为学习模式，默认为普通学习，
+/// 按各牌组的学习队列选项取出今天要学习的全部卡片；筛选学习提前学习尚未到期的卡片，
+/// 临时抱佛脚学习所有学过的卡片。创建学习会话并返回会话的初始进度，会话保存在数据库中，
+/// 应用重启后可以继续
+#[tauri::command]
+pub async fn start_study_session(
+    state: tauri::State<'_, AppState>,
+    deck_ids: Vec<u32>,
+    mode: Option<StudyMode>,
+) -> Result<StudyProgress, String> {
+    let config = state.config().get();
+    let mode = mode.unwrap_or_default();
+    let deck_ids = get_session_deck_ids(&state.pool(), deck_ids)
+        .await
+        .map_err(error_message)?;
+    let cards = get_session_cards(&state.pool(), &deck_ids, mode, &config.fsrs_params)
+        .await
+        .map_err(|e| e.to_string())?;
+    cache_session_templates(&state, cards.iter().map(|card| card.template_id).collect()).await;
+
+    let now = Utc::now();
+    let session = create_study_session(&state.pool(), deck_ids, mode, cards, now)
+        .await
+        .map_err(|e| e.to_string())?;
+    let progress = session.progress(now);
+    state.sessions.lock().unwrap().insert(session);
+    Ok(progress)
+}
+
+/// 获取可以继续的学习会话
+///
+/// 返回最近保存且尚未打开的学习会话的进度，例如应用关闭前未完成的学习，没有时返回 None
+#[tauri::command]
+pub async fn resumable_study_session(
+    state: tauri::State<'_, AppState>,
+) -> Result<Option<StudyProgress>, String> {
+    let open_session_ids = state.sessions.lock().unwrap().session_ids();
+    let Some(session_id) = get_latest_study_session_id(&state.pool(), &open_session_ids)
+        .await
+        .map_err(|e| e.to_string())?
+    else {
+        return Ok(None);
+    };
+
+    let config = state.config().get();
+    let now = Utc::now();
+    let session = load_study_session(&state.pool(), session_id, &config.fsrs_params, now)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(session.map(|session| session.progress(now)))
+}
+
+/// 继续保存的学习会话
+///
+/// 剩余卡片使用最新的卡片状态，会话已打开时直接返回其进度
+#[tauri::command]
+pub async fn resume_study_session(
+    state: tauri::State<'_, AppState>,
+    session_id: u32,
+) -> Result<StudyProgress, String> {
+    let now = Utc::now();
+    if let Ok(session) = state.sessions.lock().unwrap().get_mut(session_id) {
+        return Ok(session.progress(now));
+    }
+
+    let config = state.config().get();
+    let session = load_study_session(&state.pool(), session_id, &config.fsrs_params, now)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "学习会话不存在或已结束".to_string())?;
+    cache_session_templates(&state, session.template_ids()).await;
+
+    let mut sessions = state.sessions.lock().unwrap();
+    if let Ok(session) = sessions.get_mut(session_id) {
+        return Ok(session.progress(now));
+    }
+    let progress = session.progress(now);
+    sessions.insert(session);
+    Ok(progress)
+}
+
+/// 获取学习会话中当前要学习的卡片
+///
+/// 返回卡片、各评分对应的间隔天数和会话进度，未回答前重复调用返回同一张卡片，
+/// 会话中没有剩余卡片时返回 None。卡片展示前重新读取其最新状态，
+/// 已删除或已不在今天到期（例如在其他学习会话中已回答）的卡片被跳过
+#[tauri::command]
+pub async fn next_study_card(
+    state: tauri::State<'_, AppState>,
+    session_id: u32,
+) -> Result<Option<StudyCard>, String> {
+    let config = state.config().get();
+    let fsrs = FSRS::new(Some(&config.fsrs_params[..])).map_err(|e| e.to_string())?;
+    loop {
+        let now = Utc::now();
+        let card_id = {
+            let mut sessions = state.sessions.lock().unwrap();
+            let session = sessions.get_mut(session_id).map_err(error_message)?;
+            if let Some(card) = session.current_card(now) {
+                return Ok(Some(card));
+            }
+            match session.next_card_id() {
+                Some(card_id) => card_id,
+                None => return Ok(None),
+            }
+        };
+
+        let mut cards = get_cards_by_ids(&state.pool(), &[card_id])
+            .await
+            .map_err(|e| e.to_string())?;
+        annotate_cards(&mut cards, &config.fsrs_params, now);
+
+        let mut sessions = state.sessions.lock().unwrap();
+        let session = sessions.get_mut(session_id).map_err(error_message)?;
+        session
+            .show_card(card_id, cards.pop(), &fsrs, config.desired_retention, now)
+            .map_err(error_message)?;
     }
+}
+
+/// 回答学习会话中的卡片
+///
+/// 只能回答会话当前展示的卡片，`rating` 为 1（忘记）到 4（简单），
+/// 当天需要再次学习的卡片重新加入会话队列，返回回答后的会话进度。
+/// 卡片在展示之后已被其他学习会话回答或被修改时不保存回答，直接跳过该卡片
+#[tauri::command]
+pub async fn answer_study_card(
+    state: tauri::State<'_, AppState>,
+    session_id: u32,
+    card_id: u32,
+    rating: u32,
+    duration_ms: Option<u32>,
+) -> Result<StudyProgress, String> {
+    let (mode, (card, next_states)) = state
+        .sessions
+        .lock()
+        .unwrap()
+        .get_mut(session_id)
+        .and_then(|session| Ok((session.mode, session.take_answer(card_id, rating)?)))
+        .map_err(error_message)?;
+    let item_state = rated_state(&next_states, rating).map_err(error_message)?;
 
-    let mut loaded_template = state.loaded_template.lock().unwrap();
-    *loaded_template = templates;
+    let now = Utc::now();
+    let result = record_answer(
+        &state.pool(),
+        card.clone(),
+        &item_state,
+        rating,
+        duration_ms,
+        mode,
+        now,
+    )
+    .await;
+
+    let (progress, snapshot) = {
+        let mut sessions = state.sessions.lock().unwrap();
+        let session = sessions.get_mut(session_id).map_err(error_message)?;
+        match result {
+            Ok(Some(card)) => {
+                session.finish_answer(card, rating, now);
+                (session.progress(now), session.snapshot(now))
+            }
+            Ok(None) => {
+                session.skip_answer(card);
+                (session.progress(now), session.snapshot(now))
+            }
+            Err(e) => {
+                session.restore_answer(card, next_states);
+                return Err(error_message(e));
+            }
+        }
+    };
 
-    Ok(cards)
+    // 回答已经保存，会话保存失败时返回错误，未保存的卡片在下次保存时写入
+    if let Err(e) = save_study_session(&state.pool(), &snapshot, now).await {
+        if let Ok(session) = state.sessions.lock().unwrap().get_mut(session_id) {
+            session.save_failed(&snapshot);
+        }
+        return Err(e.to_string());
+    }
+    Ok(progress)
+}
+
+/// 暂停学习会话
+///
+/// 保存会话并关闭，之后可以通过 `resume_study_session` 继续。保存失败时会话保持打开
+#[tauri::command]
+pub async fn suspend_study_session(
+    state: tauri::State<'_, AppState>,
+    session_id: u32,
+) -> Result<(), String> {
+    let now = Utc::now();
+    let snapshot = state
+        .sessions
+        .lock()
+        .unwrap()
+        .get_mut(session_id)
+        .map_err(error_message)?
+        .snapshot(now);
+    let result = save_study_session(&state.pool(), &snapshot, now).await;
+
+    let mut sessions = state.sessions.lock().unwrap();
+    match result {
+        Ok(()) => {
+            sessions.remove(session_id).ok();
+            Ok(())
+        }
+        Err(e) => {
+            if let Ok(session) = sessions.get_mut(session_id) {
+                session.save_failed(&snapshot);
+            }
+            Err(e.to_string())
+        }
+    }
+}
+
+/// 结束学习会话
+///
+/// 返回会话结束时的进度并删除保存的会话，之后该会话 ID 不再可用，
+/// 也可以用于放弃未打开的保存的会话
+#[tauri::command]
+pub async fn end_study_session(
+    state: tauri::State<'_, AppState>,
+    session_id: u32,
+) -> Result<StudyProgress, String> {
+    let now = Utc::now();
+    let session = state.sessions.lock().unwrap().remove(session_id).ok();
+    let session = match session {
+        Some(session) => session,
+        None => {
+            let config = state.config().get();
+            load_study_session(&state.pool(), session_id, &config.fsrs_params, now)
+                .await
+                .map_err(|e| e.to_string())?
+                .ok_or_else(|| "学习会话不存在或已结束".to_string())?
+        }
+    };
+
+    delete_study_session(&state.pool(), session_id)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(session.progress(now))
 }
 
 #[tauri::command]
@@ -97,7 +351,7 @@ pub async fn get_loaded_template(
     }
 
     // If not in cache, fetch from database
-    let template = get_template(&state.pool, template_id)
+    let template = get_template(&state.pool(), template_id)
         .await
         .map_err(|e| e.to_string())?;
 
@@ -107,78 +361,17 @@ pub async fn get_loaded_template(
     }
 }
 
+/// 渲染卡片
+///
+/// 按卡片所属模板的正面、背面格式生成完整的 HTML，并返回模板的 CSS
 #[tauri::command]
-pub async fn load_next_state(
+pub async fn render_card(
     state: tauri::State<'_, AppState>,
-    card: Card,
-) -> Result<NextIntervals, String> {
-    let fsrs_params = state.fsrs_params.lock().unwrap();
-    let desired_retention = state.desired_retention.lock().unwrap();
-    let fsrs = FSRS::new(Some(&*fsrs_params)).unwrap();
-    let review_date = Utc::now();
-    let next_states = match card.last_review {
-        Some(last_review) => {
-            let elapsed_days = (review_date - last_review).num_days() as u32;
-            fsrs.next_states(card.memory_state, *desired_retention, elapsed_days)
-                .unwrap()
-        }
-        None => fsrs
-            .next_states(card.memory_state, *desired_retention, 0)
-            .unwrap(),
-    };
-    let mut loaded_card = state.loaded_card.lock().unwrap();
-    *loaded_card = Some(card);
-    let mut loaded_next_states = state.loaded_next_states.lock().unwrap();
-    *loaded_next_states = Some(next_states.clone());
-    Ok(NextIntervals {
-        again: next_states.again.interval.round() as u32,
-        hard: next_states.hard.interval.round() as u32,
-        good: next_states.good.interval.round() as u32,
-        easy: next_states.easy.interval.round() as u32,
-    })
-}
-
-#[tauri::command]
-pub async fn emit_card_review(
-    state: tauri::State<'_, AppState>,
-    rating: u32,
-) -> Result<(), String> {
-    let card = {
-        let loaded_card = state.loaded_card.lock().unwrap();
-        loaded_card.clone().expect("Card not loaded")
-    };
-    let next_states = {
-        let loaded_next_states = state.loaded_next_states.lock().unwrap();
-        loaded_next_states.clone().expect("Next states not loaded")
-    };
-    let next_state = match rating {
-        1 => &next_states.again,
-        2 => &next_states.hard,
-        3 => &next_states.good,
-        4 => &next_states.easy,
-        _ => return Err("Invalid rating".to_string()),
-    };
-    let interval = next_state.interval.round() as u32;
-
-    let memory_state = Some(next_state.memory.clone());
-    let scheduled_days = interval;
-    let last_review = Some(Utc::now());
-    let due = card.last_review.unwrap_or(last_review.unwrap()) + Duration::days(interval as i64);
-
-    update_card_state(
-        &state.pool,
-        card.card_id,
-        memory_state,
-        scheduled_days,
-        last_review,
-        due,
-    )
-    .await
-    .map_err(|e| e.to_string())?;
-
-    create_review(&state.pool, card.card_id, last_review.unwrap(), rating)
+    card_id: u32,
+) -> Result<RenderedCard, String> {
+    let rendered = render_card_by_id(&state.pool(), card_id)
         .await
-        .map_err(|e| e.to_string())?;
+        .map_err(error_message)?;
 
-    Ok(())
+    Ok(rendered)
 }
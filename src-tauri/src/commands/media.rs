@@ -0,0 +1,56 @@
+use crate::controller::media_controller::{check_media_files, store_media};
+use crate::models::MediaCheckReport;
+use crate::AppState;
+use std::fs;
+use std::path::Path;
+
+/// 导入媒体文件
+///
+/// 将前端读取的文件内容按内容哈希保存到媒体文件夹，内容相同的文件只保存一份，
+/// 返回保存后的媒体文件名，可在字段中以 [media:文件名] 的形式引用
+#[tauri::command]
+pub async fn import_media(
+    state: tauri::State<'_, AppState>,
+    file_name: String,
+    data: Vec<u8>,
+) -> Result<String, String> {
+    let media_name = store_media(&state.media_dir(), &file_name, &data)
+        .map_err(|e| format!("保存媒体文件失败: {}", e))?;
+
+    Ok(media_name)
+}
+
+/// 从本地路径导入媒体文件
+///
+/// 读取指定路径的文件并保存到媒体文件夹，返回保存后的媒体文件名
+#[tauri::command]
+pub async fn import_media_file(
+    state: tauri::State<'_, AppState>,
+    path: String,
+) -> Result<String, String> {
+    let data = fs::read(&path).map_err(|e| format!("读取媒体文件失败: {}", e))?;
+    let file_name = Path::new(&path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let media_name = store_media(&state.media_dir(), &file_name, &data)
+        .map_err(|e| format!("保存媒体文件失败: {}", e))?;
+
+    Ok(media_name)
+}
+
+/// 检查媒体文件
+///
+/// 找出卡片引用但不存在的媒体文件，以及媒体文件夹中未被任何卡片引用的文件，
+/// `delete_unused` 为 true 时同时删除未被引用的文件
+#[tauri::command]
+pub async fn check_media(
+    state: tauri::State<'_, AppState>,
+    delete_unused: bool,
+) -> Result<MediaCheckReport, String> {
+    let report = check_media_files(&state.pool(), &state.media_dir(), delete_unused)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(report)
+}
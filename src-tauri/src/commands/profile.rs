@@ -0,0 +1,71 @@
+use crate::database::builtin_template_renderers;
+use crate::profile::{open_collection, Profile};
+use crate::AppState;
+
+/// 获取所有配置文件
+#[tauri::command]
+pub async fn list_profiles(state: tauri::State<'_, AppState>) -> Result<Vec<Profile>, String> {
+    Ok(state.profiles.list())
+}
+
+/// 获取当前使用的配置文件
+#[tauri::command]
+pub async fn current_profile(state: tauri::State<'_, AppState>) -> Result<Profile, String> {
+    Ok(state.profiles.current())
+}
+
+/// 创建配置文件
+///
+/// 新配置文件拥有独立的数据库、设置和媒体文件夹，数据库在第一次切换到该配置文件时创建
+#[tauri::command]
+pub async fn create_profile(
+    state: tauri::State<'_, AppState>,
+    name: String,
+) -> Result<Profile, String> {
+    state.profiles.create(&name)
+}
+
+/// 切换配置文件
+///
+/// 打开目标配置文件的数据库并替换当前的数据库连接池、媒体文件夹和设置，无需重启应用
+#[tauri::command]
+pub async fn switch_profile(
+    state: tauri::State<'_, AppState>,
+    profile_id: String,
+) -> Result<Profile, String> {
+    let profile = state.profiles.get(&profile_id)?;
+    if profile.id == state.profiles.current().id {
+        return Ok(profile);
+    }
+
+    let config = state.profiles.open_config(&profile.id)?;
+    let collection = open_collection(config, &builtin_template_renderers()).await?;
+    if let Err(e) = state.profiles.set_current(&profile.id) {
+        collection.pool.close().await;
+        return Err(e);
+    }
+    state.replace_collection(collection).await;
+
+    Ok(profile)
+}
+
+/// 重命名配置文件
+#[tauri::command]
+pub async fn rename_profile(
+    state: tauri::State<'_, AppState>,
+    profile_id: String,
+    name: String,
+) -> Result<Profile, String> {
+    state.profiles.rename(&profile_id, &name)
+}
+
+/// 删除配置文件
+///
+/// 不能删除当前使用的配置文件，配置文件的数据库、设置和媒体文件夹会一并删除
+#[tauri::command]
+pub async fn delete_profile(
+    state: tauri::State<'_, AppState>,
+    profile_id: String,
+) -> Result<(), String> {
+    state.profiles.delete(&profile_id)
+}
@@ -1,61 +1,537 @@
-use crate::controller::review_controller::train_fsrs_parameters;
+use crate::config::{
+    default_fsrs_params, validate_fsrs_params, AutoOptimize, Config, ConfigStore, DeckPreset,
+    OptimizationRun,
+};
+use crate::controller::fsrs_params_controller::{
+    add_params_history, ensure_params_history, get_params_history, get_params_history_entry,
+};
+use crate::controller::review_controller::{count_reviews, optimize_fsrs_parameters};
+use crate::controller::schedule_controller::{recompute_memory_states, reschedule_cards};
+use crate::controller::simulation_controller::{
+    load_simulation_input, recommend_retention, simulate_workload,
+};
+use crate::models::{
+    JobProgress, ParameterMetrics, ParamsHistoryEntry, ParamsSource, RecomputeSummary,
+    RescheduleSummary, RetentionRecommendation, SimulationOptions, TrainingResult,
+    WorkloadSimulation,
+};
 use crate::AppState;
+use chrono::Utc;
+use fsrs::CombinedProgressState;
+use sqlx::SqlitePool;
+use std::sync::{Arc, Mutex};
+use tauri::{Emitter, Manager};
+
+/// 重新安排复习时间的进度事件
+pub const RESCHEDULE_PROGRESS_EVENT: &str = "reschedule-progress";
+
+/// 重新计算记忆状态的进度事件
+pub const RECOMPUTE_PROGRESS_EVENT: &str = "recompute-progress";
+
+/// 训练FSRS模型的进度事件
+pub const TRAINING_PROGRESS_EVENT: &str = "training-progress";
+
+/// 自动重新训练FSRS模型完成的事件
+pub const AUTO_OPTIMIZE_FINISHED_EVENT: &str = "auto-optimize-finished";
 
 /// 训练FSRS模型参数
 ///
-/// 使用数据库中的复习记录训练FSRS模型，返回优化后的参数
+/// 在后台使用数据库中的复习记录训练候选参数，并用同一份复习记录评估当前参数和候选参数。
+/// 训练期间发送 training-progress 事件报告进度，可以通过 `cancel_fsrs_training` 取消。
+/// 候选参数更好时立即保存到配置文件并记录到参数历史，否则只返回评估结果，由用户确认后通过
+/// `apply_trained_fsrs_params` 保存；没有训练时返回的 `skipped` 说明原因
 #[tauri::command]
-pub async fn train_fsrs_model(state: tauri::State<'_, AppState>) -> Result<Vec<f32>, String> {
-    // 获取当前的FSRS参数
-    let current_params = {
-        let params = state.fsrs_params.lock().unwrap();
-        *params
-    };
+pub async fn train_fsrs_model(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<TrainingResult, String> {
+    run_fsrs_training(app, &state, false).await
+}
+
+/// 训练FSRS模型，候选参数更好时保存，完成训练后记录到配置中
+///
+/// 同一时间只能有一个训练，`automatic` 表示是否为自动重新训练
+async fn run_fsrs_training(
+    app: tauri::AppHandle,
+    state: &AppState,
+    automatic: bool,
+) -> Result<TrainingResult, String> {
+    let job = start_job(state)?;
+
+    // 训练期间可能切换配置文件，结果保存到开始训练时的配置文件中
+    let pool = state.pool();
+    let config = state.config();
+    let current = config.get();
+    let result = async {
+        let review_count = count_reviews(&pool).await?;
+        let result = optimize_fsrs_parameters(
+            &pool,
+            &current.fsrs_params,
+            current.exclude_filtered_reviews,
+            job.abort(),
+            move |current, total| {
+                let _ = app.emit(TRAINING_PROGRESS_EVENT, JobProgress { current, total });
+            },
+        )
+        .await?;
+        Ok::<_, sqlx::Error>((review_count, result))
+    }
+    .await;
+    drop(job);
+    let (review_count, mut result) = result.map_err(|e| e.to_string())?;
+
+    if result.improved {
+        save_fsrs_params(
+            &pool,
+            &config,
+            &result.candidate_params,
+            ParamsSource::Trained,
+            Some(result.dataset.review_count),
+            result.candidate,
+            None,
+        )
+        .await?;
+        result.applied = true;
+    }
+    if result.skipped.is_none() {
+        let run = OptimizationRun {
+            trained_at: Utc::now(),
+            review_count,
+            automatic,
+            applied: result.applied,
+            candidate_params: result.candidate_params.clone(),
+            candidate: result.candidate,
+            dataset_review_count: result.dataset.review_count,
+        };
+        config.update(|config| config.last_optimization = Some(run))?;
+    }
+
+    Ok(result)
+}
+
+/// 登记可以取消的后台任务
+///
+/// FSRS训练、重新安排复习时间和重新计算记忆状态共用同一个取消标记，同一时间只能进行其中一个，
+/// 都可以通过 `cancel_fsrs_training` 取消。返回的 `JobGuard` 被释放时任务结束
+fn start_job(state: &AppState) -> Result<JobGuard<'_>, String> {
+    let mut running = state.training.lock().unwrap();
+    if running.is_some() {
+        return Err("正在训练FSRS模型或重新计算卡片，请等待完成或取消后再试".to_string());
+    }
+    let abort = running.insert(CombinedProgressState::new_shared()).clone();
+    Ok(JobGuard {
+        slot: &state.training,
+        abort,
+    })
+}
+
+/// 正在进行的后台任务
+///
+/// 被释放时将 `state.training` 重置为 None，任务出错、panic 或被丢弃时也能登记新的任务
+struct JobGuard<'a> {
+    slot: &'a Mutex<Option<Arc<Mutex<CombinedProgressState>>>>,
+    abort: Arc<Mutex<CombinedProgressState>>,
+}
+
+impl JobGuard<'_> {
+    /// 任务的取消标记
+    fn abort(&self) -> Arc<Mutex<CombinedProgressState>> {
+        self.abort.clone()
+    }
+}
+
+impl Drop for JobGuard<'_> {
+    fn drop(&mut self) {
+        // 任务 panic 时锁可能已中毒，仍然需要清除
+        let mut running = self.slot.lock().unwrap_or_else(|e| e.into_inner());
+        *running = None;
+    }
+}
 
-    // 调用controller中的训练函数，传入当前参数作为初始值
-    let optimized_parameters = train_fsrs_parameters(&state.pool, current_params)
+/// 按设置自动重新训练FSRS模型
+///
+/// 启动时在后台调用，开启自动训练且距上次训练新增的复习数量或经过的天数达到间隔时训练，
+/// 完成后发送 auto-optimize-finished 事件，事件内容为训练结果
+pub async fn auto_optimize_fsrs(app: tauri::AppHandle) -> Result<(), String> {
+    let state = app.state::<AppState>();
+    let review_count = count_reviews(&state.pool())
         .await
         .map_err(|e| e.to_string())?;
+    if !state
+        .config()
+        .get()
+        .optimization_due(review_count, Utc::now())
+    {
+        return Ok(());
+    }
 
-    // 更新应用状态中的FSRS参数
-    if !optimized_parameters.is_empty() {
-        // 获取fsrs_params的锁
-        let mut fsrs_params = state.fsrs_params.lock().unwrap();
+    let result = run_fsrs_training(app.clone(), &state, true).await?;
+    let _ = app.emit(AUTO_OPTIMIZE_FINISHED_EVENT, &result);
+    Ok(())
+}
 
-        // 复制优化后的参数到数组中
-        for (i, &param) in optimized_parameters.iter().enumerate() {
-            if i < fsrs_params.len() {
-                fsrs_params[i] = param;
+/// 取消FSRS模型训练、重新安排复习时间或重新计算记忆状态
+///
+/// 返回是否有正在进行的任务，被取消的训练返回的结果中 `skipped` 为 cancelled，
+/// 被取消的重新计算返回的结果中 `cancelled` 为 true，且不修改任何卡片
+#[tauri::command]
+pub async fn cancel_fsrs_training(state: tauri::State<'_, AppState>) -> Result<bool, String> {
+    Ok(state.cancel_training())
+}
+
+/// 保存FSRS模型参数并记录到参数历史
+///
+/// 第一次修改参数时先记录修改前的参数
+async fn save_fsrs_params(
+    pool: &SqlitePool,
+    config: &ConfigStore,
+    params: &[f32],
+    source: ParamsSource,
+    review_count: Option<u32>,
+    metrics: Option<ParameterMetrics>,
+    reverted_from: Option<u32>,
+) -> Result<(), String> {
+    ensure_params_history(pool, &config.get().fsrs_params)
+        .await
+        .map_err(|e| e.to_string())?;
+    config.update(|config| config.fsrs_params = params.to_vec())?;
+    add_params_history(pool, params, source, review_count, metrics, reverted_from)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// 应用训练得到的FSRS模型参数
+///
+/// 用于确认保存最近一次训练得到但没有自动应用的候选参数。候选参数和评估结果从最近一次训练的记录中读取，
+/// 校验后保存并记录到参数历史，然后将这次训练标记为已应用，返回应用的参数
+#[tauri::command]
+pub async fn apply_trained_fsrs_params(
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<f32>, String> {
+    let config = state.config();
+    let run = config
+        .get()
+        .last_optimization
+        .ok_or_else(|| "还没有训练过FSRS模型".to_string())?;
+    if run.applied {
+        return Err("最近一次训练得到的参数已经应用".to_string());
+    }
+    validate_fsrs_params(&run.candidate_params)?;
+
+    save_fsrs_params(
+        &state.pool(),
+        &config,
+        &run.candidate_params,
+        ParamsSource::Trained,
+        Some(run.dataset_review_count),
+        run.candidate,
+        None,
+    )
+    .await?;
+    config.update(|config| {
+        if let Some(last) = config.last_optimization.as_mut() {
+            if last.trained_at == run.trained_at {
+                last.applied = true;
             }
         }
-    }
+    })?;
+    Ok(run.candidate_params)
+}
 
-    Ok(optimized_parameters)
+/// 获取FSRS参数历史记录
+///
+/// 按时间从新到旧排序，第一条为当前使用的参数
+#[tauri::command]
+pub async fn get_fsrs_params_history(
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<ParamsHistoryEntry>, String> {
+    get_params_history(&state.pool())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 恢复历史记录中的FSRS模型参数
+///
+/// 恢复后添加一条新的历史记录，保留原记录的来源、复习数量和评估结果
+#[tauri::command]
+pub async fn revert_fsrs_params(
+    state: tauri::State<'_, AppState>,
+    history_id: u32,
+) -> Result<Vec<f32>, String> {
+    let pool = state.pool();
+    let entry = get_params_history_entry(&pool, history_id)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("参数历史记录 {} 不存在", history_id))?;
+    save_fsrs_params(
+        &pool,
+        &state.config(),
+        &entry.params,
+        entry.source,
+        entry.review_count,
+        entry.metrics,
+        Some(history_id),
+    )
+    .await?;
+    Ok(entry.params)
 }
 
 /// 获得FSRS模型参数
 ///
-/// 返回当前的FSRS参数
+/// 返回当前的FSRS参数，FSRS-5 为 19 个，FSRS-6 为 21 个
+#[tauri::command]
+pub async fn get_fsrs_params(state: tauri::State<'_, AppState>) -> Result<Vec<f32>, String> {
+    Ok(state.config().get().fsrs_params)
+}
+
+/// 设置FSRS模型参数
+///
+/// 用于填入已知的参数，校验参数数量和每个参数的取值范围，保存后记录到参数历史
+#[tauri::command]
+pub async fn set_fsrs_params(
+    state: tauri::State<'_, AppState>,
+    params: Vec<f32>,
+) -> Result<(), String> {
+    validate_fsrs_params(&params)?;
+    save_fsrs_params(
+        &state.pool(),
+        &state.config(),
+        &params,
+        ParamsSource::Manual,
+        None,
+        None,
+        None,
+    )
+    .await
+}
+
+/// 恢复FSRS库的默认参数
+///
+/// 保存后记录到参数历史，返回默认参数
 #[tauri::command]
-pub async fn get_fsrs_params(state: tauri::State<'_, AppState>) -> Result<[f32; 19], String> {
-    let params = state.fsrs_params.lock().unwrap();
-    Ok(params.clone())
+pub async fn reset_fsrs_params(state: tauri::State<'_, AppState>) -> Result<Vec<f32>, String> {
+    let params = default_fsrs_params();
+    save_fsrs_params(
+        &state.pool(),
+        &state.config(),
+        &params,
+        ParamsSource::Default,
+        None,
+        None,
+        None,
+    )
+    .await?;
+    Ok(params)
 }
 
 // 获得当前的记忆留存率
 #[tauri::command]
 pub async fn get_desired_retention(state: tauri::State<'_, AppState>) -> Result<f32, String> {
-    let desired_retention = state.desired_retention.lock().unwrap();
-    Ok(*desired_retention)
+    Ok(state.config().get().desired_retention)
 }
 
-// 设置记忆留存率
+// 设置记忆留存率，超出范围时返回错误，设置成功后立即保存到配置文件
 #[tauri::command]
 pub async fn set_desired_retention(
     state: tauri::State<'_, AppState>,
     retention: f32,
 ) -> Result<(), String> {
-    let mut desired_retention = state.desired_retention.lock().unwrap();
-    *desired_retention = retention;
+    state
+        .config()
+        .update(|config| config.desired_retention = retention)?;
+    Ok(())
+}
+
+/// 获得训练FSRS模型时是否排除筛选学习和临时抱佛脚的复习
+#[tauri::command]
+pub async fn get_exclude_filtered_reviews(
+    state: tauri::State<'_, AppState>,
+) -> Result<bool, String> {
+    Ok(state.config().get().exclude_filtered_reviews)
+}
+
+/// 设置训练FSRS模型时是否排除筛选学习和临时抱佛脚的复习，设置后立即保存到配置文件
+#[tauri::command]
+pub async fn set_exclude_filtered_reviews(
+    state: tauri::State<'_, AppState>,
+    exclude: bool,
+) -> Result<(), String> {
+    state
+        .config()
+        .update(|config| config.exclude_filtered_reviews = exclude)?;
+    Ok(())
+}
+
+/// 获得自动重新训练FSRS模型的设置
+#[tauri::command]
+pub async fn get_auto_optimize(state: tauri::State<'_, AppState>) -> Result<AutoOptimize, String> {
+    Ok(state.config().get().auto_optimize)
+}
+
+/// 设置自动重新训练FSRS模型，间隔为 0 时返回错误，设置成功后立即保存到配置文件
+#[tauri::command]
+pub async fn set_auto_optimize(
+    state: tauri::State<'_, AppState>,
+    settings: AutoOptimize,
+) -> Result<(), String> {
+    state
+        .config()
+        .update(|config| config.auto_optimize = settings)?;
+    Ok(())
+}
+
+/// 获得牌组预设
+#[tauri::command]
+pub async fn get_deck_presets(
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<DeckPreset>, String> {
+    Ok(state.config().get().deck_presets)
+}
+
+/// 设置牌组预设，名称为空或重复时返回错误，设置成功后立即保存到配置文件
+#[tauri::command]
+pub async fn set_deck_presets(
+    state: tauri::State<'_, AppState>,
+    presets: Vec<DeckPreset>,
+) -> Result<(), String> {
+    state
+        .config()
+        .update(|config| config.deck_presets = presets)?;
     Ok(())
 }
+
+/// 获得加载设置时被重置为默认值的配置项，设置有效时返回空列表
+#[tauri::command]
+pub async fn get_config_warnings(state: tauri::State<'_, AppState>) -> Result<Vec<String>, String> {
+    Ok(state.config().warnings())
+}
+
+/// 获得最近一次完成的FSRS模型训练，从未训练过时返回 None
+#[tauri::command]
+pub async fn get_last_optimization(
+    state: tauri::State<'_, AppState>,
+) -> Result<Option<OptimizationRun>, String> {
+    Ok(state.config().get().last_optimization)
+}
+
+/// 模拟给定记忆留存率下未来每天的工作量
+///
+/// 使用当前的FSRS参数和卡片集合中的卡片状态，模拟在后台线程中进行
+#[tauri::command]
+pub async fn simulate_retention_workload(
+    state: tauri::State<'_, AppState>,
+    options: SimulationOptions,
+    retention: f32,
+) -> Result<WorkloadSimulation, String> {
+    let fsrs_params = state.config().get().fsrs_params;
+    let input = load_simulation_input(&state.pool(), &options)
+        .await
+        .map_err(|e| e.to_string())?;
+    tokio::task::spawn_blocking(move || simulate_workload(&input, &fsrs_params, retention, 42))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+/// 推荐记忆留存率
+///
+/// 扫描一组记忆留存率，返回每个留存率的预计工作量和每记住一张卡片用时最少的留存率
+#[tauri::command]
+pub async fn recommend_desired_retention(
+    state: tauri::State<'_, AppState>,
+    options: SimulationOptions,
+) -> Result<RetentionRecommendation, String> {
+    let fsrs_params = state.config().get().fsrs_params;
+    let input = load_simulation_input(&state.pool(), &options)
+        .await
+        .map_err(|e| e.to_string())?;
+    tokio::task::spawn_blocking(move || recommend_retention(&input, &fsrs_params))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+/// 确定需要处理的牌组
+///
+/// 指定预设时使用预设中的牌组，此时 `deck_ids` 必须为空；预设不存在或没有牌组时返回错误。
+/// 返回的列表为空时表示所有牌组
+fn scope_deck_ids(
+    config: &Config,
+    deck_ids: Vec<u32>,
+    preset: Option<String>,
+) -> Result<Vec<u32>, String> {
+    let Some(name) = preset else {
+        return Ok(deck_ids);
+    };
+    if !deck_ids.is_empty() {
+        return Err("不能同时指定牌组和预设".to_string());
+    }
+    let preset = config
+        .deck_presets
+        .iter()
+        .find(|preset| preset.name == name)
+        .ok_or_else(|| format!("预设 {} 不存在", name))?;
+    if preset.deck_ids.is_empty() {
+        return Err(format!("预设 {} 中没有牌组", name));
+    }
+    Ok(preset.deck_ids.clone())
+}
+
+/// 重新安排复习时间
+///
+/// 修改FSRS参数或记忆留存率后，用当前设置重新计算卡片的记忆状态和到期时间。
+/// 指定 `preset` 时重新安排预设中的牌组，否则重新安排 `deck_ids` 中的牌组，都为空时重新安排所有卡组。
+/// 计算过程中发送 reschedule-progress 事件报告进度，可以通过 `cancel_fsrs_training` 取消，
+/// 完成后返回提前和推后的卡片数量
+#[tauri::command]
+pub async fn reschedule_all_cards(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    deck_ids: Vec<u32>,
+    preset: Option<String>,
+) -> Result<RescheduleSummary, String> {
+    let config = state.config().get();
+    let deck_ids = scope_deck_ids(&config, deck_ids, preset)?;
+    let job = start_job(&state)?;
+    reschedule_cards(
+        &state.pool(),
+        &deck_ids,
+        &config.fsrs_params,
+        config.desired_retention,
+        job.abort(),
+        move |current, total| {
+            let _ = app.emit(RESCHEDULE_PROGRESS_EVENT, JobProgress { current, total });
+        },
+    )
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// 从复习记录重新计算记忆状态
+///
+/// 用当前的FSRS参数重放复习记录，重建卡片的稳定性和难度，到期时间保持不变。
+/// 牌组的范围与 `reschedule_all_cards` 相同，计算过程中发送 recompute-progress 事件报告进度，
+/// 可以通过 `cancel_fsrs_training` 取消
+#[tauri::command]
+pub async fn recompute_card_memory_states(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    deck_ids: Vec<u32>,
+    preset: Option<String>,
+) -> Result<RecomputeSummary, String> {
+    let config = state.config().get();
+    let deck_ids = scope_deck_ids(&config, deck_ids, preset)?;
+    let job = start_job(&state)?;
+    recompute_memory_states(
+        &state.pool(),
+        &deck_ids,
+        &config.fsrs_params,
+        job.abort(),
+        move |current, total| {
+            let _ = app.emit(RECOMPUTE_PROGRESS_EVENT, JobProgress { current, total });
+        },
+    )
+    .await
+    .map_err(|e| e.to_string())
+}
@@ -0,0 +1,83 @@
+use crate::controller::statistics_controller::{
+    get_answer_time_stats, get_card_state_counts, get_daily_reviews, get_due_forecast,
+    get_memory_distributions, get_true_retention,
+};
+use crate::models::{
+    AnswerTimeStats, CardStateCounts, DailyReviewCount, DueForecast, MemoryDistributions,
+    PeriodRetention, StatisticsFilter, StatisticsPeriod,
+};
+use crate::AppState;
+
+/// 获取每日复习数量
+///
+/// 按本地日期统计每天各评分的复习次数
+#[tauri::command]
+pub async fn review_statistics(
+    state: tauri::State<'_, AppState>,
+    filter: StatisticsFilter,
+) -> Result<Vec<DailyReviewCount>, String> {
+    get_daily_reviews(&state.pool(), &filter)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 获取真实留存率
+///
+/// 按日、周或月统计间隔至少一天的复习中没有选择“重来”的比例
+#[tauri::command]
+pub async fn retention_statistics(
+    state: tauri::State<'_, AppState>,
+    filter: StatisticsFilter,
+    period: StatisticsPeriod,
+) -> Result<Vec<PeriodRetention>, String> {
+    get_true_retention(&state.pool(), &filter, period)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 获取到期预测
+///
+/// 时间范围为预测的日期范围，默认从今天开始预测 30 天
+#[tauri::command]
+pub async fn due_forecast(
+    state: tauri::State<'_, AppState>,
+    filter: StatisticsFilter,
+) -> Result<DueForecast, String> {
+    get_due_forecast(&state.pool(), &filter)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 获取稳定性、难度和可提取性分布
+#[tauri::command]
+pub async fn memory_distributions(
+    state: tauri::State<'_, AppState>,
+    filter: StatisticsFilter,
+) -> Result<MemoryDistributions, String> {
+    let fsrs_params = state.config().get().fsrs_params;
+    get_memory_distributions(&state.pool(), &filter, &fsrs_params)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 获取各状态的卡片数量
+#[tauri::command]
+pub async fn card_state_statistics(
+    state: tauri::State<'_, AppState>,
+    filter: StatisticsFilter,
+) -> Result<CardStateCounts, String> {
+    get_card_state_counts(&state.pool(), &filter)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 获取回答用时统计
+#[tauri::command]
+pub async fn answer_time_statistics(
+    state: tauri::State<'_, AppState>,
+    filter: StatisticsFilter,
+) -> Result<AnswerTimeStats, String> {
+    get_answer_time_stats(&state.pool(), &filter)
+        .await
+        .map_err(|e| e.to_string())
+}
@@ -0,0 +1,404 @@
+use crate::models::ParameterMetrics;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// 当前配置文件版本
+///
+/// 旧版本的配置在加载时依次升级到当前版本，未写版本号的 conf.json 视为版本 0
+pub const CONFIG_VERSION: u32 = 1;
+
+/// 配置文件名，位于应用配置目录下
+pub const CONFIG_FILE_NAME: &str = "settings.json";
+
+/// 无效配置文件的备份文件名后缀
+const BACKUP_SUFFIX: &str = ".bak";
+
+/// 记忆留存率的取值范围
+pub const DESIRED_RETENTION_RANGE: (f32, f32) = (0.6, 0.99);
+
+/// 早期版本使用的 FSRS-5 默认参数
+///
+/// 之前创建的配置文件中保存的是这组参数，记录参数历史时仍视为默认参数
+pub const FSRS5_DEFAULT_PARAMS: [f32; 19] = [
+    0.40255, 1.18385, 3.173, 15.69105, 7.1949, 0.5345, 1.4604, 0.0046, 1.54575, 0.1192, 1.01925,
+    1.9395, 0.11, 0.29605, 2.2698, 0.2315, 2.9898, 0.51655, 0.6621,
+];
+
+/// 支持的 FSRS 参数数量，17 个为 FSRS-4.5，19 个为 FSRS-5，21 个为 FSRS-6
+pub const FSRS_PARAM_COUNTS: [usize; 3] = [17, 19, 21];
+
+/// FSRS 每个参数的取值范围，与 FSRS 库训练时裁剪参数使用的范围一致
+pub const FSRS_PARAM_BOUNDS: [(f32, f32); 21] = [
+    (0.001, 100.0),
+    (0.001, 100.0),
+    (0.001, 100.0),
+    (0.001, 100.0),
+    (1.0, 10.0),
+    (0.001, 4.0),
+    (0.001, 4.0),
+    (0.001, 0.75),
+    (0.0, 4.5),
+    (0.0, 0.8),
+    (0.001, 3.5),
+    (0.001, 5.0),
+    (0.001, 0.25),
+    (0.001, 0.9),
+    (0.0, 4.0),
+    (0.0, 1.0),
+    (1.0, 6.0),
+    (0.0, 2.0),
+    (0.0, 2.0),
+    (0.0, 0.8),
+    (0.1, 0.8),
+];
+
+/// FSRS 库的默认参数
+pub fn default_fsrs_params() -> Vec<f32> {
+    fsrs::DEFAULT_PARAMETERS.to_vec()
+}
+
+/// 校验 FSRS 参数的数量和每个参数的取值范围
+///
+/// 返回第一个不合法的参数对应的错误信息
+pub fn validate_fsrs_params(params: &[f32]) -> Result<(), String> {
+    if !FSRS_PARAM_COUNTS.contains(&params.len()) {
+        return Err(format!(
+            "FSRS 参数数量必须为 17、19 或 21，实际为 {}",
+            params.len()
+        ));
+    }
+    for (index, (&param, (min, max))) in params.iter().zip(FSRS_PARAM_BOUNDS).enumerate() {
+        if !(min..=max).contains(&param) {
+            return Err(format!(
+                "FSRS 参数 w{} 必须在 {} 到 {} 之间，实际为 {}",
+                index, min, max, param
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// 自动重新训练FSRS模型的设置
+///
+/// 开启后在启动时检查，距上次训练新增的复习数量或经过的天数达到间隔时在后台重新训练，
+/// 只有评估结果更好时才应用新的参数
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AutoOptimize {
+    pub enabled: bool,
+    pub review_interval: u32, // 新增的复习数量
+    pub day_interval: u32,    // 经过的天数，期间至少有一次新的复习
+}
+
+impl Default for AutoOptimize {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            review_interval: 1000,
+            day_interval: 30,
+        }
+    }
+}
+
+/// 最近一次完成的FSRS模型训练
+///
+/// 同时保存训练得到的候选参数，没有自动应用的候选参数只能从这里应用
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OptimizationRun {
+    pub trained_at: DateTime<Utc>,
+    pub review_count: u32, // 训练时卡片集合中的复习数量
+    pub automatic: bool,
+    pub applied: bool,
+    #[serde(default)]
+    pub candidate_params: Vec<f32>,
+    #[serde(default)]
+    pub candidate: Option<ParameterMetrics>, // 候选参数的评估结果
+    #[serde(default)]
+    pub dataset_review_count: u32, // 训练使用的复习数量
+}
+
+/// 牌组预设
+///
+/// 命名的一组牌组，重新安排复习时间和重新计算记忆状态时可以按预设选择牌组
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DeckPreset {
+    pub name: String,
+    pub deck_ids: Vec<u32>,
+}
+
+/// 应用配置
+///
+/// 缺失的字段使用默认值，因此新增配置项不需要升级配置文件版本
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub version: u32,
+    pub database_url: String,
+    pub fsrs_params: Vec<f32>,
+    pub desired_retention: f32,
+    pub exclude_filtered_reviews: bool, // 训练FSRS模型时排除筛选学习和临时抱佛脚的复习
+    pub auto_optimize: AutoOptimize,
+    pub last_optimization: Option<OptimizationRun>,
+    pub deck_presets: Vec<DeckPreset>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            version: CONFIG_VERSION,
+            // 配置文件管理加载设置时会替换为配置文件目录中的数据库
+            database_url: "sqlite://db/echocard.db".to_string(),
+            fsrs_params: default_fsrs_params(),
+            desired_retention: 0.9,
+            exclude_filtered_reviews: true,
+            auto_optimize: AutoOptimize::default(),
+            last_optimization: None,
+            deck_presets: Vec::new(),
+        }
+    }
+}
+
+impl Config {
+    /// 校验配置
+    ///
+    /// 返回第一个不合法的配置项对应的错误信息
+    pub fn validate(&self) -> Result<(), String> {
+        if self.version != CONFIG_VERSION {
+            return Err(format!("不支持的配置版本 {}", self.version));
+        }
+        if !self.database_url.starts_with("sqlite://") {
+            return Err(format!(
+                "数据库地址 {} 必须以 sqlite:// 开头",
+                self.database_url
+            ));
+        }
+        let (min_retention, max_retention) = DESIRED_RETENTION_RANGE;
+        if !(min_retention..=max_retention).contains(&self.desired_retention) {
+            return Err(format!(
+                "记忆留存率必须在 {} 到 {} 之间",
+                min_retention, max_retention
+            ));
+        }
+        // 只检查数量和有效数值，不检查取值范围，避免已保存的配置因范围调整而无法加载
+        if !FSRS_PARAM_COUNTS.contains(&self.fsrs_params.len()) {
+            return Err(format!(
+                "FSRS 参数数量必须为 17、19 或 21，实际为 {}",
+                self.fsrs_params.len()
+            ));
+        }
+        if self.auto_optimize.review_interval == 0 || self.auto_optimize.day_interval == 0 {
+            return Err("自动训练的间隔必须大于 0".to_string());
+        }
+        if let Some(index) = self.fsrs_params.iter().position(|param| !param.is_finite()) {
+            return Err(format!("FSRS 参数 w{} 不是有效的数值", index));
+        }
+        for (index, preset) in self.deck_presets.iter().enumerate() {
+            if preset.name.trim().is_empty() {
+                return Err("预设名称不能为空".to_string());
+            }
+            if self.deck_presets[..index]
+                .iter()
+                .any(|other| other.name == preset.name)
+            {
+                return Err(format!("预设 {} 重复", preset.name));
+            }
+        }
+        Ok(())
+    }
+
+    /// 是否需要自动重新训练FSRS模型
+    ///
+    /// `review_count` 为卡片集合中当前的复习数量。从未训练过时，复习数量达到间隔后训练
+    pub fn optimization_due(&self, review_count: u32, now: DateTime<Utc>) -> bool {
+        if !self.auto_optimize.enabled {
+            return false;
+        }
+        let (last_count, last_trained_at) = match &self.last_optimization {
+            Some(run) => (run.review_count, Some(run.trained_at)),
+            None => (0, None),
+        };
+        let new_reviews = review_count.saturating_sub(last_count);
+        let days = last_trained_at.map_or(0, |trained_at| (now - trained_at).num_days());
+        new_reviews >= self.auto_optimize.review_interval
+            || (new_reviews > 0 && days >= self.auto_optimize.day_interval as i64)
+    }
+
+    /// 将旧版本配置升级到当前版本
+    fn upgrade(mut self) -> Self {
+        // 版本 0：conf.json 中的配置，字段与版本 1 相同
+        if self.version == 0 {
+            self.version = 1;
+        }
+        self
+    }
+}
+
+/// 配置存储
+///
+/// 配置只通过这里读取和修改，每次修改都会立即以原子方式写入配置文件
+pub struct ConfigStore {
+    path: PathBuf,
+    config: Mutex<Config>,
+    warnings: Vec<String>, // 加载时被重置为默认值的配置项
+}
+
+impl ConfigStore {
+    /// 加载配置
+    ///
+    /// 配置文件不存在时依次尝试旧版配置文件 `legacy_path` 和默认配置 `default`，并立即写入配置文件。
+    /// 配置文件或旧版配置文件中无效的配置项重置为 `default` 中的值，其余配置项保留，
+    /// 无效的配置文件先备份为 settings.json.bak。被重置的配置项可以通过 `warnings` 获取
+    pub fn load(
+        path: PathBuf,
+        legacy_path: Option<&Path>,
+        default: Config,
+    ) -> Result<Self, String> {
+        let mut warnings = Vec::new();
+        let (config, changed) = match fs::read_to_string(&path) {
+            Ok(json) => match Self::parse(&json) {
+                Ok(config) => (config, false),
+                Err(_) => {
+                    let mut backup_name = path.file_name().unwrap_or_default().to_os_string();
+                    backup_name.push(BACKUP_SUFFIX);
+                    let backup_path = path.with_file_name(backup_name);
+                    fs::copy(&path, &backup_path)
+                        .map_err(|e| format!("备份配置文件失败: {}", e))?;
+                    warnings.push(format!("配置文件无效，已备份为 {}", backup_path.display()));
+                    (Self::recover(&json, default, &mut warnings), true)
+                }
+            },
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                let legacy =
+                    legacy_path.and_then(|legacy_path| fs::read_to_string(legacy_path).ok());
+                let config = match legacy {
+                    Some(json) => {
+                        println!("已从旧版配置文件迁移配置");
+                        Self::recover(&json, default, &mut warnings)
+                    }
+                    None => default,
+                };
+                (config, true)
+            }
+            Err(e) => return Err(format!("读取配置文件失败: {}", e)),
+        };
+        for warning in &warnings {
+            println!("{}", warning);
+        }
+
+        let store = Self {
+            path,
+            config: Mutex::new(config.clone()),
+            warnings,
+        };
+        if changed {
+            store.save(&config)?;
+        }
+        Ok(store)
+    }
+
+    /// 从无效的配置中恢复
+    ///
+    /// 按当前版本的字段逐项读取，无法解析或校验不通过的配置项重置为 `default` 中的值并记录到
+    /// `warnings`，整个文件无法解析时使用 `default`
+    fn recover(json: &str, default: Config, warnings: &mut Vec<String>) -> Config {
+        let object = match serde_json::from_str::<serde_json::Value>(json) {
+            Ok(serde_json::Value::Object(object)) => object,
+            Ok(_) => {
+                warnings.push("配置文件不是 JSON 对象，已使用默认配置".to_string());
+                return default;
+            }
+            Err(e) => {
+                warnings.push(format!("解析配置文件失败，已使用默认配置: {}", e));
+                return default;
+            }
+        };
+
+        let mut config = default;
+        for (key, value) in object {
+            if key == "version" {
+                continue;
+            }
+            let mut candidate = serde_json::to_value(&config).expect("config is serializable");
+            candidate[key.as_str()] = value;
+            let result = serde_json::from_value::<Config>(candidate)
+                .map_err(|e| e.to_string())
+                .and_then(|candidate| candidate.validate().map(|_| candidate));
+            match result {
+                Ok(candidate) => config = candidate,
+                Err(e) => warnings.push(format!("配置项 {} 无效，已重置为默认值: {}", key, e)),
+            }
+        }
+        config
+    }
+
+    fn parse(json: &str) -> Result<Config, String> {
+        let mut config: serde_json::Value =
+            serde_json::from_str(json).map_err(|e| format!("解析配置文件失败: {}", e))?;
+        // 旧版配置没有版本号，缺失时不能使用默认的当前版本
+        if let Some(object) = config.as_object_mut() {
+            object.entry("version").or_insert(0.into());
+        }
+        let config = serde_json::from_value::<Config>(config)
+            .map_err(|e| format!("解析配置文件失败: {}", e))?
+            .upgrade();
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// 获取当前配置
+    pub fn get(&self) -> Config {
+        self.config.lock().unwrap().clone()
+    }
+
+    /// 获取加载配置时发现的问题，没有问题时为空
+    pub fn warnings(&self) -> Vec<String> {
+        self.warnings.clone()
+    }
+
+    /// 修改配置
+    ///
+    /// 修改后的配置通过校验并成功写入文件后才会生效，返回修改后的配置
+    pub fn update(&self, modify: impl FnOnce(&mut Config)) -> Result<Config, String> {
+        let mut config = self.config.lock().unwrap();
+        let mut updated = config.clone();
+        modify(&mut updated);
+        updated.validate()?;
+        self.save(&updated)?;
+        *config = updated.clone();
+        Ok(updated)
+    }
+
+    /// 写入配置文件
+    fn save(&self, config: &Config) -> Result<(), String> {
+        let json =
+            serde_json::to_string_pretty(config).map_err(|e| format!("序列化配置失败: {}", e))?;
+        write_atomic(&self.path, json.as_bytes()).map_err(|e| format!("写入配置文件失败: {}", e))
+    }
+}
+
+/// 以原子方式写入文件
+///
+/// 先写入同目录下的临时文件并同步到磁盘，再重命名覆盖目标文件，
+/// 写入过程中崩溃不会留下不完整的文件
+pub fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    let mut temp_name = path.file_name().unwrap_or_default().to_os_string();
+    temp_name.push(".tmp");
+    let temp_path = path.with_file_name(temp_name);
+    let write = || -> io::Result<()> {
+        let mut file = fs::File::create(&temp_path)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+        fs::rename(&temp_path, path)
+    };
+    write().inspect_err(|_| {
+        let _ = fs::remove_file(&temp_path);
+    })
+}
@@ -1,4 +1,32 @@
+pub mod browser_controller;
 pub mod card_controller;
 pub mod deck_controller;
+pub mod fsrs_params_controller;
+pub mod media_controller;
+pub mod queue_controller;
+pub mod render_controller;
 pub mod review_controller;
+pub mod schedule_controller;
+pub mod session_controller;
+pub mod simulation_controller;
+pub mod statistics_controller;
 pub mod template_controller;
+
+use std::io::ErrorKind;
+
+/// 构造输入校验错误
+///
+/// 控制器统一返回 sqlx::Result，输入不合法时用 InvalidInput 类型的 IO 错误表示
+pub fn invalid_input(message: impl Into<String>) -> sqlx::Error {
+    sqlx::Error::Io(std::io::Error::new(ErrorKind::InvalidInput, message.into()))
+}
+
+/// 将控制器返回的错误转换为前端展示的错误信息
+///
+/// 输入校验错误只保留校验信息本身，其余错误保持原样
+pub fn error_message(e: sqlx::Error) -> String {
+    match e {
+        sqlx::Error::Io(e) if e.kind() == ErrorKind::InvalidInput => e.to_string(),
+        e => e.to_string(),
+    }
+}
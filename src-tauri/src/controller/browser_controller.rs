@@ -0,0 +1,350 @@
+use crate::controller::card_controller::card_filter_conditions;
+use crate::controller::invalid_input;
+use crate::controller::review_controller::{current_retrievability, fsrs_decay};
+use crate::models::{
+    CardBrowserCursor, CardBrowserPage, CardBrowserQuery, CardBrowserRow, CardBrowserSort,
+    CardBrowserSortKey,
+};
+use chrono::{DateTime, Local, Utc};
+use sqlx::{Result, SqlitePool};
+use std::collections::HashMap;
+
+/// 每页卡片数量的默认值
+pub const DEFAULT_BROWSER_PAGE_SIZE: u32 = 50;
+
+/// 每页卡片数量的上限
+pub const MAX_BROWSER_PAGE_SIZE: u32 = 500;
+
+/// 卡片的第一个字段，用于列表显示，不读取其余字段
+const SORT_FIELD: &str =
+    "substr(c.template_fields, 1, instr(c.template_fields || char(31), char(31)) - 1)";
+
+/// 卡片的遗忘次数，即第一次复习之后评分为忘记的次数
+const LAPSES: &str = "(
+    SELECT COUNT(*) FROM reviews r
+    WHERE r.card_id = c.card_id AND r.rating = 1 AND r.review_date > (
+        SELECT MIN(r2.review_date) FROM reviews r2 WHERE r2.card_id = c.card_id
+    )
+)";
+
+/// 按遗忘次数排序时使用的各卡片遗忘次数，复习记录只汇总一次，没有遗忘的卡片不在其中
+const LAPSE_COUNTS: &str = "
+    SELECT r.card_id, COUNT(*) AS lapses
+    FROM reviews r
+    JOIN (
+        SELECT card_id, MIN(review_date) AS first_review FROM reviews GROUP BY card_id
+    ) f ON f.card_id = r.card_id
+    WHERE r.rating = 1 AND r.review_date > f.first_review
+    GROUP BY r.card_id
+";
+
+/// 上次复习至今的天数与稳定性之比，比值越大可提取性越低，新卡片为 NULL。
+/// 查询时间 n.now_day 由查询绑定，与计算可提取性使用的时间相同
+const ELAPSED_STABILITY_RATIO: &str = "(CASE
+    WHEN c.last_review IS NULL OR NOT c.stability > 0 THEN NULL
+    ELSE (n.now_day - julianday(c.last_review)) / c.stability
+END)";
+
+/// 按添加顺序排序时的排序键，即卡片ID本身
+const CREATED_KEY: &str = "c.card_id";
+
+type BrowserRow = (
+    i64,
+    i64,
+    String,
+    i64,
+    String,
+    String,
+    DateTime<Utc>,
+    Option<DateTime<Utc>>,
+    Option<f32>,
+    Option<f32>,
+    Option<DateTime<Utc>>,
+    Option<f64>,
+    Option<String>,
+);
+
+/// 排序方式对应的排序键
+struct SortKey {
+    expr: &'static str,
+    nullable: bool, // 只有可能为 NULL 的排序键才加上 IS NULL 条件，以便按到期时间等排序时使用索引
+    descending: bool,
+    numeric: bool, // 数值排序键在游标中保存为数字，其余保存为文本
+}
+
+fn browser_sort_key(sort: CardBrowserSort, descending: bool) -> SortKey {
+    let (expr, nullable, descending, numeric) = match sort {
+        CardBrowserSort::Due => ("c.due", false, descending, false),
+        CardBrowserSort::Created => (CREATED_KEY, false, descending, true),
+        CardBrowserSort::Modified => ("c.modified_at", false, descending, false),
+        CardBrowserSort::Deck => ("d.name", false, descending, false),
+        CardBrowserSort::Template => ("t.name", false, descending, false),
+        CardBrowserSort::Stability => ("c.stability", true, descending, true),
+        CardBrowserSort::Difficulty => ("c.difficulty", true, descending, true),
+        // 可提取性从低到高即比值从大到小
+        CardBrowserSort::Retrievability => (ELAPSED_STABILITY_RATIO, true, !descending, true),
+        CardBrowserSort::Lapses => ("COALESCE(l.lapses, 0)", false, descending, true),
+    };
+    SortKey {
+        expr,
+        nullable,
+        descending,
+        numeric,
+    }
+}
+
+/// 排序语句
+///
+/// 没有对应数值的卡片（例如按稳定性排序时的新卡片）无论升序降序都排在最后，
+/// 数值相同时按添加顺序排列
+fn browser_order_by(key: &SortKey) -> String {
+    let direction = if key.descending { "DESC" } else { "ASC" };
+    let mut order_by = String::new();
+    if key.nullable {
+        order_by.push_str(&format!("{} IS NULL, ", key.expr));
+    }
+    if key.expr != CREATED_KEY {
+        order_by.push_str(&format!("{} {}, ", key.expr, direction));
+    }
+    order_by.push_str(&format!("c.card_id {}", direction));
+    order_by
+}
+
+/// 游标之后的卡片的条件，与排序语句的顺序一致
+///
+/// 游标卡片有排序值时，取排序值在其之后、排序值相同但卡片ID在其之后，以及没有排序值的卡片；
+/// 游标卡片没有排序值时，只取同样没有排序值且卡片ID在其之后的卡片。
+/// 游标的排序值与排序方式不符时返回输入校验错误
+fn browser_after_condition(key: &SortKey, cursor: &CardBrowserCursor) -> Result<String> {
+    let operator = if key.descending { "<" } else { ">" };
+    if key.expr == CREATED_KEY {
+        return Ok(format!("c.card_id {} ?", operator));
+    }
+    match &cursor.sort_key {
+        Some(CardBrowserSortKey::Number(_)) if key.numeric => {}
+        Some(CardBrowserSortKey::Text(_)) if !key.numeric => {}
+        None if key.nullable => {
+            return Ok(format!(
+                "({} IS NULL AND c.card_id {} ?)",
+                key.expr, operator
+            ));
+        }
+        _ => return Err(invalid_input("翻页位置与排序方式不一致，请回到第一页")),
+    }
+    // 先按排序值范围筛选，以便使用排序键上的索引
+    let condition = format!(
+        "{key} {op}= ? AND ({key} {op} ? OR c.card_id {op} ?)",
+        key = key.expr,
+        op = operator
+    );
+    Ok(if key.nullable {
+        format!("({} IS NULL OR ({}))", key.expr, condition)
+    } else {
+        format!("({})", condition)
+    })
+}
+
+/// 获取当前页卡片的遗忘次数，以卡片ID为键
+async fn get_lapses(pool: &SqlitePool, card_ids: &[u32]) -> Result<HashMap<u32, u32>> {
+    if card_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let placeholders = card_ids.iter().map(|_| "?").collect::<Vec<_>>().join(", ");
+    let query = format!(
+        "SELECT c.card_id, {} FROM cards c WHERE c.card_id IN ({})",
+        LAPSES, placeholders
+    );
+    let mut query_builder = sqlx::query_as::<_, (i64, i64)>(&query);
+    for card_id in card_ids {
+        query_builder = query_builder.bind(card_id);
+    }
+    Ok(query_builder
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(|(card_id, lapses)| (card_id as u32, lapses as u32))
+        .collect())
+}
+
+/// 分页获取卡片浏览器的卡片列表
+///
+/// 按筛选条件和排序方式在数据库中排序分页，只读取列表显示需要的列，
+/// 遗忘次数只对当前页的卡片计算，同时返回符合条件的卡片总数。`page_size` 为 0 时使用默认值，超过上限时按上限处理。
+/// 设置 `after` 时从游标卡片之后开始取卡片，不再跳过前面的页，查询时间使用游标中第一页的查询时间；
+/// 返回的 `next_cursor` 用于获取下一页
+pub async fn browse_cards(
+    pool: &SqlitePool,
+    query: &CardBrowserQuery,
+    fsrs_params: &[f32],
+    now: DateTime<Utc>,
+) -> Result<CardBrowserPage> {
+    let now = query.after.as_ref().map_or(now, |cursor| cursor.queried_at);
+    let page_size = match query.page_size {
+        0 => DEFAULT_BROWSER_PAGE_SIZE,
+        page_size => page_size.min(MAX_BROWSER_PAGE_SIZE),
+    };
+    let (where_clause, params, date_params) = card_filter_conditions(
+        &query.template_ids,
+        &query.deck_ids,
+        query.status_bit_filter,
+    );
+
+    let count_query = format!("SELECT COUNT(*) FROM cards c{}", where_clause);
+    let mut count_builder = sqlx::query_as::<_, (i64,)>(&count_query);
+    for param in &params {
+        count_builder = count_builder.bind(param);
+    }
+    for date_param in &date_params {
+        count_builder = count_builder.bind(date_param);
+    }
+    let (total,) = count_builder.fetch_one(pool).await?;
+
+    // 只有按遗忘次数排序时才汇总所有卡片的遗忘次数
+    let lapses_join = match query.sort {
+        CardBrowserSort::Lapses => {
+            format!("LEFT JOIN ({}) l ON l.card_id = c.card_id", LAPSE_COUNTS)
+        }
+        _ => String::new(),
+    };
+    let key = browser_sort_key(query.sort, query.descending);
+    let after_condition = match &query.after {
+        Some(cursor) => {
+            let condition = browser_after_condition(&key, cursor)?;
+            let keyword = if where_clause.is_empty() {
+                "WHERE"
+            } else {
+                "AND"
+            };
+            format!("{} {}", keyword, condition)
+        }
+        None => String::new(),
+    };
+    // 游标中的排序值为查询得到的原始值，数值和文本分别放在两列中
+    let (number_key, text_key) = match (key.expr, key.numeric) {
+        (CREATED_KEY, _) => ("NULL".to_string(), "NULL".to_string()),
+        (expr, true) => (format!("CAST({} AS REAL)", expr), "NULL".to_string()),
+        (expr, false) => ("NULL".to_string(), format!("CAST({} AS TEXT)", expr)),
+    };
+    let rows_query = format!(
+        "
+        SELECT
+            c.card_id, c.deck_id, d.name, c.template_id, t.name, {},
+            c.due, c.modified_at, c.stability, c.difficulty, c.last_review, {}, {}
+        FROM cards c
+        JOIN decks d ON d.deck_id = c.deck_id
+        JOIN templates t ON t.template_id = c.template_id
+        CROSS JOIN (SELECT julianday(?) AS now_day) n
+        {}
+        {}
+        {}
+        ORDER BY {}
+        LIMIT ? OFFSET ?
+        ",
+        SORT_FIELD,
+        number_key,
+        text_key,
+        lapses_join,
+        where_clause,
+        after_condition,
+        browser_order_by(&key)
+    );
+    let mut rows_builder = sqlx::query_as::<_, BrowserRow>(&rows_query).bind(now);
+    for param in params {
+        rows_builder = rows_builder.bind(param);
+    }
+    for date_param in date_params {
+        rows_builder = rows_builder.bind(date_param);
+    }
+    if let Some(cursor) = &query.after {
+        match &cursor.sort_key {
+            Some(_) if key.expr == CREATED_KEY => {}
+            Some(CardBrowserSortKey::Number(value)) => {
+                rows_builder = rows_builder.bind(*value).bind(*value);
+            }
+            Some(CardBrowserSortKey::Text(value)) => {
+                rows_builder = rows_builder.bind(value.clone()).bind(value.clone());
+            }
+            None => {}
+        }
+        rows_builder = rows_builder.bind(cursor.card_id as i64);
+    }
+    let offset = match query.after {
+        Some(_) => 0,
+        None => query.page as i64 * page_size as i64,
+    };
+    let rows = rows_builder
+        .bind(page_size as i64)
+        .bind(offset)
+        .fetch_all(pool)
+        .await?;
+    let next_cursor = match rows.last() {
+        Some(last) if rows.len() == page_size as usize => Some(CardBrowserCursor {
+            sort_key: match (last.11, &last.12) {
+                (Some(value), _) => Some(CardBrowserSortKey::Number(value)),
+                (None, Some(value)) => Some(CardBrowserSortKey::Text(value.clone())),
+                (None, None) => None,
+            },
+            card_id: last.0 as u32,
+            queried_at: now,
+        }),
+        _ => None,
+    };
+    let card_ids: Vec<u32> = rows.iter().map(|row| row.0 as u32).collect();
+    let lapses = get_lapses(pool, &card_ids).await?;
+
+    let decay = fsrs_decay(fsrs_params);
+    let today = now.with_timezone(&Local).date_naive();
+    let rows = rows
+        .into_iter()
+        .map(
+            |(
+                card_id,
+                deck_id,
+                deck_name,
+                template_id,
+                template_name,
+                sort_field,
+                due,
+                modified_at,
+                stability,
+                difficulty,
+                last_review,
+                _,
+                _,
+            )| {
+                let retrievability = match (last_review, stability) {
+                    (Some(last_review), Some(stability)) if stability > 0.0 => {
+                        let elapsed_days = (now - last_review).num_seconds() as f32 / 86400.0;
+                        Some(current_retrievability(stability, elapsed_days, decay))
+                    }
+                    _ => None,
+                };
+                let days_overdue = last_review
+                    .map(|_| (today - due.with_timezone(&Local).date_naive()).num_days());
+                CardBrowserRow {
+                    card_id: card_id as u32,
+                    deck_id: deck_id as u32,
+                    deck_name,
+                    template_id: template_id as u32,
+                    template_name,
+                    sort_field,
+                    due,
+                    modified_at: modified_at.unwrap_or(due),
+                    stability,
+                    difficulty,
+                    retrievability,
+                    days_overdue,
+                    lapses: lapses.get(&(card_id as u32)).copied().unwrap_or(0),
+                }
+            },
+        )
+        .collect();
+
+    Ok(CardBrowserPage {
+        rows,
+        total: total as u32,
+        page: query.page,
+        page_size,
+        next_cursor,
+    })
+}
@@ -1,33 +1,47 @@
-use crate::models::Card;
+use crate::controller::review_controller::{current_retrievability, fsrs_decay};
+use crate::controller::template_controller::validate_card_fields;
+use crate::models::{Card, CardSortOrder};
 use chrono::{DateTime, Local, Utc};
-use sqlx::{Result, SqlitePool};
+use sqlx::{Result, SqliteExecutor, SqlitePool};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+/// 按ID查询卡片时每批的卡片数
+const CARD_BATCH_SIZE: usize = 500;
 
 pub fn merge_template_fields(fields: Vec<String>) -> String {
     fields.join("\u{001F}")
 }
 
+pub fn split_template_fields(fields: &str) -> Vec<String> {
+    fields.split('\u{001F}').map(|s| s.to_string()).collect()
+}
+
 /// 添加新卡片
 ///
-/// 将新卡片添加到指定牌组，使用指定的模板和字段内容
+/// 将新卡片添加到指定牌组，使用指定的模板和字段内容，字段内容需通过模板字段的校验
 pub async fn create_card(
     pool: &SqlitePool,
     deck_id: u32,
     template_id: u32,
     template_fields: Vec<String>,
 ) -> Result<u32> {
+    validate_card_fields(pool, template_id, &template_fields).await?;
+
     let merged_fields = merge_template_fields(template_fields);
     let due = Utc::now();
 
-    let card_id = sqlx::query!(
-        "INSERT INTO cards 
-        (deck_id, template_id, template_fields, due, 
-        stability, difficulty, scheduled_days, last_review) 
-        VALUES (?, ?, ?, ?, NULL, NULL, 0, NULL)",
-        deck_id,
-        template_id,
-        merged_fields,
-        due,
+    let card_id = sqlx::query(
+        "INSERT INTO cards
+        (deck_id, template_id, template_fields, due,
+        stability, difficulty, scheduled_days, last_review, modified_at)
+        VALUES (?, ?, ?, ?, NULL, NULL, 0, NULL, ?)",
     )
+    .bind(deck_id)
+    .bind(template_id)
+    .bind(merged_fields)
+    .bind(due)
+    .bind(due)
     .execute(pool)
     .await?
     .last_insert_rowid() as u32;
@@ -35,54 +49,78 @@ pub async fn create_card(
     Ok(card_id)
 }
 
-// 分页获取卡片
-pub async fn get_cards_by_page(
-    pool: &SqlitePool,
-    deck_id: u32,
-    page_size: u32,
-) -> Result<Vec<Card>> {
-    let sql = "
-      SELECT 
-          c.card_id, c.deck_id, c.template_id, c.template_fields,
-          c.due, c.stability, c.difficulty, c.scheduled_days, c.last_review
-      FROM cards c
-      WHERE c.deck_id = ? AND c.due <= ?
-      ORDER BY c.due
-      LIMIT ?
-    ";
-
-    // 获取当地时间的今天日期范围（结束）
-    let today_local = Local::now();
-    let today_end_local = today_local.date_naive().and_hms_opt(23, 59, 59).unwrap();
-    // 转换为UTC时间用于数据库查询
-    let today_end_utc = today_end_local
-        .and_local_timezone(Local)
-        .unwrap()
-        .with_timezone(&Utc);
+/// 计算卡片的可提取性和逾期天数
+///
+/// 可提取性按上次复习到 `now` 的实际时间计算，逾期天数按本地日期计算，
+/// 未复习过的新卡片两者均为 None
+pub fn annotate_cards(cards: &mut [Card], fsrs_params: &[f32], now: DateTime<Utc>) {
+    let decay = fsrs_decay(fsrs_params);
+    let today = now.with_timezone(&Local).date_naive();
+    for card in cards.iter_mut() {
+        let Some(last_review) = card.last_review else {
+            card.retrievability = None;
+            card.days_overdue = None;
+            continue;
+        };
+        card.retrievability =
+            card.memory_state
+                .filter(|state| state.stability > 0.0)
+                .map(|state| {
+                    let elapsed_days = (now - last_review).num_seconds() as f32 / 86400.0;
+                    current_retrievability(state.stability, elapsed_days, decay)
+                });
+        let due_day = card.due.with_timezone(&Local).date_naive();
+        card.days_overdue = Some((today - due_day).num_days());
+    }
+}
 
-    // 使用sqlx查询数据库
-    let rows = sqlx::query_as::<
-        _,
-        (
-            i64,
-            i64,
-            i64,
-            String,
-            String,
-            Option<f32>,
-            Option<f32>,
-            i64,
-            Option<String>,
-        ),
-    >(sql)
-    .bind(deck_id as i64)
-    .bind(today_end_utc)
-    .bind(page_size as i64)
-    .fetch_all(pool)
-    .await?;
+/// 按指定顺序排序卡片
+///
+/// 按可提取性或逾期天数排序时需先调用 `annotate_cards`，缺少对应数值的卡片排在最后，
+/// 数值相同时按到期时间排序
+pub fn sort_cards(cards: &mut [Card], order: CardSortOrder) {
+    match order {
+        CardSortOrder::Due => cards.sort_by_key(|card| card.due),
+        CardSortOrder::RetrievabilityAscending => cards.sort_by(|a, b| {
+            match (a.retrievability, b.retrievability) {
+                (Some(x), Some(y)) => x.total_cmp(&y),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            }
+            .then(a.due.cmp(&b.due))
+        }),
+        CardSortOrder::DaysOverdue => cards.sort_by(|a, b| {
+            match (a.days_overdue, b.days_overdue) {
+                (Some(x), Some(y)) => y.cmp(&x),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            }
+            .then(a.due.cmp(&b.due))
+        }),
+    }
+}
 
-    let mut cards = Vec::new();
-    for (
+/// 卡片查询结果的一行
+///
+/// 依次为 card_id, deck_id, template_id, template_fields, due, stability, difficulty,
+/// scheduled_days, last_review
+pub type CardRow = (
+    i64,
+    i64,
+    i64,
+    String,
+    String,
+    Option<f32>,
+    Option<f32>,
+    i64,
+    Option<String>,
+);
+
+/// 将查询结果的一行转换为卡片
+pub fn card_from_row(row: CardRow) -> Result<Card> {
+    let (
         card_id,
         deck_id,
         template_id,
@@ -92,53 +130,79 @@ pub async fn get_cards_by_page(
         difficulty,
         scheduled_days,
         last_review_str,
-    ) in rows
-    {
-        // 解析模板字段，使用Unicode分隔符分割
-        let template_fields_content = template_fields
-            .split('\u{001F}')
-            .map(|s| s.to_string())
-            .collect();
-
-        // 解析日期时间字段
-        let due = chrono::DateTime::parse_from_rfc3339(&due_str)
-            .map_err(|_| sqlx::Error::RowNotFound)?
-            .with_timezone(&chrono::Utc);
-
-        // 解析可能为NULL的last_review字段
-        let last_review = if let Some(lr_str) = last_review_str {
-            Some(
-                chrono::DateTime::parse_from_rfc3339(&lr_str)
-                    .map_err(|_| sqlx::Error::RowNotFound)?
-                    .with_timezone(&chrono::Utc),
-            )
-        } else {
-            None
-        };
+    ) = row;
+
+    // 解析模板字段，使用Unicode分隔符分割
+    let template_fields_content = split_template_fields(&template_fields);
+
+    // 解析日期时间字段
+    let due = chrono::DateTime::parse_from_rfc3339(&due_str)
+        .map_err(|_| sqlx::Error::RowNotFound)?
+        .with_timezone(&chrono::Utc);
+
+    // 解析可能为NULL的last_review字段
+    let last_review = if let Some(lr_str) = last_review_str {
+        Some(
+            chrono::DateTime::parse_from_rfc3339(&lr_str)
+                .map_err(|_| sqlx::Error::RowNotFound)?
+                .with_timezone(&chrono::Utc),
+        )
+    } else {
+        None
+    };
 
-        // 构建内存状态
-        let memory_state = if let (Some(s), Some(d)) = (stability, difficulty) {
-            Some(fsrs::MemoryState {
-                stability: s,
-                difficulty: d,
-            })
-        } else {
-            None
-        };
+    // 构建内存状态
+    let memory_state = if let (Some(s), Some(d)) = (stability, difficulty) {
+        Some(fsrs::MemoryState {
+            stability: s,
+            difficulty: d,
+        })
+    } else {
+        None
+    };
 
-        cards.push(Card {
-            card_id: card_id as u32,
-            deck_id: deck_id as u32,
-            template_id: template_id as u32,
-            template_fields_content,
-            due,
-            memory_state,
-            scheduled_days: scheduled_days as u32,
-            last_review,
-        });
-    }
+    Ok(Card {
+        card_id: card_id as u32,
+        deck_id: deck_id as u32,
+        template_id: template_id as u32,
+        template_fields_content,
+        due,
+        memory_state,
+        scheduled_days: scheduled_days as u32,
+        last_review,
+        retrievability: None,
+        days_overdue: None,
+    })
+}
+
+/// 按卡片ID获取卡片
+///
+/// 返回的卡片与 `card_ids` 的顺序一致，不存在的卡片被忽略。
+/// 卡片ID分批查询，避免超过 SQLite 的参数数量限制
+pub async fn get_cards_by_ids(pool: &SqlitePool, card_ids: &[u32]) -> Result<Vec<Card>> {
+    let mut cards: HashMap<u32, Card> = HashMap::new();
+    for batch in card_ids.chunks(CARD_BATCH_SIZE) {
+        let placeholders = batch.iter().map(|_| "?").collect::<Vec<_>>().join(", ");
+        let query = format!(
+            "SELECT card_id, deck_id, template_id, template_fields,
+                due, stability, difficulty, scheduled_days, last_review
+            FROM cards WHERE card_id IN ({})",
+            placeholders
+        );
+        let mut query_builder = sqlx::query_as::<_, CardRow>(&query);
+        for card_id in batch {
+            query_builder = query_builder.bind(card_id);
+        }
 
-    Ok(cards)
+        for row in query_builder.fetch_all(pool).await? {
+            let card = card_from_row(row)?;
+            cards.insert(card.card_id, card);
+        }
+    }
+    Ok(card_ids
+        .iter()
+        .filter_map(|card_id| cards.remove(card_id))
+        .collect())
 }
 
 pub async fn get_card_count_learned_today(pool: &SqlitePool) -> Result<u32> {
@@ -172,7 +236,7 @@ pub async fn get_card_count_learned_today(pool: &SqlitePool) -> Result<u32> {
 }
 
 pub async fn update_card_state(
-    pool: &SqlitePool,
+    executor: impl SqliteExecutor<'_>,
     card_id: u32,
     memory_state: Option<fsrs::MemoryState>,
     scheduled_days: u32,
@@ -186,43 +250,39 @@ pub async fn update_card_state(
         (None, None)
     };
 
-    sqlx::query!(
-        "UPDATE cards 
-        SET stability = ?, 
-            difficulty = ?, 
+    sqlx::query(
+        "UPDATE cards
+        SET stability = ?,
+            difficulty = ?,
             scheduled_days = ?,
             last_review = ?,
-            due = ?
+            due = ?,
+            modified_at = ?
         WHERE card_id = ?",
-        stability,
-        difficulty,
-        scheduled_days,
-        last_review,
-        due,
-        card_id
     )
-    .execute(pool)
+    .bind(stability)
+    .bind(difficulty)
+    .bind(scheduled_days)
+    .bind(last_review)
+    .bind(due)
+    .bind(Utc::now())
+    .bind(card_id)
+    .execute(executor)
     .await?;
 
     Ok(())
 }
 
-pub async fn get_card_by_filter(
-    pool: &SqlitePool,
-    template_ids: Vec<u32>,
-    deck_ids: Vec<u32>,
+/// 卡片筛选条件
+///
+/// 返回以 ` WHERE` 开头的条件语句（没有条件时为空）以及依次绑定的整数参数和日期参数，
+/// 卡片表的别名为 `c`
+pub fn card_filter_conditions(
+    template_ids: &[u32],
+    deck_ids: &[u32],
     status_bit_filter: u8, // (1 << 0): tolearn, (1 << 1): learning, (1 << 2): toreview
-) -> Result<Vec<Card>> {
-    // 构建基础查询语句
+) -> (String, Vec<i64>, Vec<DateTime<Utc>>) {
     let mut conditions = Vec::new();
-    let mut query = String::from(
-        "
-      SELECT 
-          card_id, deck_id, template_id, template_fields,
-          due, stability, difficulty, scheduled_days, last_review
-      FROM cards
-    ",
-    );
     let mut params = Vec::new();
     let mut date_params = Vec::new();
 
@@ -248,14 +308,14 @@ pub async fn get_card_by_filter(
             .map(|_| "?")
             .collect::<Vec<_>>()
             .join(", ");
-        conditions.push(format!("template_id IN ({})", placeholders));
+        conditions.push(format!("c.template_id IN ({})", placeholders));
         params.extend(template_ids.iter().map(|&id| id as i64));
     }
 
     // 处理deck_ids条件
     if !deck_ids.is_empty() {
         let placeholders = deck_ids.iter().map(|_| "?").collect::<Vec<_>>().join(", ");
-        conditions.push(format!("deck_id IN ({})", placeholders));
+        conditions.push(format!("c.deck_id IN ({})", placeholders));
         params.extend(deck_ids.iter().map(|&id| id as i64));
     }
 
@@ -265,25 +325,24 @@ pub async fn get_card_by_filter(
 
         // tolearn: last_review 为 NULL 的卡片
         if status_bit_filter & (1 << 0) != 0 {
-            status_conditions.push("last_review IS NULL".to_string());
+            status_conditions.push("c.last_review IS NULL");
         }
 
         // learning: last_review 和 due 均在今天的卡片
         if status_bit_filter & (1 << 1) != 0 {
-            status_conditions.push(format!(
-                "(last_review >= ? AND last_review <= ? AND due >= ? AND due <= ?)"
-            ));
-            date_params.push(today_start_utc.clone());
-            date_params.push(today_end_utc.clone());
-            date_params.push(today_start_utc.clone());
-            date_params.push(today_end_utc.clone());
+            status_conditions
+                .push("(c.last_review >= ? AND c.last_review <= ? AND c.due >= ? AND c.due <= ?)");
+            date_params.push(today_start_utc);
+            date_params.push(today_end_utc);
+            date_params.push(today_start_utc);
+            date_params.push(today_end_utc);
         }
 
         // toreview: last_review 在今天之前，due 在今天之后的卡片
         if status_bit_filter & (1 << 2) != 0 {
-            status_conditions.push(format!("(last_review <= ? AND due > ?)"));
-            date_params.push(today_end_utc.clone());
-            date_params.push(today_end_utc.clone());
+            status_conditions.push("(c.last_review <= ? AND c.due > ?)");
+            date_params.push(today_end_utc);
+            date_params.push(today_end_utc);
         }
 
         // 将状态条件用OR连接，并添加到主条件列表
@@ -293,26 +352,34 @@ pub async fn get_card_by_filter(
     }
 
     // 添加WHERE子句（如果有条件）
-    if !conditions.is_empty() {
-        query.push_str(" WHERE ");
-        query.push_str(&conditions.join(" AND "));
-    }
+    let where_clause = if conditions.is_empty() {
+        String::new()
+    } else {
+        format!(" WHERE {}", conditions.join(" AND "))
+    };
+    (where_clause, params, date_params)
+}
+
+pub async fn get_card_by_filter(
+    pool: &SqlitePool,
+    template_ids: Vec<u32>,
+    deck_ids: Vec<u32>,
+    status_bit_filter: u8, // (1 << 0): tolearn, (1 << 1): learning, (1 << 2): toreview
+) -> Result<Vec<Card>> {
+    let (where_clause, params, date_params) =
+        card_filter_conditions(&template_ids, &deck_ids, status_bit_filter);
+    let query = format!(
+        "
+      SELECT
+          c.card_id, c.deck_id, c.template_id, c.template_fields,
+          c.due, c.stability, c.difficulty, c.scheduled_days, c.last_review
+      FROM cards c{}
+    ",
+        where_clause
+    );
 
     // 创建查询并绑定参数
-    let mut query_builder = sqlx::query_as::<
-        _,
-        (
-            i64,
-            i64,
-            i64,
-            String,
-            String,
-            Option<f32>,
-            Option<f32>,
-            i64,
-            Option<String>,
-        ),
-    >(&query);
+    let mut query_builder = sqlx::query_as::<_, CardRow>(&query);
 
     // 绑定所有参数
     for param in params {
@@ -326,86 +393,31 @@ pub async fn get_card_by_filter(
     // 执行查询
     let rows = query_builder.fetch_all(pool).await?;
 
-    // 手动构建Card对象
-    let mut cards = Vec::new();
-    for (
-        card_id,
-        deck_id,
-        template_id,
-        template_fields,
-        due_str,
-        stability,
-        difficulty,
-        scheduled_days,
-        last_review_str,
-    ) in rows
-    {
-        // 解析模板字段，使用Unicode分隔符分割
-        let template_fields_content = template_fields
-            .split('\u{001F}')
-            .map(|s| s.to_string())
-            .collect();
-
-        // 解析日期时间字段
-        let due = chrono::DateTime::parse_from_rfc3339(&due_str)
-            .map_err(|_| sqlx::Error::RowNotFound)?
-            .with_timezone(&chrono::Utc);
-
-        // 解析可能为NULL的last_review字段
-        let last_review = if let Some(lr_str) = last_review_str {
-            Some(
-                chrono::DateTime::parse_from_rfc3339(&lr_str)
-                    .map_err(|_| sqlx::Error::RowNotFound)?
-                    .with_timezone(&chrono::Utc),
-            )
-        } else {
-            None
-        };
-
-        // 构建内存状态
-        let memory_state = if let (Some(s), Some(d)) = (stability, difficulty) {
-            Some(fsrs::MemoryState {
-                stability: s,
-                difficulty: d,
-            })
-        } else {
-            None
-        };
-
-        cards.push(Card {
-            card_id: card_id as u32,
-            deck_id: deck_id as u32,
-            template_id: template_id as u32,
-            template_fields_content,
-            due,
-            memory_state,
-            scheduled_days: scheduled_days as u32,
-            last_review,
-        });
-    }
-
-    Ok(cards)
+    rows.into_iter().map(card_from_row).collect()
 }
 
 /// 更新卡片字段内容
 ///
-/// 根据卡片ID更新卡片的模板字段内容
+/// 根据卡片ID更新卡片的模板字段内容，字段内容需通过模板字段的校验
 pub async fn update_card_fields(
     pool: &SqlitePool,
     card_id: u32,
     template_fields: Vec<String>,
 ) -> Result<()> {
+    let card = sqlx::query!("SELECT template_id FROM cards WHERE card_id = ?", card_id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or(sqlx::Error::RowNotFound)?;
+    validate_card_fields(pool, card.template_id as u32, &template_fields).await?;
+
     let merged_fields = merge_template_fields(template_fields);
 
-    sqlx::query!(
-        "UPDATE cards 
-        SET template_fields = ? 
-        WHERE card_id = ?",
-        merged_fields,
-        card_id
-    )
-    .execute(pool)
-    .await?;
+    sqlx::query("UPDATE cards SET template_fields = ?, modified_at = ? WHERE card_id = ?")
+        .bind(merged_fields)
+        .bind(Utc::now())
+        .bind(card_id)
+        .execute(pool)
+        .await?;
 
     Ok(())
 }
@@ -1,4 +1,4 @@
-use crate::models::Deck;
+use crate::models::{Deck, QueueOptions};
 use chrono::{Local, Utc};
 use sqlx::{Result, SqlitePool};
 
@@ -10,79 +10,111 @@ pub async fn create_deck(pool: &SqlitePool, deck_name: &str) -> Result<u32> {
     Ok(deck_id)
 }
 
+/// 获取所有牌组及其今天的卡片数量
+///
+/// 用一条分组聚合查询统计所有牌组，没有卡片的牌组数量均为 0
 pub async fn get_decks(pool: &SqlitePool) -> Result<Vec<Deck>> {
-    // 查询所有卡组的基本信息
-    let decks_rows = sqlx::query!("SELECT deck_id, name FROM decks")
-        .fetch_all(pool)
-        .await?;
+    // 获取当地时间的今天日期范围（开始和结束），转换为UTC时间用于数据库查询
+    let today_local = Local::now().date_naive();
+    let today_start_utc = today_local
+        .and_hms_opt(0, 0, 0)
+        .unwrap()
+        .and_local_timezone(Local)
+        .unwrap()
+        .with_timezone(&Utc);
+    let today_end_utc = today_local
+        .and_hms_opt(23, 59, 59)
+        .unwrap()
+        .and_local_timezone(Local)
+        .unwrap()
+        .with_timezone(&Utc);
+
+    // tolearn：last_review 为 NULL 的卡片
+    // learning：last_review 和 due 均在今天的卡片
+    // toreview：last_review 在今天之前，due 在今天及今天之前的卡片
+    let rows: Vec<(i64, String, i64, i64, i64)> = sqlx::query_as(
+        "
+        SELECT
+            d.deck_id, d.name,
+            COALESCE(SUM(c.last_review IS NULL), 0),
+            COALESCE(SUM(c.last_review >= ? AND c.last_review <= ?
+                AND c.due >= ? AND c.due <= ?), 0),
+            COALESCE(SUM(c.last_review < ? AND c.due <= ?), 0)
+        FROM decks d
+        LEFT JOIN cards c ON c.deck_id = d.deck_id
+        GROUP BY d.deck_id
+        ORDER BY d.deck_id
+        ",
+    )
+    .bind(today_start_utc)
+    .bind(today_end_utc)
+    .bind(today_start_utc)
+    .bind(today_end_utc)
+    .bind(today_start_utc)
+    .bind(today_end_utc)
+    .fetch_all(pool)
+    .await?;
 
-    let mut decks = Vec::new();
-    for row in decks_rows {
-        let mut deck = Deck {
-            deck_id: row.deck_id as u32,
-            deck_name: row.name.clone(),
-            tolearn: 0,
-            learning: 0,
-            toreview: 0,
-        };
-
-        // 获取当地时间的今天日期范围（开始和结束）
-        let today_local = Local::now();
-        let today_start_local = today_local.date_naive().and_hms_opt(0, 0, 0).unwrap();
-        let today_end_local = today_local.date_naive().and_hms_opt(23, 59, 59).unwrap();
-
-        // 转换为UTC时间用于数据库查询
-        let today_start_utc = today_start_local
-            .and_local_timezone(Local)
-            .unwrap()
-            .with_timezone(&Utc);
-        let today_end_utc = today_end_local
-            .and_local_timezone(Local)
-            .unwrap()
-            .with_timezone(&Utc);
-
-        // 统计 tolearn：last_review 为 NULL 的卡片数量
-        let tolearn_result = sqlx::query!(
-            "SELECT COUNT(*) as count FROM cards 
-            WHERE deck_id = ? AND last_review 
-            IS NULL",
-            deck.deck_id
-        )
-        .fetch_one(pool)
-        .await?;
-        deck.tolearn = tolearn_result.count as u32;
-
-        // 统计 learning：last_review 和 due 均在今天的卡片数量
-        let learning_result = sqlx::query!(
-            "SELECT COUNT(*) as count FROM cards 
-            WHERE deck_id = ? AND last_review >= ? 
-            AND last_review <= ? AND due >= ? AND due <= ?",
-            deck.deck_id,
-            today_start_utc,
-            today_end_utc,
-            today_start_utc,
-            today_end_utc
-        )
-        .fetch_one(pool)
-        .await?;
-        deck.learning = learning_result.count as u32;
-
-        // 统计 toreview：last_review 在今天之前，due 在今天及今天之前的卡片数量
-        let toreview_result = sqlx::query!(
-            "SELECT COUNT(*) as count FROM cards 
-            WHERE deck_id = ? AND last_review < ? AND due <= ?",
-            deck.deck_id,
-            today_start_utc,
-            today_end_utc
+    Ok(rows
+        .into_iter()
+        .map(|(deck_id, name, tolearn, learning, toreview)| Deck {
+            deck_id: deck_id as u32,
+            deck_name: name,
+            tolearn: tolearn as u32,
+            learning: learning as u32,
+            toreview: toreview as u32,
+        })
+        .collect())
+}
+
+/// 数据库中的学习队列选项：(新卡片顺序, 复习顺序, 混合方式, 每天新卡片上限, 每天复习上限)
+type QueueOptionsRow = (String, String, String, Option<i64>, Option<i64>);
+
+/// 获取牌组的学习队列选项
+pub async fn get_deck_queue_options(pool: &SqlitePool, deck_id: u32) -> Result<QueueOptions> {
+    let (new_card_order, review_order, new_review_mix, new_cards_per_day, max_reviews_per_day): QueueOptionsRow =
+        sqlx::query_as(
+            "SELECT new_card_order, review_order, new_review_mix, new_cards_per_day, max_reviews_per_day
+            FROM decks WHERE deck_id = ?",
         )
+        .bind(deck_id)
         .fetch_one(pool)
         .await?;
-        deck.toreview = toreview_result.count as u32;
 
-        decks.push(deck);
+    Ok(QueueOptions {
+        new_card_order: new_card_order.parse().unwrap_or_default(),
+        review_order: review_order.parse().unwrap_or_default(),
+        new_review_mix: new_review_mix.parse().unwrap_or_default(),
+        new_cards_per_day: new_cards_per_day.map(|limit| limit.max(0) as u32),
+        max_reviews_per_day: max_reviews_per_day.map(|limit| limit.max(0) as u32),
+    })
+}
+
+/// 设置牌组的学习队列选项
+pub async fn update_deck_queue_options(
+    pool: &SqlitePool,
+    deck_id: u32,
+    options: &QueueOptions,
+) -> Result<()> {
+    let result = sqlx::query(
+        "UPDATE decks SET new_card_order = ?, review_order = ?, new_review_mix = ?,
+            new_cards_per_day = ?, max_reviews_per_day = ?
+        WHERE deck_id = ?",
+    )
+    .bind(options.new_card_order.as_str())
+    .bind(options.review_order.as_str())
+    .bind(options.new_review_mix.as_str())
+    .bind(options.new_cards_per_day)
+    .bind(options.max_reviews_per_day)
+    .bind(deck_id)
+    .execute(pool)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(sqlx::Error::RowNotFound);
     }
 
-    Ok(decks)
+    Ok(())
 }
 
 // 获取卡组中的卡片总数
@@ -113,12 +145,37 @@ pub async fn delete_deck_by_id(pool: &SqlitePool, deck_id: u32) -> Result<()> {
             .await?;
     }
 
-    // 3. 删除牌组中的所有卡片
+    // 3. 从未完成的学习会话的队列中移除这些卡片，再删除牌组中的所有卡片
+    sqlx::query(
+        "DELETE FROM study_session_cards WHERE card_id IN
+        (SELECT card_id FROM cards WHERE deck_id = ?)",
+    )
+    .bind(deck_id)
+    .execute(&mut *tx)
+    .await?;
     sqlx::query!("DELETE FROM cards WHERE deck_id = ?", deck_id)
         .execute(&mut *tx)
         .await?;
 
-    // 4. 最后删除牌组本身
+    // 4. 从未完成的学习会话中移除该牌组，删除不再包含任何牌组的会话及其队列
+    sqlx::query("DELETE FROM study_session_decks WHERE deck_id = ?")
+        .bind(deck_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query(
+        "DELETE FROM study_sessions WHERE session_id NOT IN
+        (SELECT session_id FROM study_session_decks)",
+    )
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query(
+        "DELETE FROM study_session_cards WHERE session_id NOT IN
+        (SELECT session_id FROM study_sessions)",
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    // 5. 最后删除牌组本身
     sqlx::query!("DELETE FROM decks WHERE deck_id = ?", deck_id)
         .execute(&mut *tx)
         .await?;
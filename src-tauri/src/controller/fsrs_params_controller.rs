@@ -0,0 +1,117 @@
+use crate::config::{default_fsrs_params, FSRS5_DEFAULT_PARAMS};
+use crate::models::{ParameterMetrics, ParamsHistoryEntry, ParamsSource};
+use chrono::{DateTime, Utc};
+use sqlx::{Result, SqlitePool};
+
+type HistoryRow = (
+    i64,
+    String,
+    String,
+    DateTime<Utc>,
+    Option<i64>,
+    Option<f32>,
+    Option<f32>,
+    Option<i64>,
+);
+
+fn history_entry(row: HistoryRow) -> ParamsHistoryEntry {
+    let (history_id, params, source, created_at, review_count, log_loss, rmse_bins, reverted_from) =
+        row;
+    ParamsHistoryEntry {
+        history_id: history_id as u32,
+        params: serde_json::from_str(&params).unwrap_or_default(),
+        source: source.parse().unwrap_or(ParamsSource::Manual),
+        created_at,
+        review_count: review_count.map(|count| count as u32),
+        metrics: log_loss
+            .zip(rmse_bins)
+            .map(|(log_loss, rmse_bins)| ParameterMetrics {
+                log_loss,
+                rmse_bins,
+            }),
+        reverted_from: reverted_from.map(|id| id as u32),
+    }
+}
+
+/// 添加FSRS参数历史记录
+///
+/// `review_count` 和 `metrics` 为训练时的复习数量和评估结果，`reverted_from` 为恢复的历史记录ID
+pub async fn add_params_history(
+    pool: &SqlitePool,
+    params: &[f32],
+    source: ParamsSource,
+    review_count: Option<u32>,
+    metrics: Option<ParameterMetrics>,
+    reverted_from: Option<u32>,
+) -> Result<u32> {
+    let params = serde_json::to_string(params)
+        .map_err(|e| sqlx::Error::Io(std::io::Error::other(e.to_string())))?;
+    let history_id = sqlx::query(
+        "INSERT INTO fsrs_params_history
+        (params, source, created_at, review_count, log_loss, rmse_bins, reverted_from)
+        VALUES (?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(params)
+    .bind(source.as_str())
+    .bind(Utc::now())
+    .bind(review_count.map(|count| count as i64))
+    .bind(metrics.map(|metrics| metrics.log_loss))
+    .bind(metrics.map(|metrics| metrics.rmse_bins))
+    .bind(reverted_from.map(|id| id as i64))
+    .execute(pool)
+    .await?
+    .last_insert_rowid() as u32;
+    Ok(history_id)
+}
+
+/// 确保历史记录中有修改前的参数
+///
+/// 历史记录为空时，先记录当前使用的参数，使第一次修改之后也能恢复。
+/// 与默认参数相同时来源记为默认，否则来源未知，记为手动设置
+pub async fn ensure_params_history(pool: &SqlitePool, current_params: &[f32]) -> Result<()> {
+    let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM fsrs_params_history")
+        .fetch_one(pool)
+        .await?;
+    if count == 0 {
+        let source =
+            if current_params == default_fsrs_params() || current_params == FSRS5_DEFAULT_PARAMS {
+                ParamsSource::Default
+            } else {
+                ParamsSource::Manual
+            };
+        add_params_history(pool, current_params, source, None, None, None).await?;
+    }
+    Ok(())
+}
+
+/// 获取FSRS参数历史记录
+///
+/// 按时间从新到旧排序，第一条为最近一次应用的参数
+pub async fn get_params_history(pool: &SqlitePool) -> Result<Vec<ParamsHistoryEntry>> {
+    let rows = sqlx::query_as::<_, HistoryRow>(
+        "SELECT history_id, params, source, created_at, review_count, log_loss, rmse_bins,
+            reverted_from
+        FROM fsrs_params_history
+        ORDER BY history_id DESC",
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows.into_iter().map(history_entry).collect())
+}
+
+/// 根据ID获取FSRS参数历史记录
+pub async fn get_params_history_entry(
+    pool: &SqlitePool,
+    history_id: u32,
+) -> Result<Option<ParamsHistoryEntry>> {
+    let row = sqlx::query_as::<_, HistoryRow>(
+        "SELECT history_id, params, source, created_at, review_count, log_loss, rmse_bins,
+            reverted_from
+        FROM fsrs_params_history
+        WHERE history_id = ?",
+    )
+    .bind(history_id as i64)
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.map(history_entry))
+}
@@ -0,0 +1,219 @@
+use crate::controller::card_controller::split_template_fields;
+use crate::models::{FieldType, MediaCheckReport, MissingMedia};
+use regex::Regex;
+use sha2::{Digest, Sha256};
+use sqlx::{Result, SqlitePool};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+/// 字段中引用媒体文件的格式：[media:文件名]
+pub static MEDIA_REFERENCE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\[media:([^\[\]\s]+)\]").unwrap());
+
+/// 获取数据库对应的媒体文件夹
+///
+/// 媒体文件夹与数据库文件位于同一目录，名称为数据库文件名加上 .media 后缀
+pub fn media_dir_for_database(db_url: &str) -> PathBuf {
+    let db_path = PathBuf::from(db_url.strip_prefix("sqlite://").unwrap_or(db_url));
+    let stem = db_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_else(|| "collection".to_string());
+    db_path.with_file_name(format!("{}.media", stem))
+}
+
+/// 检查媒体文件名
+///
+/// 媒体文件名不能包含路径，且必须带有扩展名
+pub fn is_media_file_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('.')
+        && !name.contains(['/', '\\'])
+        && name
+            .rsplit_once('.')
+            .is_some_and(|(_, extension)| !extension.is_empty())
+}
+
+/// 解析媒体类型字段中的文件名
+///
+/// 媒体类型字段的内容可以是文件名本身，也可以是 [media:文件名] 形式的引用
+pub fn media_field_file_name(content: &str) -> Option<&str> {
+    let content = content.trim();
+    let file_name = content
+        .strip_prefix("[media:")
+        .and_then(|rest| rest.strip_suffix(']'))
+        .unwrap_or(content);
+    is_media_file_name(file_name).then_some(file_name)
+}
+
+/// 提取字段内容中引用的所有媒体文件名
+pub fn media_references(content: &str) -> Vec<String> {
+    MEDIA_REFERENCE
+        .captures_iter(content)
+        .map(|captures| captures[1].to_string())
+        .filter(|file_name| is_media_file_name(file_name))
+        .collect()
+}
+
+/// 根据扩展名获取媒体文件的 MIME 类型
+pub fn media_mime_type(file_name: &str) -> &'static str {
+    let extension = file_name
+        .rsplit_once('.')
+        .map(|(_, extension)| extension.to_ascii_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "bmp" => "image/bmp",
+        "mp3" => "audio/mpeg",
+        "wav" => "audio/wav",
+        "ogg" => "audio/ogg",
+        "m4a" => "audio/mp4",
+        "flac" => "audio/flac",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        _ => "application/octet-stream",
+    }
+}
+
+/// 保存媒体文件
+///
+/// 以文件内容的 SHA-256 作为文件名保存到媒体文件夹，扩展名沿用原文件名，
+/// 内容相同的文件只保存一份。返回保存后的媒体文件名
+pub fn store_media(media_dir: &Path, original_name: &str, data: &[u8]) -> io::Result<String> {
+    let extension = Path::new(original_name)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.to_ascii_lowercase())
+        .filter(|extension| {
+            !extension.is_empty() && extension.chars().all(|c| c.is_ascii_alphanumeric())
+        })
+        .ok_or_else(|| {
+            io::Error::new(
+                ErrorKind::InvalidInput,
+                format!("媒体文件 {} 缺少扩展名", original_name),
+            )
+        })?;
+
+    let hash = format!("{:x}", Sha256::digest(data));
+    let file_name = format!("{}.{}", hash, extension);
+
+    fs::create_dir_all(media_dir)?;
+    let path = media_dir.join(&file_name);
+    if !path.exists() {
+        // 先写入临时文件再重命名，避免中断时留下不完整的媒体文件
+        let temp_path = media_dir.join(format!(".{}.tmp", file_name));
+        fs::write(&temp_path, data)?;
+        fs::rename(&temp_path, &path)?;
+    }
+
+    Ok(file_name)
+}
+
+/// 读取媒体文件
+pub fn read_media(media_dir: &Path, file_name: &str) -> io::Result<Vec<u8>> {
+    if !is_media_file_name(file_name) {
+        return Err(io::Error::new(
+            ErrorKind::InvalidInput,
+            format!("无效的媒体文件名 {}", file_name),
+        ));
+    }
+    fs::read(media_dir.join(file_name))
+}
+
+/// 检查媒体文件
+///
+/// 对比卡片中引用的媒体文件和媒体文件夹中的文件，找出缺失的文件和未被引用的文件。
+/// `delete_unused` 为 true 时删除未被引用的文件
+pub async fn check_media_files(
+    pool: &SqlitePool,
+    media_dir: &Path,
+    delete_unused: bool,
+) -> Result<MediaCheckReport> {
+    // 每个模板中媒体类型字段的位置
+    let fields = sqlx::query_as::<_, (i64, String)>(
+        "SELECT template_id, field_type FROM template_fields ORDER BY template_id, fields_id",
+    )
+    .fetch_all(pool)
+    .await?;
+    let mut media_fields: HashMap<i64, Vec<usize>> = HashMap::new();
+    let mut field_counts: HashMap<i64, usize> = HashMap::new();
+    for (template_id, field_type) in fields {
+        let index = field_counts.entry(template_id).or_default();
+        if field_type.parse::<FieldType>() == Ok(FieldType::Media) {
+            media_fields.entry(template_id).or_default().push(*index);
+        }
+        *index += 1;
+    }
+
+    // 收集卡片引用的媒体文件
+    let cards = sqlx::query_as::<_, (i64, i64, String)>(
+        "SELECT card_id, template_id, template_fields FROM cards",
+    )
+    .fetch_all(pool)
+    .await?;
+    let mut referenced: BTreeMap<String, Vec<u32>> = BTreeMap::new();
+    for (card_id, template_id, template_fields) in cards {
+        let content = split_template_fields(&template_fields);
+        let mut file_names: BTreeSet<String> = content
+            .iter()
+            .flat_map(|field| media_references(field))
+            .collect();
+        for &index in media_fields.get(&template_id).into_iter().flatten() {
+            if let Some(file_name) = content.get(index).and_then(|c| media_field_file_name(c)) {
+                file_names.insert(file_name.to_string());
+            }
+        }
+        for file_name in file_names {
+            referenced
+                .entry(file_name)
+                .or_default()
+                .push(card_id as u32);
+        }
+    }
+
+    // 收集媒体文件夹中的文件
+    let mut stored = BTreeSet::new();
+    if media_dir.exists() {
+        for entry in fs::read_dir(media_dir)? {
+            let entry = entry?;
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            if entry.file_type()?.is_file() && is_media_file_name(&file_name) {
+                stored.insert(file_name);
+            }
+        }
+    }
+
+    let missing = referenced
+        .iter()
+        .filter(|(file_name, _)| !stored.contains(*file_name))
+        .map(|(file_name, card_ids)| MissingMedia {
+            file_name: file_name.clone(),
+            card_ids: card_ids.clone(),
+        })
+        .collect();
+    let unused: Vec<String> = stored
+        .into_iter()
+        .filter(|file_name| !referenced.contains_key(file_name))
+        .collect();
+
+    let mut deleted = 0;
+    if delete_unused {
+        for file_name in &unused {
+            fs::remove_file(media_dir.join(file_name))?;
+            deleted += 1;
+        }
+    }
+
+    Ok(MediaCheckReport {
+        missing,
+        unused,
+        deleted,
+    })
+}
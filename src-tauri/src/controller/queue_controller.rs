@@ -0,0 +1,357 @@
+use crate::controller::card_controller::{annotate_cards, card_from_row, sort_cards, CardRow};
+use crate::controller::statistics_controller::{deck_condition, local_day_start};
+use crate::models::{Card, CardSortOrder, NewCardOrder, NewReviewMix, QueueOptions, ReviewOrder};
+use chrono::{DateTime, Local, Utc};
+use sqlx::{Result, SqlitePool};
+
+/// 新卡片的排序语句
+fn new_card_order_by(order: NewCardOrder) -> &'static str {
+    match order {
+        NewCardOrder::Added => "c.card_id",
+        NewCardOrder::Random => "RANDOM()",
+        NewCardOrder::Template => "c.template_id, c.card_id",
+    }
+}
+
+/// 复习卡片的排序语句
+///
+/// 按可提取性排序需要先计算可提取性，查询时按到期时间排序，之后在内存中重新排序
+fn review_order_by(order: ReviewOrder) -> &'static str {
+    match order {
+        ReviewOrder::Due | ReviewOrder::RetrievabilityAscending | ReviewOrder::Deck => {
+            "c.due, c.card_id"
+        }
+        ReviewOrder::Random => "RANDOM()",
+    }
+}
+
+/// 当地时间今天的结束时间，转换为UTC时间用于数据库查询
+fn today_end_utc() -> DateTime<Utc> {
+    Local::now()
+        .date_naive()
+        .and_hms_opt(23, 59, 59)
+        .unwrap()
+        .and_local_timezone(Local)
+        .unwrap()
+        .with_timezone(&Utc)
+}
+
+/// 牌组今天已经学习的卡片数：(第一次学习的新卡片数, 复习的卡片数)
+///
+/// 只统计普通学习的复习记录，每张卡片只计一次
+async fn get_studied_today(
+    pool: &SqlitePool,
+    deck_id: u32,
+    today_start: DateTime<Utc>,
+) -> Result<(u32, u32)> {
+    let (new_count, review_count): (i64, i64) = sqlx::query_as(
+        "
+        SELECT
+            COALESCE(SUM(first_review >= ?), 0),
+            COALESCE(SUM(first_review < ?), 0)
+        FROM (
+            SELECT MIN(r.review_date) AS first_review
+            FROM reviews r
+            JOIN cards c ON c.card_id = r.card_id
+            WHERE c.deck_id = ?
+            GROUP BY r.card_id
+            HAVING MAX(CASE WHEN r.study_mode = 'normal' THEN r.review_date END) >= ?
+        )
+        ",
+    )
+    .bind(today_start)
+    .bind(today_start)
+    .bind(deck_id)
+    .bind(today_start)
+    .fetch_one(pool)
+    .await?;
+
+    Ok((new_count as u32, review_count as u32))
+}
+
+/// 按每天的上限保留卡片
+///
+/// `studied` 为今天已经学习的卡片数，保留的卡片数不超过剩余的数量。
+/// 今天已经学过、当天需要再次学习的卡片不受上限限制
+fn limit_cards(
+    cards: &mut Vec<Card>,
+    limit: Option<u32>,
+    studied: u32,
+    today_start: DateTime<Utc>,
+) {
+    let Some(limit) = limit else {
+        return;
+    };
+    let mut remaining = limit.saturating_sub(studied);
+    cards.retain(|card| {
+        if card.last_review.is_some_and(|last| last >= today_start) {
+            return true;
+        }
+        if remaining == 0 {
+            return false;
+        }
+        remaining -= 1;
+        true
+    });
+}
+
+/// 获取牌组今天到期的新卡片或复习卡片
+async fn get_due_cards(
+    pool: &SqlitePool,
+    deck_id: u32,
+    new_cards: bool,
+    order_by: &str,
+) -> Result<Vec<Card>> {
+    let query = format!(
+        "
+        SELECT
+            c.card_id, c.deck_id, c.template_id, c.template_fields,
+            c.due, c.stability, c.difficulty, c.scheduled_days, c.last_review
+        FROM cards c
+        WHERE c.deck_id = ? AND c.due <= ? AND c.last_review IS {}
+        ORDER BY {}
+        ",
+        if new_cards { "NULL" } else { "NOT NULL" },
+        order_by
+    );
+
+    let rows = sqlx::query_as::<_, CardRow>(&query)
+        .bind(deck_id)
+        .bind(today_end_utc())
+        .fetch_all(pool)
+        .await?;
+
+    rows.into_iter().map(card_from_row).collect()
+}
+
+/// 按混合方式合并新卡片和复习卡片
+///
+/// 穿插时新卡片均匀分布在复习卡片之间，第 k 张新卡片放在整个队列约 k / (新卡片数 + 1) 的位置
+pub fn mix_queue(new_cards: Vec<Card>, reviews: Vec<Card>, mix: NewReviewMix) -> Vec<Card> {
+    match mix {
+        NewReviewMix::Before => new_cards.into_iter().chain(reviews).collect(),
+        NewReviewMix::After => reviews.into_iter().chain(new_cards).collect(),
+        NewReviewMix::Interleaved => {
+            let new_count = new_cards.len();
+            let total = new_count + reviews.len();
+            let mut new_iter = new_cards.into_iter();
+            let mut review_iter = reviews.into_iter();
+            let mut queue = Vec::with_capacity(total);
+            let mut new_taken = 0;
+            while queue.len() < total {
+                // 已到第 new_taken + 1 张新卡片的位置，或复习卡片已用完时取新卡片
+                let new_due = (queue.len() + 1) * (new_count + 1) >= (new_taken + 1) * total;
+                let card = if new_taken < new_count && new_due {
+                    new_taken += 1;
+                    new_iter.next()
+                } else {
+                    review_iter.next().or_else(|| {
+                        new_taken += 1;
+                        new_iter.next()
+                    })
+                };
+                match card {
+                    Some(card) => queue.push(card),
+                    None => break,
+                }
+            }
+            queue
+        }
+    }
+}
+
+/// 合并多个牌组的学习队列
+///
+/// 每个队列带有是否按牌组集中学习的标记。在牌组之间轮流取卡片，每次取一张，
+/// 集中学习的牌组轮到时一次取出全部卡片。每个牌组内保持原有顺序，卡片较少的牌组取完后继续取其余牌组
+pub fn merge_deck_queues(queues: Vec<(Vec<Card>, bool)>) -> Vec<Card> {
+    let total = queues.iter().map(|(cards, _)| cards.len()).sum();
+    let mut iters: Vec<_> = queues
+        .into_iter()
+        .map(|(cards, grouped)| (cards.into_iter(), grouped))
+        .collect();
+    let mut queue = Vec::with_capacity(total);
+    while queue.len() < total {
+        for (iter, grouped) in iters.iter_mut() {
+            if *grouped {
+                queue.extend(iter.by_ref());
+            } else {
+                queue.extend(iter.next());
+            }
+        }
+    }
+    queue
+}
+
+/// 构建牌组的学习队列
+///
+/// 按牌组的队列选项 `options` 分别排序今天到期的新卡片和复习卡片，扣除今天已经学习的数量后
+/// 按每天的上限截取，再按混合方式合并。每张卡片带有当前可提取性和逾期天数
+pub async fn build_study_queue(
+    pool: &SqlitePool,
+    deck_id: u32,
+    options: &QueueOptions,
+    fsrs_params: &[f32],
+) -> Result<Vec<Card>> {
+    let now = Utc::now();
+    let today_start = local_day_start(now.with_timezone(&Local).date_naive());
+    let (new_studied, reviews_studied) = get_studied_today(pool, deck_id, today_start).await?;
+
+    let mut new_cards = get_due_cards(
+        pool,
+        deck_id,
+        true,
+        new_card_order_by(options.new_card_order),
+    )
+    .await?;
+    let mut reviews =
+        get_due_cards(pool, deck_id, false, review_order_by(options.review_order)).await?;
+
+    annotate_cards(&mut reviews, fsrs_params, now);
+    if options.review_order == ReviewOrder::RetrievabilityAscending {
+        sort_cards(&mut reviews, CardSortOrder::RetrievabilityAscending);
+    }
+
+    limit_cards(
+        &mut new_cards,
+        options.new_cards_per_day,
+        new_studied,
+        today_start,
+    );
+    limit_cards(
+        &mut reviews,
+        options.max_reviews_per_day,
+        reviews_studied,
+        today_start,
+    );
+    Ok(mix_queue(new_cards, reviews, options.new_review_mix))
+}
+
+/// 构建筛选学习或临时抱佛脚的学习队列
+///
+/// 包含牌组中所有学过的卡片，`not_due_only` 为 true 时只包含今天之后才到期的卡片，即提前学习。
+/// 卡片按可提取性从低到高排列，最容易忘记的卡片在前，`deck_ids` 为空时包含所有牌组
+pub async fn build_extra_study_queue(
+    pool: &SqlitePool,
+    deck_ids: &[u32],
+    not_due_only: bool,
+    fsrs_params: &[f32],
+) -> Result<Vec<Card>> {
+    let query = format!(
+        "
+        SELECT
+            c.card_id, c.deck_id, c.template_id, c.template_fields,
+            c.due, c.stability, c.difficulty, c.scheduled_days, c.last_review
+        FROM cards c
+        WHERE c.last_review IS NOT NULL AND {} AND {}
+        ORDER BY c.due, c.card_id
+        ",
+        if not_due_only { "c.due > ?" } else { "1" },
+        deck_condition(deck_ids)
+    );
+    let mut query_builder = sqlx::query_as::<_, CardRow>(&query);
+    if not_due_only {
+        query_builder = query_builder.bind(today_end_utc());
+    }
+    let mut cards = query_builder
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(card_from_row)
+        .collect::<Result<Vec<_>>>()?;
+
+    annotate_cards(&mut cards, fsrs_params, Utc::now());
+    sort_cards(&mut cards, CardSortOrder::RetrievabilityAscending);
+    Ok(cards)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cards(deck_id: u32, card_ids: &[u32]) -> Vec<Card> {
+        card_ids
+            .iter()
+            .map(|&card_id| Card {
+                card_id,
+                deck_id,
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    fn card_ids(queue: &[Card]) -> Vec<u32> {
+        queue.iter().map(|card| card.card_id).collect()
+    }
+
+    #[test]
+    fn mix_queue_puts_new_cards_before_or_after_reviews() {
+        let before = mix_queue(cards(1, &[1, 2]), cards(1, &[10, 11]), NewReviewMix::Before);
+        assert_eq!(card_ids(&before), [1, 2, 10, 11]);
+        let after = mix_queue(cards(1, &[1, 2]), cards(1, &[10, 11]), NewReviewMix::After);
+        assert_eq!(card_ids(&after), [10, 11, 1, 2]);
+    }
+
+    #[test]
+    fn mix_queue_spreads_new_cards_among_reviews() {
+        let queue = mix_queue(
+            cards(1, &[1, 2]),
+            cards(1, &[10, 11, 12, 13]),
+            NewReviewMix::Interleaved,
+        );
+        assert_eq!(card_ids(&queue), [10, 1, 11, 2, 12, 13]);
+
+        let only_new = mix_queue(cards(1, &[1, 2]), Vec::new(), NewReviewMix::Interleaved);
+        assert_eq!(card_ids(&only_new), [1, 2]);
+        let only_reviews = mix_queue(Vec::new(), cards(1, &[10, 11]), NewReviewMix::Interleaved);
+        assert_eq!(card_ids(&only_reviews), [10, 11]);
+    }
+
+    #[test]
+    fn limit_cards_keeps_the_remaining_cards_for_today() {
+        let today_start = Utc::now() - chrono::Duration::hours(1);
+        let mut reviews = cards(1, &[1, 2, 3]);
+        limit_cards(&mut reviews, Some(5), 3, today_start);
+        assert_eq!(card_ids(&reviews), [1, 2]);
+
+        let mut unlimited = cards(1, &[1, 2, 3]);
+        limit_cards(&mut unlimited, None, 100, today_start);
+        assert_eq!(card_ids(&unlimited), [1, 2, 3]);
+    }
+
+    #[test]
+    fn limit_cards_gives_nothing_at_the_limit_except_cards_studied_today() {
+        let today_start = Utc::now() - chrono::Duration::hours(1);
+        let mut reviews = cards(1, &[1, 2, 3]);
+        reviews[1].last_review = Some(today_start - chrono::Duration::days(3));
+        reviews[2].last_review = Some(today_start + chrono::Duration::minutes(10));
+        limit_cards(&mut reviews, Some(20), 20, today_start);
+        assert_eq!(card_ids(&reviews), [3]);
+
+        // 多个牌组一起学习时，达到上限的牌组不再提供卡片
+        let mut new_cards = cards(2, &[10, 11]);
+        limit_cards(&mut new_cards, Some(10), 12, today_start);
+        let queue = merge_deck_queues(vec![(cards(1, &[1, 2]), false), (new_cards, false)]);
+        assert_eq!(card_ids(&queue), [1, 2]);
+    }
+
+    #[test]
+    fn merge_deck_queues_alternates_between_decks() {
+        let queue = merge_deck_queues(vec![
+            (cards(1, &[1, 2, 3]), false),
+            (cards(2, &[10]), false),
+            (cards(3, &[20, 21]), false),
+        ]);
+        assert_eq!(card_ids(&queue), [1, 10, 20, 2, 21, 3]);
+    }
+
+    #[test]
+    fn merge_deck_queues_keeps_grouped_decks_together() {
+        let queue = merge_deck_queues(vec![
+            (cards(1, &[1, 2, 3]), false),
+            (cards(2, &[10, 11, 12]), true),
+            (cards(3, &[20, 21]), false),
+        ]);
+        assert_eq!(card_ids(&queue), [1, 10, 11, 12, 20, 2, 21, 3]);
+    }
+}
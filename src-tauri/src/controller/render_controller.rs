@@ -0,0 +1,468 @@
+use crate::controller::card_controller::split_template_fields;
+use crate::controller::invalid_input;
+use crate::controller::media_controller::{
+    is_media_file_name, media_field_file_name, media_mime_type, MEDIA_REFERENCE,
+};
+use crate::controller::template_controller::get_template_fields;
+use crate::models::{FieldType, RenderedCard, TemplateField, TemplateFormat};
+use regex::{Captures, Regex};
+use sqlx::{Result, SqlitePool};
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+/// 背面格式中引用正面渲染结果的特殊字段
+pub const FRONT_SIDE: &str = "FrontSide";
+
+/// 填空字段中的填空及其可选的提示，格式为 {{c1::答案::提示}}
+static CLOZE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?s)\{\{c[1-9]\d*::(.+?)(?:::(.+?))?\}\}").unwrap());
+
+/// 卡片格式的语法节点
+#[derive(Debug, PartialEq)]
+pub enum FormatNode<'a> {
+    Text(&'a str),
+    Field(&'a str),
+    Section {
+        field: &'a str,
+        inverted: bool, // {{^Field}} 在字段为空时显示
+        children: Vec<FormatNode<'a>>,
+    },
+}
+
+/// 解析过程中尚未结束的段落：(段落的字段名与是否反向, 已解析的子节点)
+type OpenSection<'a> = (Option<(&'a str, bool)>, Vec<FormatNode<'a>>);
+
+/// 解析卡片格式
+///
+/// 支持 `{{字段}}` 替换、`{{#字段}}…{{/字段}}` 条件段落（字段非空时显示）、
+/// `{{^字段}}…{{/字段}}` 反向条件段落（字段为空时显示）
+pub fn parse_format(format: &str) -> std::result::Result<Vec<FormatNode<'_>>, String> {
+    // 栈底为最外层节点，每进入一个条件段落压入一层
+    let mut stack: Vec<OpenSection> = vec![(None, Vec::new())];
+    let mut rest = format;
+
+    while let Some(start) = rest.find("{{") {
+        let Some(length) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        if start > 0 {
+            stack
+                .last_mut()
+                .unwrap()
+                .1
+                .push(FormatNode::Text(&rest[..start]));
+        }
+        let tag = rest[start + 2..start + 2 + length].trim();
+        rest = &rest[start + 2 + length + 2..];
+
+        if let Some(field) = tag.strip_prefix('#') {
+            stack.push((Some((field.trim(), false)), Vec::new()));
+        } else if let Some(field) = tag.strip_prefix('^') {
+            stack.push((Some((field.trim(), true)), Vec::new()));
+        } else if let Some(field) = tag.strip_prefix('/') {
+            let field = field.trim();
+            match stack.pop() {
+                Some((Some((open, inverted)), children)) if open == field => {
+                    stack.last_mut().unwrap().1.push(FormatNode::Section {
+                        field,
+                        inverted,
+                        children,
+                    });
+                }
+                Some((Some((open, _)), _)) => {
+                    return Err(format!(
+                        "条件段落 {{{{#{}}}}} 与 {{{{/{}}}}} 不匹配",
+                        open, field
+                    ));
+                }
+                _ => return Err(format!("{{{{/{}}}}} 缺少对应的开始标记", field)),
+            }
+        } else if tag.is_empty() {
+            return Err("存在空的 {{}} 标记".to_string());
+        } else {
+            stack.last_mut().unwrap().1.push(FormatNode::Field(tag));
+        }
+    }
+
+    if !rest.is_empty() {
+        stack.last_mut().unwrap().1.push(FormatNode::Text(rest));
+    }
+    if stack.len() > 1 {
+        let (open, _) = stack.pop().unwrap();
+        return Err(format!(
+            "条件段落 {{{{#{}}}}} 没有结束标记",
+            open.unwrap().0
+        ));
+    }
+
+    Ok(stack.pop().unwrap().1)
+}
+
+/// 检查卡片格式
+///
+/// 格式必须能够正确解析，且只能引用模板中存在的字段。
+/// 正面格式至少要引用一个字段，并且不能引用 {{FrontSide}}
+pub fn check_format(
+    format: &str,
+    field_names: &[&str],
+    is_front: bool,
+) -> std::result::Result<(), String> {
+    fn check_nodes(
+        nodes: &[FormatNode],
+        field_names: &[&str],
+        is_front: bool,
+        referenced: &mut bool,
+    ) -> std::result::Result<(), String> {
+        for node in nodes {
+            let field = match node {
+                FormatNode::Text(_) => continue,
+                FormatNode::Field(field) => *field,
+                FormatNode::Section {
+                    field, children, ..
+                } => {
+                    check_nodes(children, field_names, is_front, referenced)?;
+                    *field
+                }
+            };
+
+            if field == FRONT_SIDE {
+                if is_front {
+                    return Err("正面格式中不能使用 {{FrontSide}}".to_string());
+                }
+            } else if field_names.contains(&field) {
+                *referenced = true;
+            } else {
+                return Err(format!("模板中不存在字段 {}", field));
+            }
+        }
+        Ok(())
+    }
+
+    let nodes = parse_format(format)?;
+    let mut referenced = false;
+    check_nodes(&nodes, field_names, is_front, &mut referenced)?;
+
+    if is_front && !referenced {
+        return Err("正面格式至少需要引用一个字段".to_string());
+    }
+    Ok(())
+}
+
+/// 生成模板的默认格式
+///
+/// 正面依次显示所有正面字段，背面在正面内容之后依次显示非空的背面字段
+pub fn default_formats(fields: &[TemplateField]) -> (String, String) {
+    let front_format = fields
+        .iter()
+        .filter(|field| field.is_front)
+        .map(|field| format!("{{{{{}}}}}", field.name))
+        .collect::<Vec<_>>()
+        .join("<br>\n");
+
+    let mut back_format = format!("{{{{{}}}}}\n\n<hr id=\"answer\">\n", FRONT_SIDE);
+    for field in fields.iter().filter(|field| !field.is_front) {
+        back_format.push_str(&format!(
+            "\n{{{{#{0}}}}}<div>{{{{{0}}}}}</div>{{{{/{0}}}}}",
+            field.name
+        ));
+    }
+
+    (front_format, back_format)
+}
+
+/// 转义 HTML 特殊字符
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// 获取媒体文件在前端的访问地址
+///
+/// 媒体文件通过 media 协议提供，Windows 下 WebView2 需要使用 http://media.localhost 形式
+pub fn media_url(file_name: &str) -> String {
+    if cfg!(windows) {
+        format!("http://media.localhost/{}", file_name)
+    } else {
+        format!("media://localhost/{}", file_name)
+    }
+}
+
+/// 根据媒体类型生成对应的 HTML 标签
+pub fn media_html(file_name: &str) -> String {
+    let src = escape_html(&media_url(file_name));
+    let mime_type = media_mime_type(file_name);
+    if mime_type.starts_with("audio/") {
+        format!("<audio controls src=\"{}\"></audio>", src)
+    } else if mime_type.starts_with("video/") {
+        format!("<video controls src=\"{}\"></video>", src)
+    } else {
+        format!("<img src=\"{}\">", src)
+    }
+}
+
+/// 将字段内容中的 [media:文件名] 引用替换为媒体标签
+fn replace_media_references(html: &str) -> String {
+    MEDIA_REFERENCE
+        .replace_all(html, |captures: &Captures| {
+            if is_media_file_name(&captures[1]) {
+                media_html(&captures[1])
+            } else {
+                captures[0].to_string()
+            }
+        })
+        .to_string()
+}
+
+/// 渲染填空字段
+///
+/// 正面将 {{c1::答案::提示}} 显示为 [提示] 或 [...]，背面显示答案
+fn render_cloze(html: &str, is_front: bool) -> String {
+    CLOZE
+        .replace_all(html, |captures: &Captures| {
+            if is_front {
+                let hint = captures.get(2).map_or("...", |hint| hint.as_str());
+                format!("<span class=\"cloze\">[{}]</span>", hint)
+            } else {
+                format!("<span class=\"cloze\">{}</span>", &captures[1])
+            }
+        })
+        .to_string()
+}
+
+/// 按字段类型将字段内容渲染为 HTML
+///
+/// HTML 字段原样输出，其余文本类字段转义后保留换行，Markdown 字段暂按纯文本显示。
+/// 渲染结果由前端在不允许脚本的沙箱 iframe 中显示
+pub fn render_field(field_type: FieldType, content: &str, is_front: bool) -> String {
+    let html = match field_type {
+        FieldType::Html => content.to_string(),
+        FieldType::Media => {
+            return media_field_file_name(content)
+                .map(media_html)
+                .unwrap_or_default();
+        }
+        _ => escape_html(content).replace('\n', "<br>"),
+    };
+
+    let html = if field_type == FieldType::Cloze {
+        render_cloze(&html, is_front)
+    } else {
+        html
+    };
+
+    replace_media_references(&html)
+}
+
+/// 渲染上下文，包含字段原始内容与渲染结果
+struct RenderContext<'a> {
+    fields: HashMap<&'a str, (&'a str, String)>, // (原始内容, 渲染后的 HTML)
+    front_side: Option<&'a str>,
+}
+
+fn render_nodes(nodes: &[FormatNode], context: &RenderContext, output: &mut String) {
+    for node in nodes {
+        match node {
+            FormatNode::Text(text) => output.push_str(text),
+            FormatNode::Field(field) if *field == FRONT_SIDE => {
+                output.push_str(context.front_side.unwrap_or_default());
+            }
+            FormatNode::Field(field) => match context.fields.get(field) {
+                Some((_, html)) => output.push_str(html),
+                None => output.push_str(&escape_html(&format!("{{{{未知字段 {}}}}}", field))),
+            },
+            FormatNode::Section {
+                field,
+                inverted,
+                children,
+            } => {
+                let non_empty = if *field == FRONT_SIDE {
+                    context.front_side.is_some_and(|front| !front.is_empty())
+                } else {
+                    context
+                        .fields
+                        .get(field)
+                        .is_some_and(|(content, _)| !content.trim().is_empty())
+                };
+                if non_empty != *inverted {
+                    render_nodes(children, context, output);
+                }
+            }
+        }
+    }
+}
+
+/// 使用给定格式渲染卡片一面
+///
+/// `contents` 与 `fields` 按字段ID一一对应，`front_side` 为渲染背面时正面的渲染结果
+pub fn render_side(
+    format: &str,
+    fields: &[TemplateField],
+    contents: &[String],
+    front_side: Option<&str>,
+) -> std::result::Result<String, String> {
+    let nodes = parse_format(format)?;
+    let is_front = front_side.is_none();
+    let context = RenderContext {
+        fields: fields
+            .iter()
+            .zip(contents)
+            .map(|(field, content)| {
+                (
+                    field.name.as_str(),
+                    (
+                        content.as_str(),
+                        render_field(field.field_type, content, is_front),
+                    ),
+                )
+            })
+            .collect(),
+        front_side,
+    };
+
+    let mut output = String::new();
+    render_nodes(&nodes, &context, &mut output);
+    Ok(output)
+}
+
+/// 获取模板的卡片格式
+///
+/// 未设置格式的模板返回根据字段生成的默认格式
+pub async fn get_template_format(pool: &SqlitePool, template_id: u32) -> Result<TemplateFormat> {
+    let (front_format, back_format, css) =
+        sqlx::query_as::<_, (Option<String>, Option<String>, String)>(
+            "SELECT front_format, back_format, css FROM templates WHERE template_id = ?",
+        )
+        .bind(template_id as i64)
+        .fetch_optional(pool)
+        .await?
+        .ok_or(sqlx::Error::RowNotFound)?;
+
+    let (front_format, back_format) = match (front_format, back_format) {
+        (Some(front_format), Some(back_format)) => (front_format, back_format),
+        _ => default_formats(&get_template_fields(pool, template_id).await?),
+    };
+
+    Ok(TemplateFormat {
+        template_id,
+        front_format,
+        back_format,
+        css,
+    })
+}
+
+/// 更新模板的卡片格式
+///
+/// 保存前检查正面和背面格式的语法以及引用的字段
+pub async fn update_template_format(pool: &SqlitePool, format: &TemplateFormat) -> Result<()> {
+    let fields = get_template_fields(pool, format.template_id).await?;
+    if fields.is_empty() {
+        return Err(sqlx::Error::RowNotFound);
+    }
+    let field_names: Vec<&str> = fields.iter().map(|field| field.name.as_str()).collect();
+
+    check_format(&format.front_format, &field_names, true)
+        .map_err(|e| invalid_input(format!("正面格式错误: {}", e)))?;
+    check_format(&format.back_format, &field_names, false)
+        .map_err(|e| invalid_input(format!("背面格式错误: {}", e)))?;
+
+    sqlx::query(
+        "UPDATE templates SET front_format = ?, back_format = ?, css = ? WHERE template_id = ?",
+    )
+    .bind(&format.front_format)
+    .bind(&format.back_format)
+    .bind(&format.css)
+    .bind(format.template_id as i64)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// 渲染卡片
+///
+/// 使用卡片所属模板的格式渲染正面和背面，返回完整的 HTML 与模板 CSS
+pub async fn render_card_by_id(pool: &SqlitePool, card_id: u32) -> Result<RenderedCard> {
+    let (template_id, template_fields) = sqlx::query_as::<_, (i64, String)>(
+        "SELECT template_id, template_fields FROM cards WHERE card_id = ?",
+    )
+    .bind(card_id as i64)
+    .fetch_optional(pool)
+    .await?
+    .ok_or(sqlx::Error::RowNotFound)?;
+
+    let template_id = template_id as u32;
+    let format = get_template_format(pool, template_id).await?;
+    let fields = get_template_fields(pool, template_id).await?;
+    let contents = split_template_fields(&template_fields);
+
+    let front =
+        render_side(&format.front_format, &fields, &contents, None).map_err(invalid_input)?;
+    let back = render_side(&format.back_format, &fields, &contents, Some(&front))
+        .map_err(invalid_input)?;
+
+    Ok(RenderedCard {
+        card_id,
+        template_id,
+        front,
+        back,
+        css: format.css,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_format_reads_text_fields_and_sections() {
+        let nodes =
+            parse_format("问：{{正面}}{{#提示}}（{{ 提示 }}）{{/提示}}{{^图片}}无图{{/图片}}");
+        assert_eq!(
+            nodes.unwrap(),
+            vec![
+                FormatNode::Text("问："),
+                FormatNode::Field("正面"),
+                FormatNode::Section {
+                    field: "提示",
+                    inverted: false,
+                    children: vec![
+                        FormatNode::Text("（"),
+                        FormatNode::Field("提示"),
+                        FormatNode::Text("）"),
+                    ],
+                },
+                FormatNode::Section {
+                    field: "图片",
+                    inverted: true,
+                    children: vec![FormatNode::Text("无图")],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_format_keeps_unclosed_braces_as_text() {
+        assert_eq!(
+            parse_format("{{正面}} {{未结束").unwrap(),
+            vec![FormatNode::Field("正面"), FormatNode::Text(" {{未结束")]
+        );
+    }
+
+    #[test]
+    fn parse_format_rejects_unbalanced_sections() {
+        assert!(parse_format("{{#提示}}{{提示}}").is_err());
+        assert!(parse_format("{{#提示}}{{/正面}}").is_err());
+        assert!(parse_format("{{/提示}}").is_err());
+        assert!(parse_format("{{}}").is_err());
+    }
+}
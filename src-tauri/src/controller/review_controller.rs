@@ -1,160 +1,366 @@
-use chrono::{DateTime, NaiveDate, Utc};
-use fsrs::{ComputeParametersInput, FSRSItem, FSRSReview, FSRS};
-use serde_json::Value;
-use sqlx::{Result, SqlitePool};
+use crate::controller::statistics_controller::deck_condition;
+use crate::models::{
+    ParameterMetrics, StudyMode, TrainingDataset, TrainingResult, TrainingSkipReason,
+};
+use chrono::{DateTime, Local, NaiveDate, Utc};
+use fsrs::{CombinedProgressState, ComputeParametersInput, FSRSError, FSRSItem, FSRSReview, FSRS};
+use sqlx::{Result, SqliteExecutor, SqlitePool};
 use std::collections::HashMap;
-use std::fs;
-use std::path::Path;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
 
+/// 添加复习记录
+///
+/// `duration_ms` 为回答卡片所用的时间，未记录时为 None，`study_mode` 为复习时的学习模式
 pub async fn create_review(
-    pool: &SqlitePool,
+    executor: impl SqliteExecutor<'_>,
     card_id: u32,
     review_date: DateTime<Utc>,
     rating: u32,
+    duration_ms: Option<u32>,
+    study_mode: StudyMode,
 ) -> Result<()> {
-    sqlx::query!(
+    sqlx::query(
         "INSERT INTO reviews 
-        (card_id, review_date, rating) 
-        VALUES (?, ?, ?)",
-        card_id,
-        review_date,
-        rating
+        (card_id, review_date, rating, duration_ms, study_mode) 
+        VALUES (?, ?, ?, ?, ?)",
     )
-    .execute(pool)
+    .bind(card_id as i64)
+    .bind(review_date)
+    .bind(rating as i64)
+    .bind(duration_ms.map(|duration| duration as i64))
+    .bind(study_mode.as_str())
+    .execute(executor)
     .await?;
 
     Ok(())
 }
 
-/// 获取所有复习记录
-///
-/// 从数据库中获取所有卡片的复习记录
-pub async fn get_all_reviews(pool: &SqlitePool) -> Result<Vec<(u32, NaiveDate, u32)>> {
-    let rows = sqlx::query!(
-        "SELECT card_id, review_date, rating FROM reviews ORDER BY card_id, review_date"
-    )
-    .fetch_all(pool)
-    .await?;
+/// 获取复习记录的数量
+pub async fn count_reviews(pool: &SqlitePool) -> Result<u32> {
+    let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM reviews")
+        .fetch_one(pool)
+        .await?;
+    Ok(count as u32)
+}
 
-    let reviews = rows
-        .into_iter()
-        .map(|row| {
-            (
-                row.card_id as u32,
-                row.review_date.date(),
-                row.rating as u32,
-            )
-        })
-        .collect();
+/// FSRS 遗忘曲线的衰减参数
+///
+/// 21 个参数的 FSRS-6 使用最后一个参数，更早的 FSRS-5 参数固定为 0.5
+pub fn fsrs_decay(fsrs_params: &[f32]) -> f32 {
+    fsrs_params.get(20).copied().unwrap_or(0.5)
+}
 
-    Ok(reviews)
+/// 计算卡片当前的可提取性
+///
+/// 与 FSRS 库的遗忘曲线一致，`elapsed_days` 可以是非整数天数
+pub fn current_retrievability(stability: f32, elapsed_days: f32, decay: f32) -> f32 {
+    let factor = 0.9f32.powf(-1.0 / decay) - 1.0;
+    (elapsed_days.max(0.0) / stability * factor + 1.0).powf(-decay)
 }
 
-/// 训练FSRS模型参数
+/// 获取卡片的复习历史
 ///
-/// 使用数据库中的复习记录训练FSRS模型，返回优化后的参数
-/// 如果提供了有效的fsrs_params参数，将优先使用该参数；否则从配置文件读取
-pub async fn train_fsrs_parameters(pool: &SqlitePool, fsrs_params: [f32; 19]) -> Result<Vec<f32>> {
-    // 检查传入的参数是否有效（非零值）
-    let is_valid_params = fsrs_params.iter().any(|&x| x != 0.0);
-
-    // 如果传入的参数有效，则使用传入的参数
-    let initial_params = if is_valid_params {
-        fsrs_params.to_vec()
+/// 返回每张卡片按时间排序的复习记录（本地日期、评分），`deck_ids` 为空时获取所有卡组，
+/// `exclude_filtered` 为 true 时不包含筛选学习和临时抱佛脚的复习
+pub async fn get_review_histories(
+    pool: &SqlitePool,
+    deck_ids: &[u32],
+    exclude_filtered: bool,
+) -> Result<HashMap<u32, Vec<(NaiveDate, u32)>>> {
+    let mode_condition = if exclude_filtered {
+        format!("r.study_mode = '{}'", StudyMode::Normal.as_str())
     } else {
-        // 否则从配置文件读取初始参数
-        let config_path = Path::new("conf.json");
-        let config_content = fs::read_to_string(config_path)
-            .map_err(|e| sqlx::Error::Io(std::io::Error::new(std::io::ErrorKind::Other, e)))?;
-
-        let config: Value = serde_json::from_str(&config_content)
-            .map_err(|e| sqlx::Error::Io(std::io::Error::new(std::io::ErrorKind::Other, e)))?;
-
-        if let Some(params) = config["fsrs_params"].as_array() {
-            params
-                .iter()
-                .filter_map(|v| v.as_f64().map(|f| f as f32))
-                .collect::<Vec<f32>>()
-        } else {
-            Vec::new() // 如果没有参数，使用空向量，FSRS库会使用默认参数
-        }
+        "1".to_string()
     };
+    let query = format!(
+        "SELECT r.card_id, r.review_date, r.rating
+        FROM reviews r
+        JOIN cards c ON c.card_id = r.card_id
+        WHERE {} AND {}
+        ORDER BY r.card_id, r.review_date",
+        deck_condition(deck_ids),
+        mode_condition
+    );
+    let rows = sqlx::query_as::<_, (i64, DateTime<Utc>, i64)>(&query)
+        .fetch_all(pool)
+        .await?;
 
-    // 获取所有复习记录
-    let all_reviews = get_all_reviews(pool).await?;
+    let mut histories: HashMap<u32, Vec<(NaiveDate, u32)>> = HashMap::new();
+    for (card_id, review_date, rating) in rows {
+        histories.entry(card_id as u32).or_default().push((
+            review_date.with_timezone(&Local).date_naive(),
+            rating as u32,
+        ));
+    }
+    Ok(histories)
+}
 
-    // 按卡片ID分组
-    let mut reviews_by_card: HashMap<u32, Vec<(NaiveDate, u32)>> = HashMap::new();
-    for (card_id, review_date, rating) in all_reviews {
-        reviews_by_card
-            .entry(card_id)
-            .or_insert_with(Vec::new)
-            .push((review_date, rating));
+/// 将复习历史转换为 FSRS 的复习序列
+///
+/// 第一次复习的间隔为 0，之后每次复习的间隔为与上一次复习相差的天数
+pub fn review_history_item(history: &[(NaiveDate, u32)]) -> FSRSItem {
+    let mut last_date = history.first().map(|review| review.0);
+    FSRSItem {
+        reviews: history
+            .iter()
+            .map(|&(date, rating)| {
+                let delta_t = last_date.map_or(0, |last| (date - last).num_days().max(0) as u32);
+                last_date = Some(date);
+                FSRSReview { rating, delta_t }
+            })
+            .collect(),
     }
+}
 
-    // 转换为FSRS库需要的格式
-    let mut fsrs_items: Vec<FSRSItem> = Vec::new();
-    for (_, reviews) in reviews_by_card {
-        // 确保复习记录按日期排序
-        let mut sorted_reviews = reviews.clone();
-        sorted_reviews.sort_by(|a, b| a.0.cmp(&b.0));
+/// 训练FSRS模型所需的最少复习序列数量，不足时不训练
+const MIN_TRAINING_ITEMS: usize = 10;
 
-        if sorted_reviews.len() < 2 {
-            // 至少需要两条记录才能计算间隔
-            continue;
-        }
+/// 训练期间报告进度的时间间隔
+const TRAINING_PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
 
-        let mut last_date = sorted_reviews[0].0;
-        let mut fsrs_reviews = Vec::new();
+pub(crate) fn fsrs_error(e: fsrs::FSRSError) -> sqlx::Error {
+    sqlx::Error::Io(std::io::Error::other(e.to_string()))
+}
 
-        for (date, rating) in sorted_reviews {
-            let delta_t = (date - last_date).num_days() as u32;
-            fsrs_reviews.push(FSRSReview { rating, delta_t });
+/// 训练和评估使用的初始参数
+///
+/// 全为零的参数视为未设置，返回空向量，FSRS库会使用默认参数
+fn initial_fsrs_params(fsrs_params: &[f32]) -> Vec<f32> {
+    if fsrs_params.iter().any(|&x| x != 0.0) {
+        fsrs_params.to_vec()
+    } else {
+        Vec::new()
+    }
+}
 
-            // 为每个复习状态创建一个FSRSItem
-            let item = FSRSItem {
-                reviews: fsrs_reviews.clone(),
-            };
+/// 由复习历史构建训练和评估FSRS模型使用的复习序列
+///
+/// 间隔按本地日期计算，同一天内的多次复习间隔为 0。每张卡片在每次与上一次复习不在同一天的复习处
+/// 生成一个序列，包含从第一次复习开始的全部复习，最后一次复习为预测目标；同一天内的复习只作为历史，
+/// 第一次复习的评分用于估计初始稳定性。只复习过一天的卡片没有可预测的复习，不生成序列
+pub fn build_training_items(
+    histories: &HashMap<u32, Vec<(NaiveDate, u32)>>,
+) -> (Vec<FSRSItem>, TrainingDataset) {
+    let mut fsrs_items = Vec::new();
+    let mut dataset = TrainingDataset::default();
 
-            // 只添加包含长期记忆复习的项目
-            if item.long_term_review_cnt() > 0 {
-                fsrs_items.push(item);
+    for history in histories.values() {
+        let reviews = review_history_item(history).reviews;
+        let count_before = fsrs_items.len();
+        for (index, review) in reviews.iter().enumerate() {
+            if review.delta_t > 0 {
+                fsrs_items.push(FSRSItem {
+                    reviews: reviews[..=index].to_vec(),
+                });
             }
-
-            last_date = date;
         }
-    }
+        if fsrs_items.len() == count_before {
+            continue;
+        }
 
-    // 如果没有足够的数据，返回初始参数
-    if fsrs_items.len() < 10 {
-        return Ok(initial_params);
+        dataset.card_count += 1;
+        dataset.review_count += history.len() as u32;
+        let (first_day, last_day) = (history[0].0, history[history.len() - 1].0);
+        dataset.first_day = Some(
+            dataset
+                .first_day
+                .map_or(first_day, |day| day.min(first_day)),
+        );
+        dataset.last_day = Some(dataset.last_day.map_or(last_day, |day| day.max(last_day)));
     }
 
+    dataset.item_count = fsrs_items.len() as u32;
+    (fsrs_items, dataset)
+}
+
+/// 训练FSRS模型参数
+///
+/// 使用复习序列训练FSRS模型，返回优化后的参数
+/// 传入的fsrs_params作为初始参数，全为零时使用FSRS库的默认参数。
+/// 训练进度写入 `progress`，其中的 `want_abort` 被设置后训练中止并返回 `FSRSError::Interrupted`
+pub fn train_fsrs_parameters(
+    fsrs_items: Vec<FSRSItem>,
+    fsrs_params: &[f32],
+    progress: Option<Arc<Mutex<CombinedProgressState>>>,
+) -> std::result::Result<Vec<f32>, FSRSError> {
+    let initial_params = initial_fsrs_params(fsrs_params);
+
     // 创建FSRS实例并训练模型
     let fsrs = FSRS::new(if initial_params.is_empty() {
         None
     } else {
         Some(&initial_params)
-    })
-    .map_err(|e| {
-        sqlx::Error::Io(std::io::Error::new(
-            std::io::ErrorKind::Other,
-            e.to_string(),
-        ))
     })?;
 
     // 计算优化参数
-    let optimized_parameters = fsrs
-        .compute_parameters(ComputeParametersInput {
-            train_set: fsrs_items,
-            ..Default::default()
+    fsrs.compute_parameters(ComputeParametersInput {
+        train_set: fsrs_items,
+        progress,
+        ..Default::default()
+    })
+}
+
+/// 评估FSRS模型参数
+///
+/// 用参数预测复习序列最后一次复习的结果，返回对数损失和分箱后的均方根误差，两者都是越小越好
+pub fn evaluate_fsrs_parameters(
+    fsrs_items: &[FSRSItem],
+    fsrs_params: &[f32],
+) -> Result<ParameterMetrics> {
+    let fsrs = FSRS::new(Some(&initial_fsrs_params(fsrs_params))).map_err(fsrs_error)?;
+    let evaluation = fsrs
+        .evaluate(fsrs_items.to_vec(), |_| true)
+        .map_err(fsrs_error)?;
+    Ok(ParameterMetrics {
+        log_loss: evaluation.log_loss,
+        rmse_bins: evaluation.rmse_bins,
+    })
+}
+
+/// 训练并评估FSRS模型参数
+///
+/// 用复习记录训练候选参数，并在同一份复习记录上评估当前参数和候选参数，
+/// 对数损失更小且均方根误差不更大时视为更好。`exclude_filtered` 为 true 时不使用筛选学习和
+/// 临时抱佛脚的复习，结果中包含训练使用的复习序列、卡片、复习数量和日期范围。
+///
+/// 训练在阻塞线程中进行，期间每隔一段时间通过 `on_progress` 报告已训练和总共的样本数量，
+/// 设置 `training` 中的 `want_abort` 可以取消训练。复习记录不足或训练被取消时，
+/// 结果中的 `skipped` 说明原因，候选参数与当前参数相同
+pub async fn optimize_fsrs_parameters(
+    pool: &SqlitePool,
+    current_params: &[f32],
+    exclude_filtered: bool,
+    training: Arc<Mutex<CombinedProgressState>>,
+    mut on_progress: impl FnMut(u32, u32) + Send + 'static,
+) -> Result<TrainingResult> {
+    let histories = get_review_histories(pool, &[], exclude_filtered).await?;
+    let current_params = current_params.to_vec();
+
+    tokio::task::spawn_blocking(move || {
+        let (fsrs_items, dataset) = build_training_items(&histories);
+        let skipped = |current_params: Vec<f32>, dataset, reason| TrainingResult {
+            candidate_params: current_params.clone(),
+            current_params,
+            current: None,
+            candidate: None,
+            improved: false,
+            applied: false,
+            dataset,
+            skipped: Some(reason),
+        };
+
+        if fsrs_items.len() < MIN_TRAINING_ITEMS {
+            return Ok(skipped(
+                current_params,
+                dataset,
+                TrainingSkipReason::NotEnoughReviews,
+            ));
+        }
+
+        // 训练期间定时读取训练进度，训练结束后发送端被丢弃，报告线程随之退出
+        let (done, finished) = mpsc::channel::<()>();
+        let reporter = {
+            let training = training.clone();
+            thread::spawn(move || {
+                while let Err(RecvTimeoutError::Timeout) =
+                    finished.recv_timeout(TRAINING_PROGRESS_INTERVAL)
+                {
+                    let (current, total) = {
+                        let state = training.lock().unwrap();
+                        (state.current() as u32, state.total() as u32)
+                    };
+                    on_progress(current, total);
+                }
+            })
+        };
+        let trained = train_fsrs_parameters(fsrs_items.clone(), &current_params, Some(training));
+        drop(done);
+        let _ = reporter.join();
+
+        let candidate_params = match trained {
+            Ok(candidate_params) => candidate_params,
+            Err(FSRSError::Interrupted) => {
+                return Ok(skipped(
+                    current_params,
+                    dataset,
+                    TrainingSkipReason::Cancelled,
+                ));
+            }
+            Err(e) => return Err(fsrs_error(e)),
+        };
+
+        let current = evaluate_fsrs_parameters(&fsrs_items, &current_params)?;
+        let candidate = evaluate_fsrs_parameters(&fsrs_items, &candidate_params)?;
+        let improved =
+            candidate.log_loss < current.log_loss && candidate.rmse_bins <= current.rmse_bins;
+
+        Ok(TrainingResult {
+            current_params,
+            candidate_params,
+            current: Some(current),
+            candidate: Some(candidate),
+            improved,
+            applied: false,
+            dataset,
+            skipped: None,
         })
-        .map_err(|e| {
-            sqlx::Error::Io(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                e.to_string(),
-            ))
-        })?;
-
-    Ok(optimized_parameters)
+    })
+    .await
+    .map_err(|e| sqlx::Error::Io(std::io::Error::other(e.to_string())))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 1, day).unwrap()
+    }
+
+    #[test]
+    fn build_training_items_predicts_each_later_day() {
+        let histories =
+            HashMap::from([(1, vec![(day(1), 1), (day(1), 3), (day(3), 3), (day(8), 4)])]);
+        let (items, dataset) = build_training_items(&histories);
+
+        let sequences: Vec<Vec<(u32, u32)>> = items
+            .iter()
+            .map(|item| {
+                item.reviews
+                    .iter()
+                    .map(|review| (review.rating, review.delta_t))
+                    .collect()
+            })
+            .collect();
+        assert_eq!(
+            sequences,
+            [
+                vec![(1, 0), (3, 0), (3, 2)],
+                vec![(1, 0), (3, 0), (3, 2), (4, 5)],
+            ]
+        );
+        assert_eq!(dataset.item_count, 2);
+        assert_eq!(dataset.card_count, 1);
+        assert_eq!(dataset.review_count, 4);
+        assert_eq!(dataset.first_day, Some(day(1)));
+        assert_eq!(dataset.last_day, Some(day(8)));
+    }
+
+    #[test]
+    fn build_training_items_skips_cards_reviewed_on_one_day() {
+        let histories = HashMap::from([
+            (1, vec![(day(2), 1), (day(2), 3)]),
+            (2, vec![(day(5), 3), (day(9), 3)]),
+        ]);
+        let (items, dataset) = build_training_items(&histories);
+
+        assert_eq!(items.len(), 1);
+        assert_eq!(dataset.card_count, 1);
+        assert_eq!(dataset.review_count, 2);
+        assert_eq!(dataset.first_day, Some(day(5)));
+        assert_eq!(dataset.last_day, Some(day(9)));
+    }
 }
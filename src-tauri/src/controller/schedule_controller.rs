@@ -0,0 +1,349 @@
+use crate::controller::review_controller::{fsrs_error, get_review_histories, review_history_item};
+use crate::controller::statistics_controller::deck_condition;
+use crate::models::{RecomputeSummary, RescheduleSummary};
+use chrono::{DateTime, Duration, Local, NaiveDate, Utc};
+use fsrs::{CombinedProgressState, MemoryState, FSRS};
+use sqlx::{Result, SqliteExecutor, SqlitePool};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// 重新安排后的最长间隔天数
+const MAX_INTERVAL_DAYS: f32 = 36500.0;
+
+/// 没有记录难度系数的旧卡片使用的 SM-2 默认难度系数
+const DEFAULT_EASE_FACTOR: f32 = 2.5;
+
+/// SM-2 难度系数的最小值
+const MIN_EASE_FACTOR: f32 = 1.3;
+
+/// 各评分（1 到 4）对 SM-2 难度系数的调整，与 Anki 的调整一致
+const EASE_FACTOR_DELTAS: [f32; 4] = [-0.2, -0.15, 0.0, 0.15];
+
+/// 估算旧卡片记忆状态时假定的 SM-2 记忆留存率
+const SM2_RETENTION: f32 = 0.9;
+
+/// 需要计算记忆状态的卡片
+struct ScheduleCard {
+    card_id: u32,
+    due: DateTime<Utc>,
+    last_review: Option<DateTime<Utc>>,
+    scheduled_days: u32,
+    ease_factor: Option<f32>,
+}
+
+/// 记忆状态的来源
+enum MemorySource {
+    History,
+    Legacy,
+}
+
+/// 重新计算后的卡片状态，`schedule` 为 None 时保持原有的间隔和到期时间
+struct CardUpdate {
+    card_id: u32,
+    last_review: DateTime<Utc>, // 计算时读取的最后一次复习时间
+    due: DateTime<Utc>,         // 计算时读取的到期时间
+    memory_state: MemoryState,
+    source: MemorySource,
+    schedule: Option<(u32, DateTime<Utc>)>,
+}
+
+/// 按回答的评分更新卡片的 SM-2 难度系数
+///
+/// 没有难度系数的卡片从默认难度系数开始调整，复习记录无法重放时用于估算记忆状态
+pub async fn update_ease_factor(
+    executor: impl SqliteExecutor<'_>,
+    card_id: u32,
+    rating: u32,
+) -> Result<()> {
+    let delta = EASE_FACTOR_DELTAS
+        .get((rating as usize).wrapping_sub(1))
+        .copied()
+        .unwrap_or(0.0);
+    sqlx::query(
+        "UPDATE cards SET ease_factor = MAX(?, COALESCE(ease_factor, ?) + ?) WHERE card_id = ?",
+    )
+    .bind(MIN_EASE_FACTOR)
+    .bind(DEFAULT_EASE_FACTOR)
+    .bind(delta)
+    .bind(card_id as i64)
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+/// 读取卡组中的卡片和它们的复习历史
+async fn load_schedule_cards(
+    pool: &SqlitePool,
+    deck_ids: &[u32],
+) -> Result<(Vec<ScheduleCard>, HashMap<u32, Vec<(NaiveDate, u32)>>)> {
+    let query = format!(
+        "SELECT c.card_id, c.due, c.last_review, c.scheduled_days, c.ease_factor
+        FROM cards c
+        WHERE {}",
+        deck_condition(deck_ids)
+    );
+    let cards =
+        sqlx::query_as::<_, (i64, DateTime<Utc>, Option<DateTime<Utc>>, i64, Option<f32>)>(&query)
+            .fetch_all(pool)
+            .await?
+            .into_iter()
+            .map(
+                |(card_id, due, last_review, scheduled_days, ease_factor)| ScheduleCard {
+                    card_id: card_id as u32,
+                    due,
+                    last_review,
+                    scheduled_days: scheduled_days.max(0) as u32,
+                    ease_factor,
+                },
+            )
+            .collect();
+    let histories = get_review_histories(pool, deck_ids, false).await?;
+    Ok((cards, histories))
+}
+
+/// 计算卡片的记忆状态
+///
+/// 有复习记录时使用FSRS重放全部复习记录；没有复习记录但有间隔的旧卡片，
+/// 使用间隔和 SM-2 难度系数估算记忆状态。新卡片和无法计算的卡片返回 None
+fn card_memory_state(
+    fsrs: &FSRS,
+    card: &ScheduleCard,
+    history: &[(NaiveDate, u32)],
+) -> Option<(MemoryState, MemorySource)> {
+    card.last_review?;
+    if !history.is_empty() {
+        return fsrs
+            .memory_state(review_history_item(history), None)
+            .ok()
+            .map(|memory_state| (memory_state, MemorySource::History));
+    }
+    if card.scheduled_days > 0 {
+        return fsrs
+            .memory_state_from_sm2(
+                card.ease_factor.unwrap_or(DEFAULT_EASE_FACTOR),
+                card.scheduled_days as f32,
+                SM2_RETENTION,
+            )
+            .ok()
+            .map(|memory_state| (memory_state, MemorySource::Legacy));
+    }
+    None
+}
+
+/// 在阻塞线程中计算卡片的记忆状态
+///
+/// 重放复习记录是计算密集的操作，不在异步运行时中执行。`schedule` 为有记忆状态的卡片计算新的间隔和
+/// 到期时间，返回 None 时保持原有的间隔和到期时间。返回需要写入数据库的卡片状态和没有记忆状态的卡片数量，
+/// `abort` 中的 `want_abort` 被设置时停止计算并返回 None
+async fn compute_memory_states<F, P>(
+    cards: Vec<ScheduleCard>,
+    mut histories: HashMap<u32, Vec<(NaiveDate, u32)>>,
+    fsrs_params: &[f32],
+    abort: Arc<Mutex<CombinedProgressState>>,
+    mut schedule: F,
+    mut progress: P,
+) -> Result<Option<(Vec<CardUpdate>, u32)>>
+where
+    F: FnMut(&FSRS, &MemoryState, DateTime<Utc>) -> Option<(u32, DateTime<Utc>)> + Send + 'static,
+    P: FnMut(u32, u32) + Send + 'static,
+{
+    let fsrs_params = fsrs_params.to_vec();
+    tokio::task::spawn_blocking(move || {
+        let fsrs = FSRS::new(Some(&fsrs_params)).map_err(fsrs_error)?;
+        let total = cards.len() as u32;
+        let report_every = (total / 100).max(1);
+        let mut updates = Vec::new();
+        let mut skipped = 0;
+
+        progress(0, total);
+        for (index, card) in cards.iter().enumerate() {
+            if abort.lock().unwrap().want_abort {
+                return Ok(None);
+            }
+
+            let history = histories.remove(&card.card_id).unwrap_or_default();
+            match (card_memory_state(&fsrs, card, &history), card.last_review) {
+                (Some((memory_state, source)), Some(last_review)) => {
+                    updates.push(CardUpdate {
+                        card_id: card.card_id,
+                        last_review,
+                        due: card.due,
+                        schedule: schedule(&fsrs, &memory_state, last_review),
+                        memory_state,
+                        source,
+                    });
+                }
+                _ => skipped += 1,
+            }
+
+            let current = index as u32 + 1;
+            if current.is_multiple_of(report_every) || current == total {
+                progress(current, total);
+            }
+        }
+        Ok(Some((updates, skipped)))
+    })
+    .await
+    .map_err(|e| sqlx::Error::Io(std::io::Error::other(e.to_string())))?
+}
+
+/// 在同一个事务中写入卡片状态
+///
+/// 计算期间被复习过或已被删除的卡片保持不变，返回实际写入的卡片状态
+async fn save_card_updates(pool: &SqlitePool, updates: Vec<CardUpdate>) -> Result<Vec<CardUpdate>> {
+    let modified_at = Utc::now();
+    let mut saved = Vec::with_capacity(updates.len());
+    let mut tx = pool.begin().await?;
+    for update in updates {
+        let last_review = sqlx::query_as::<_, (Option<DateTime<Utc>>,)>(
+            "SELECT last_review FROM cards WHERE card_id = ?",
+        )
+        .bind(update.card_id as i64)
+        .fetch_optional(&mut *tx)
+        .await?;
+        if last_review != Some((Some(update.last_review),)) {
+            continue;
+        }
+
+        match update.schedule {
+            Some((scheduled_days, due)) => {
+                sqlx::query(
+                    "UPDATE cards
+                    SET stability = ?, difficulty = ?, scheduled_days = ?, due = ?,
+                        modified_at = ?
+                    WHERE card_id = ?",
+                )
+                .bind(update.memory_state.stability)
+                .bind(update.memory_state.difficulty)
+                .bind(scheduled_days as i64)
+                .bind(due)
+                .bind(modified_at)
+                .bind(update.card_id as i64)
+                .execute(&mut *tx)
+                .await?;
+            }
+            None => {
+                sqlx::query(
+                    "UPDATE cards SET stability = ?, difficulty = ?, modified_at = ?
+                    WHERE card_id = ?",
+                )
+                .bind(update.memory_state.stability)
+                .bind(update.memory_state.difficulty)
+                .bind(modified_at)
+                .bind(update.card_id as i64)
+                .execute(&mut *tx)
+                .await?;
+            }
+        }
+        saved.push(update);
+    }
+    tx.commit().await?;
+    Ok(saved)
+}
+
+/// 重新安排卡片的复习时间
+///
+/// 使用当前的FSRS参数重新计算每张卡片的记忆状态，再按记忆留存率计算新的间隔，
+/// 新的到期时间为最后一次复习时间加上新的间隔。`deck_ids` 为空时重新安排所有卡组，
+/// 计算过程中通过 `progress` 报告已处理和总共的卡片数量。计算期间被复习的卡片保持不变，
+/// 通过 `abort` 取消时不修改任何卡片
+pub async fn reschedule_cards(
+    pool: &SqlitePool,
+    deck_ids: &[u32],
+    fsrs_params: &[f32],
+    desired_retention: f32,
+    abort: Arc<Mutex<CombinedProgressState>>,
+    progress: impl FnMut(u32, u32) + Send + 'static,
+) -> Result<RescheduleSummary> {
+    let (cards, histories) = load_schedule_cards(pool, deck_ids).await?;
+    let mut summary = RescheduleSummary {
+        total: cards.len() as u32,
+        ..Default::default()
+    };
+
+    let computed = compute_memory_states(
+        cards,
+        histories,
+        fsrs_params,
+        abort,
+        move |fsrs, memory_state, last_review| {
+            let interval = fsrs
+                .next_interval(Some(memory_state.stability), desired_retention, 1)
+                .round()
+                .clamp(1.0, MAX_INTERVAL_DAYS) as u32;
+            Some((interval, last_review + Duration::days(interval as i64)))
+        },
+        progress,
+    )
+    .await?;
+    let Some((updates, skipped)) = computed else {
+        summary.cancelled = true;
+        return Ok(summary);
+    };
+
+    let computed_count = updates.len() as u32;
+    let saved = save_card_updates(pool, updates).await?;
+    summary.skipped = skipped;
+    summary.reviewed_during_job = computed_count - saved.len() as u32;
+    for update in saved {
+        let Some((_, due)) = update.schedule else {
+            continue;
+        };
+        let old_day = update.due.with_timezone(&Local).date_naive();
+        let new_day = due.with_timezone(&Local).date_naive();
+        match new_day.cmp(&old_day) {
+            Ordering::Less => summary.earlier += 1,
+            Ordering::Greater => summary.later += 1,
+            Ordering::Equal => summary.unchanged += 1,
+        }
+        summary.rescheduled += 1;
+    }
+    Ok(summary)
+}
+
+/// 重新计算卡片的记忆状态
+///
+/// 使用当前的FSRS参数重放每张卡片的复习记录，重建稳定性和难度，卡片的到期时间保持不变。
+/// 没有复习记录但有间隔的旧卡片（如从 SM-2 类算法导入的卡片）使用间隔和难度系数估算记忆状态。
+/// `deck_ids` 为空时处理所有卡组，计算过程中通过 `progress` 报告进度。
+/// 计算期间被复习的卡片保持不变，通过 `abort` 取消时不修改任何卡片
+pub async fn recompute_memory_states(
+    pool: &SqlitePool,
+    deck_ids: &[u32],
+    fsrs_params: &[f32],
+    abort: Arc<Mutex<CombinedProgressState>>,
+    progress: impl FnMut(u32, u32) + Send + 'static,
+) -> Result<RecomputeSummary> {
+    let (cards, histories) = load_schedule_cards(pool, deck_ids).await?;
+    let mut summary = RecomputeSummary {
+        total: cards.len() as u32,
+        ..Default::default()
+    };
+
+    let computed = compute_memory_states(
+        cards,
+        histories,
+        fsrs_params,
+        abort,
+        |_, _, _| None,
+        progress,
+    )
+    .await?;
+    let Some((updates, skipped)) = computed else {
+        summary.cancelled = true;
+        return Ok(summary);
+    };
+
+    let computed_count = updates.len() as u32;
+    let saved = save_card_updates(pool, updates).await?;
+    summary.skipped = skipped;
+    summary.reviewed_during_job = computed_count - saved.len() as u32;
+    for update in saved {
+        match update.source {
+            MemorySource::History => summary.from_history += 1,
+            MemorySource::Legacy => summary.from_legacy += 1,
+        }
+    }
+    Ok(summary)
+}
@@ -0,0 +1,813 @@
+use crate::controller::card_controller::{annotate_cards, get_cards_by_ids, update_card_state};
+use crate::controller::deck_controller::get_deck_queue_options;
+use crate::controller::invalid_input;
+use crate::controller::queue_controller::{
+    build_extra_study_queue, build_study_queue, merge_deck_queues,
+};
+use crate::controller::review_controller::{create_review, fsrs_error};
+use crate::controller::schedule_controller::update_ease_factor;
+use crate::models::{
+    Card, DeckStudyProgress, NextIntervals, ReviewOrder, StudyCard, StudyCounts, StudyMode,
+    StudyProgress,
+};
+use chrono::{DateTime, Duration, Local, Utc};
+use fsrs::{ItemState, NextStates, FSRS};
+use sqlx::{Result, Sqlite, SqlitePool, Transaction};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// 当天需要再次学习的卡片重新加入队列时，与当前位置间隔的卡片数
+pub const INTRADAY_REQUEUE_GAP: usize = 4;
+
+/// 保存的队列中相邻卡片位置的间隔，重新加入队列的卡片取前后两张卡片位置的中间值
+pub const POSITION_GAP: i64 = 1024;
+
+/// 学习会话
+///
+/// 保存一次学习的剩余队列、当前展示的卡片及其各评分的下一状态和答题统计，
+/// 当天需要再次学习的卡片在答题后重新加入队列。一个会话可以同时学习多个牌组
+pub struct StudySession {
+    pub session_id: u32,
+    pub deck_ids: Vec<u32>,
+    pub mode: StudyMode,
+    queue: VecDeque<Card>,
+    requeued: HashSet<u32>,       // 本次会话中答过且当天需要再次学习的卡片
+    positions: HashMap<u32, i64>, // 剩余卡片（包括当前展示的卡片）在保存的队列中的位置
+    changed: HashSet<u32>,        // 上次保存之后位置改变或移出队列的卡片
+    current: Option<(Card, NextStates)>,
+    answered: u32,
+    deck_answered: HashMap<u32, u32>, // 各牌组的答题数
+    correct: u32,
+    started_at: DateTime<Utc>,
+    elapsed_ms: u64,           // 本次打开会话之前已学习的时间
+    resumed_at: DateTime<Utc>, // 本次打开会话的时间，应用关闭期间不计入学习时间
+}
+
+/// 保存的队列中的一张卡片
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SessionCard {
+    pub card_id: u32,
+    pub position: i64,
+    pub requeued: bool,
+}
+
+/// 保存到数据库的学习会话
+///
+/// 从会话生成时 `cards` 只包含上次保存之后改变的卡片，从数据库加载时包含保存的全部卡片。
+/// 当前展示但未回答的卡片保持原来的位置，即剩余卡片的最前面
+pub struct StudySessionSnapshot {
+    pub session_id: u32,
+    pub mode: StudyMode,
+    pub decks: Vec<(u32, u32)>, // 会话的牌组及各牌组的答题数
+    pub cards: Vec<SessionCard>,
+    pub removed_card_ids: Vec<u32>, // 上次保存之后移出队列的卡片
+    pub answered: u32,
+    pub correct: u32,
+    pub elapsed_ms: u64,
+    pub started_at: DateTime<Utc>,
+}
+
+impl StudySession {
+    pub fn new(
+        session_id: u32,
+        deck_ids: Vec<u32>,
+        mode: StudyMode,
+        cards: Vec<Card>,
+        now: DateTime<Utc>,
+    ) -> Self {
+        let positions: HashMap<u32, i64> = cards
+            .iter()
+            .enumerate()
+            .map(|(index, card)| (card.card_id, index as i64 * POSITION_GAP))
+            .collect();
+        Self {
+            session_id,
+            deck_ids,
+            mode,
+            changed: positions.keys().copied().collect(),
+            positions,
+            queue: cards.into(),
+            requeued: HashSet::new(),
+            current: None,
+            answered: 0,
+            deck_answered: HashMap::new(),
+            correct: 0,
+            started_at: now,
+            elapsed_ms: 0,
+            resumed_at: now,
+        }
+    }
+
+    /// 从保存的会话恢复
+    ///
+    /// `cards` 为剩余卡片的最新状态，保存的队列中不在 `cards` 里的卡片在下次保存时删除
+    pub fn restore(snapshot: StudySessionSnapshot, cards: Vec<Card>, now: DateTime<Utc>) -> Self {
+        let card_ids: HashSet<u32> = cards.iter().map(|card| card.card_id).collect();
+        let (saved, removed): (Vec<SessionCard>, Vec<SessionCard>) = snapshot
+            .cards
+            .into_iter()
+            .partition(|card| card_ids.contains(&card.card_id));
+        Self {
+            session_id: snapshot.session_id,
+            deck_ids: snapshot.decks.iter().map(|(deck_id, _)| *deck_id).collect(),
+            mode: snapshot.mode,
+            queue: cards.into(),
+            requeued: saved
+                .iter()
+                .filter(|card| card.requeued)
+                .map(|card| card.card_id)
+                .collect(),
+            positions: saved
+                .iter()
+                .map(|card| (card.card_id, card.position))
+                .collect(),
+            changed: removed.iter().map(|card| card.card_id).collect(),
+            current: None,
+            answered: snapshot.answered,
+            deck_answered: snapshot.decks.into_iter().collect(),
+            correct: snapshot.correct,
+            started_at: snapshot.started_at,
+            elapsed_ms: snapshot.elapsed_ms,
+            resumed_at: now,
+        }
+    }
+
+    /// 生成要保存的会话，只包含上次保存之后改变的卡片
+    pub fn snapshot(&mut self, now: DateTime<Utc>) -> StudySessionSnapshot {
+        let mut cards = Vec::new();
+        let mut removed_card_ids = Vec::new();
+        for card_id in self.changed.drain() {
+            match self.positions.get(&card_id) {
+                Some(position) => cards.push(SessionCard {
+                    card_id,
+                    position: *position,
+                    requeued: self.requeued.contains(&card_id),
+                }),
+                None => removed_card_ids.push(card_id),
+            }
+        }
+        StudySessionSnapshot {
+            session_id: self.session_id,
+            mode: self.mode,
+            decks: self
+                .deck_ids
+                .iter()
+                .map(|deck_id| (*deck_id, self.deck_answered(*deck_id)))
+                .collect(),
+            cards,
+            removed_card_ids,
+            answered: self.answered,
+            correct: self.correct,
+            elapsed_ms: self.elapsed(now),
+            started_at: self.started_at,
+        }
+    }
+
+    /// 会话保存失败时重新标记其中的卡片，下次保存时一并写入
+    pub fn save_failed(&mut self, snapshot: &StudySessionSnapshot) {
+        let card_ids = snapshot.cards.iter().map(|card| card.card_id);
+        self.changed
+            .extend(card_ids.chain(snapshot.removed_card_ids.iter().copied()));
+    }
+
+    /// 将卡片移出队列，下次保存时从保存的队列中删除
+    fn remove_card(&mut self, card_id: u32) {
+        self.requeued.remove(&card_id);
+        self.positions.remove(&card_id);
+        self.changed.insert(card_id);
+    }
+
+    /// 将卡片插入队列的 `index` 处，位置取前后两张卡片的中间值，没有空余位置时重新编号整个队列
+    fn insert_card(&mut self, index: usize, card: Card) {
+        let card_id = card.card_id;
+        let position = |session: &Self, index: Option<usize>| {
+            index
+                .and_then(|index| session.queue.get(index))
+                .map(|card| session.positions[&card.card_id])
+        };
+        let previous = position(self, index.checked_sub(1));
+        let next = position(self, Some(index));
+        self.queue.insert(index, card);
+        self.changed.insert(card_id);
+
+        let position = match (previous, next) {
+            (None, None) => 0,
+            (Some(previous), None) => previous + POSITION_GAP,
+            (None, Some(next)) => next - POSITION_GAP,
+            (Some(previous), Some(next)) if next - previous > 1 => previous + (next - previous) / 2,
+            (Some(_), Some(_)) => {
+                for (index, card) in self.queue.iter().enumerate() {
+                    self.positions
+                        .insert(card.card_id, index as i64 * POSITION_GAP);
+                    self.changed.insert(card.card_id);
+                }
+                return;
+            }
+        };
+        self.positions.insert(card_id, position);
+    }
+
+    fn deck_answered(&self, deck_id: u32) -> u32 {
+        self.deck_answered.get(&deck_id).copied().unwrap_or(0)
+    }
+
+    fn elapsed(&self, now: DateTime<Utc>) -> u64 {
+        self.elapsed_ms + (now - self.resumed_at).num_milliseconds().max(0) as u64
+    }
+
+    /// 剩余卡片用到的模板
+    pub fn template_ids(&self) -> HashSet<u32> {
+        let current = self.current.as_ref().map(|(card, _)| card);
+        self.queue
+            .iter()
+            .chain(current)
+            .map(|card| card.template_id)
+            .collect()
+    }
+
+    /// 剩余卡片的数量，包括当前展示的卡片，`deck_id` 为 None 时统计所有牌组
+    pub fn counts(&self, deck_id: Option<u32>) -> StudyCounts {
+        let mut counts = StudyCounts::default();
+        let current = self.current.as_ref().map(|(card, _)| card);
+        let cards = self.queue.iter().chain(current);
+        for card in cards.filter(|card| deck_id.is_none_or(|deck_id| card.deck_id == deck_id)) {
+            if self.requeued.contains(&card.card_id) {
+                counts.learning_count += 1;
+            } else if card.last_review.is_none() {
+                counts.new_count += 1;
+            } else {
+                counts.review_count += 1;
+            }
+        }
+        counts
+    }
+
+    pub fn progress(&self, now: DateTime<Utc>) -> StudyProgress {
+        StudyProgress {
+            session_id: self.session_id,
+            mode: self.mode,
+            deck_ids: self.deck_ids.clone(),
+            decks: self
+                .deck_ids
+                .iter()
+                .map(|deck_id| DeckStudyProgress {
+                    deck_id: *deck_id,
+                    counts: self.counts(Some(*deck_id)),
+                    answered: self.deck_answered(*deck_id),
+                })
+                .collect(),
+            counts: self.counts(None),
+            answered: self.answered,
+            correct: self.correct,
+            elapsed_ms: self.elapsed(now),
+        }
+    }
+
+    /// 当前展示的卡片，没有展示中的卡片时返回 None
+    pub fn current_card(&self, now: DateTime<Utc>) -> Option<StudyCard> {
+        let (card, next_states) = self.current.as_ref()?;
+        Some(StudyCard {
+            card: card.clone(),
+            intervals: next_intervals(next_states),
+            progress: self.progress(now),
+        })
+    }
+
+    /// 队列中下一张要展示的卡片ID，已有展示中的卡片或队列为空时返回 None
+    pub fn next_card_id(&self) -> Option<u32> {
+        if self.current.is_some() {
+            return None;
+        }
+        self.queue.front().map(|card| card.card_id)
+    }
+
+    /// 从队列取出卡片 `card_id` 并展示
+    ///
+    /// `latest` 为卡片在数据库中的最新状态，各评分的下一状态按最新状态计算。
+    /// 卡片已删除或已不能在会话中学习（例如普通学习时在其他学习会话中已回答）时移出队列而不展示。
+    /// 已有展示中的卡片或队列最前面不是该卡片时不做任何修改
+    pub fn show_card(
+        &mut self,
+        card_id: u32,
+        latest: Option<Card>,
+        fsrs: &FSRS,
+        desired_retention: f32,
+        now: DateTime<Utc>,
+    ) -> Result<()> {
+        if self.next_card_id() != Some(card_id) {
+            return Ok(());
+        }
+        match latest.filter(|card| is_studiable(self.mode, card, now)) {
+            Some(card) => {
+                let next_states = preview_next_states(fsrs, &card, desired_retention, now)?;
+                self.queue.pop_front();
+                self.current = Some((card, next_states));
+            }
+            None => {
+                self.queue.pop_front();
+                self.remove_card(card_id);
+            }
+        }
+        Ok(())
+    }
+
+    /// 取出要回答的卡片
+    ///
+    /// 校验评分以及卡片是否为当前展示的卡片，成功时取出当前卡片及其各评分的下一状态，
+    /// 同一张卡片不会被重复回答
+    pub fn take_answer(&mut self, card_id: u32, rating: u32) -> Result<(Card, NextStates)> {
+        match &self.current {
+            Some((card, next_states)) if card.card_id == card_id => {
+                rated_state(next_states, rating)?;
+            }
+            Some(_) => return Err(invalid_input("该卡片不是当前学习的卡片")),
+            None => return Err(invalid_input("当前没有正在学习的卡片")),
+        }
+        Ok(self.current.take().unwrap())
+    }
+
+    /// 回答未能保存时放回卡片，之后可以重新回答
+    pub fn restore_answer(&mut self, card: Card, next_states: NextStates) {
+        self.current = Some((card, next_states));
+    }
+
+    /// 卡片在展示之后已被其他地方修改时跳过该卡片，不计入答题统计
+    pub fn skip_answer(&mut self, card: Card) {
+        self.remove_card(card.card_id);
+    }
+
+    /// 记录回答结果
+    ///
+    /// 卡片在回答时间 `now` 的当天需要再次学习时以新的状态重新加入队列
+    pub fn finish_answer(&mut self, card: Card, rating: u32, now: DateTime<Utc>) {
+        self.answered += 1;
+        *self.deck_answered.entry(card.deck_id).or_insert(0) += 1;
+        if rating > 1 {
+            self.correct += 1;
+        }
+
+        if is_due_today(&card, now) {
+            self.requeued.insert(card.card_id);
+            self.insert_card(INTRADAY_REQUEUE_GAP.min(self.queue.len()), card);
+        } else {
+            self.remove_card(card.card_id);
+        }
+    }
+}
+
+/// 所有打开的学习会话，以会话 ID 为键
+#[derive(Default)]
+pub struct StudySessions {
+    sessions: HashMap<u32, StudySession>,
+}
+
+impl StudySessions {
+    pub fn insert(&mut self, session: StudySession) {
+        self.sessions.insert(session.session_id, session);
+    }
+
+    /// 已打开的会话 ID
+    pub fn session_ids(&self) -> Vec<u32> {
+        self.sessions.keys().copied().collect()
+    }
+
+    pub fn get_mut(&mut self, session_id: u32) -> Result<&mut StudySession> {
+        self.sessions
+            .get_mut(&session_id)
+            .ok_or_else(|| invalid_input("学习会话不存在或已结束"))
+    }
+
+    pub fn remove(&mut self, session_id: u32) -> Result<StudySession> {
+        self.sessions
+            .remove(&session_id)
+            .ok_or_else(|| invalid_input("学习会话不存在或已结束"))
+    }
+
+    pub fn clear(&mut self) {
+        self.sessions.clear();
+    }
+}
+
+/// 卡片是否在今天（本地时间）或之前到期
+fn is_due_today(card: &Card, now: DateTime<Utc>) -> bool {
+    card.due.with_timezone(&Local).date_naive() <= now.with_timezone(&Local).date_naive()
+}
+
+/// 卡片是否可以在该学习模式的会话中学习
+///
+/// 普通学习只学习今天到期的卡片，筛选学习和临时抱佛脚学习所有学过的卡片
+fn is_studiable(mode: StudyMode, card: &Card, now: DateTime<Utc>) -> bool {
+    match mode {
+        StudyMode::Normal => is_due_today(card, now),
+        StudyMode::Filtered | StudyMode::Cram => card.last_review.is_some(),
+    }
+}
+
+/// 计算卡片在各评分下的下一状态
+pub fn preview_next_states(
+    fsrs: &FSRS,
+    card: &Card,
+    desired_retention: f32,
+    now: DateTime<Utc>,
+) -> Result<NextStates> {
+    let elapsed_days = match card.last_review {
+        Some(last_review) => (now - last_review).num_days().max(0) as u32,
+        None => 0,
+    };
+    fsrs.next_states(card.memory_state, desired_retention, elapsed_days)
+        .map_err(fsrs_error)
+}
+
+/// 各评分对应的间隔天数
+pub fn next_intervals(next_states: &NextStates) -> NextIntervals {
+    NextIntervals {
+        again: next_states.again.interval.round() as u32,
+        hard: next_states.hard.interval.round() as u32,
+        good: next_states.good.interval.round() as u32,
+        easy: next_states.easy.interval.round() as u32,
+    }
+}
+
+/// 评分对应的下一状态，评分为 1（忘记）到 4（简单）
+pub fn rated_state(next_states: &NextStates, rating: u32) -> Result<ItemState> {
+    match rating {
+        1 => Ok(next_states.again.clone()),
+        2 => Ok(next_states.hard.clone()),
+        3 => Ok(next_states.good.clone()),
+        4 => Ok(next_states.easy.clone()),
+        _ => Err(invalid_input("评分必须在 1 到 4 之间")),
+    }
+}
+
+/// 确定学习会话包含的牌组
+///
+/// `deck_ids` 为空时学习所有牌组，否则去掉重复的牌组并检查牌组是否存在
+pub async fn get_session_deck_ids(pool: &SqlitePool, deck_ids: Vec<u32>) -> Result<Vec<u32>> {
+    let all_deck_ids: Vec<u32> =
+        sqlx::query_as::<_, (i64,)>("SELECT deck_id FROM decks ORDER BY deck_id")
+            .fetch_all(pool)
+            .await?
+            .into_iter()
+            .map(|(deck_id,)| deck_id as u32)
+            .collect();
+    if deck_ids.is_empty() {
+        return Ok(all_deck_ids);
+    }
+
+    let mut session_deck_ids = Vec::with_capacity(deck_ids.len());
+    for deck_id in deck_ids {
+        if !all_deck_ids.contains(&deck_id) {
+            return Err(invalid_input(format!("牌组 {} 不存在", deck_id)));
+        }
+        if !session_deck_ids.contains(&deck_id) {
+            session_deck_ids.push(deck_id);
+        }
+    }
+    Ok(session_deck_ids)
+}
+
+/// 获取各牌组今天要学习的全部卡片
+///
+/// 普通学习时每个牌组的卡片顺序和每天的上限由该牌组的学习队列选项决定，再在牌组之间轮流取卡片，
+/// 复习顺序为按牌组的牌组集中学习；
+/// 筛选学习和临时抱佛脚见 `build_extra_study_queue`
+pub async fn get_session_cards(
+    pool: &SqlitePool,
+    deck_ids: &[u32],
+    mode: StudyMode,
+    fsrs_params: &[f32],
+) -> Result<Vec<Card>> {
+    if mode != StudyMode::Normal {
+        let not_due_only = mode == StudyMode::Filtered;
+        return build_extra_study_queue(pool, deck_ids, not_due_only, fsrs_params).await;
+    }
+    let mut queues = Vec::with_capacity(deck_ids.len());
+    for deck_id in deck_ids {
+        let options = get_deck_queue_options(pool, *deck_id).await?;
+        let queue = build_study_queue(pool, *deck_id, &options, fsrs_params).await?;
+        queues.push((queue, options.review_order == ReviewOrder::Deck));
+    }
+    Ok(merge_deck_queues(queues))
+}
+
+/// 保存卡片的回答结果
+///
+/// 按评分对应的下一状态更新卡片并添加复习记录，两者在同一事务中保存，返回更新后的卡片。
+/// 复习记录的学习模式为会话的学习模式 `mode`。
+/// 卡片在展示之后已被删除或修改（例如在其他学习会话中已回答）时不做任何修改，返回 None
+pub async fn record_answer(
+    pool: &SqlitePool,
+    card: Card,
+    state: &ItemState,
+    rating: u32,
+    duration_ms: Option<u32>,
+    mode: StudyMode,
+    now: DateTime<Utc>,
+) -> Result<Option<Card>> {
+    let interval = state.interval.round() as u32;
+    let due = now + Duration::days(interval as i64);
+    let today = now.with_timezone(&Local).date_naive();
+
+    let mut tx = pool.begin().await?;
+    let last_review = sqlx::query_as::<_, (Option<DateTime<Utc>>,)>(
+        "SELECT last_review FROM cards WHERE card_id = ?",
+    )
+    .bind(card.card_id as i64)
+    .fetch_optional(&mut *tx)
+    .await?;
+    if last_review != Some((card.last_review,)) {
+        return Ok(None);
+    }
+
+    let card = Card {
+        memory_state: Some(state.memory),
+        scheduled_days: interval,
+        last_review: Some(now),
+        due,
+        retrievability: Some(1.0),
+        days_overdue: Some((today - due.with_timezone(&Local).date_naive()).num_days()),
+        ..card
+    };
+    update_card_state(
+        &mut *tx,
+        card.card_id,
+        card.memory_state,
+        card.scheduled_days,
+        card.last_review,
+        card.due,
+    )
+    .await?;
+    update_ease_factor(&mut *tx, card.card_id, rating).await?;
+    create_review(&mut *tx, card.card_id, now, rating, duration_ms, mode).await?;
+    tx.commit().await?;
+
+    Ok(Some(card))
+}
+
+type SessionRow = (i64, String, i64, i64, i64, DateTime<Utc>);
+
+/// 创建学习会话并保存到数据库
+pub async fn create_study_session(
+    pool: &SqlitePool,
+    deck_ids: Vec<u32>,
+    mode: StudyMode,
+    cards: Vec<Card>,
+    now: DateTime<Utc>,
+) -> Result<StudySession> {
+    let mut tx = pool.begin().await?;
+    let session_id = sqlx::query(
+        "INSERT INTO study_sessions (study_mode, started_at, updated_at) VALUES (?, ?, ?)",
+    )
+    .bind(mode.as_str())
+    .bind(now)
+    .bind(now)
+    .execute(&mut *tx)
+    .await?
+    .last_insert_rowid() as u32;
+    for deck_id in &deck_ids {
+        sqlx::query("INSERT INTO study_session_decks (session_id, deck_id) VALUES (?, ?)")
+            .bind(session_id)
+            .bind(deck_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    let mut session = StudySession::new(session_id, deck_ids, mode, cards, now);
+    save_session_cards(&mut tx, &session.snapshot(now)).await?;
+    tx.commit().await?;
+
+    Ok(session)
+}
+
+/// 保存学习会话的统计以及改变的剩余卡片
+pub async fn save_study_session(
+    pool: &SqlitePool,
+    snapshot: &StudySessionSnapshot,
+    now: DateTime<Utc>,
+) -> Result<()> {
+    let mut tx = pool.begin().await?;
+    sqlx::query(
+        "UPDATE study_sessions
+        SET answered = ?, correct = ?, elapsed_ms = ?, updated_at = ?
+        WHERE session_id = ?",
+    )
+    .bind(snapshot.answered)
+    .bind(snapshot.correct)
+    .bind(snapshot.elapsed_ms as i64)
+    .bind(now)
+    .bind(snapshot.session_id)
+    .execute(&mut *tx)
+    .await?;
+    for (deck_id, answered) in &snapshot.decks {
+        sqlx::query(
+            "UPDATE study_session_decks SET answered = ?
+            WHERE session_id = ? AND deck_id = ?",
+        )
+        .bind(answered)
+        .bind(snapshot.session_id)
+        .bind(deck_id)
+        .execute(&mut *tx)
+        .await?;
+    }
+    save_session_cards(&mut tx, snapshot).await?;
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// 写入会话中改变的卡片，删除移出队列的卡片
+async fn save_session_cards(
+    tx: &mut Transaction<'_, Sqlite>,
+    snapshot: &StudySessionSnapshot,
+) -> Result<()> {
+    for card_id in &snapshot.removed_card_ids {
+        sqlx::query("DELETE FROM study_session_cards WHERE session_id = ? AND card_id = ?")
+            .bind(snapshot.session_id)
+            .bind(card_id)
+            .execute(&mut **tx)
+            .await?;
+    }
+    for card in &snapshot.cards {
+        sqlx::query(
+            "INSERT OR REPLACE INTO study_session_cards
+            (session_id, card_id, position, requeued)
+            VALUES (?, ?, ?, ?)",
+        )
+        .bind(snapshot.session_id)
+        .bind(card.card_id)
+        .bind(card.position)
+        .bind(card.requeued)
+        .execute(&mut **tx)
+        .await?;
+    }
+    Ok(())
+}
+
+/// 删除保存的学习会话
+pub async fn delete_study_session(pool: &SqlitePool, session_id: u32) -> Result<()> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("DELETE FROM study_session_cards WHERE session_id = ?")
+        .bind(session_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("DELETE FROM study_session_decks WHERE session_id = ?")
+        .bind(session_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("DELETE FROM study_sessions WHERE session_id = ?")
+        .bind(session_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// 获取最近保存的学习会话ID
+///
+/// 跳过 `open_session_ids` 中已经打开的会话，没有可以继续的会话时返回 None
+pub async fn get_latest_study_session_id(
+    pool: &SqlitePool,
+    open_session_ids: &[u32],
+) -> Result<Option<u32>> {
+    let session_ids: Vec<(i64,)> =
+        sqlx::query_as("SELECT session_id FROM study_sessions ORDER BY updated_at DESC")
+            .fetch_all(pool)
+            .await?;
+    Ok(session_ids
+        .into_iter()
+        .map(|(session_id,)| session_id as u32)
+        .find(|session_id| !open_session_ids.contains(session_id)))
+}
+
+/// 从数据库加载学习会话
+///
+/// 剩余卡片使用数据库中的最新状态，已删除或已不能在会话中学习的卡片被移出队列，
+/// 会话不存在时返回 None
+pub async fn load_study_session(
+    pool: &SqlitePool,
+    session_id: u32,
+    fsrs_params: &[f32],
+    now: DateTime<Utc>,
+) -> Result<Option<StudySession>> {
+    let row: Option<SessionRow> = sqlx::query_as(
+        "SELECT session_id, study_mode, answered, correct, elapsed_ms, started_at
+        FROM study_sessions WHERE session_id = ?",
+    )
+    .bind(session_id)
+    .fetch_optional(pool)
+    .await?;
+    let Some((session_id, mode, answered, correct, elapsed_ms, started_at)) = row else {
+        return Ok(None);
+    };
+
+    let decks: Vec<(i64, i64)> = sqlx::query_as(
+        "SELECT deck_id, answered FROM study_session_decks
+        WHERE session_id = ? ORDER BY rowid",
+    )
+    .bind(session_id)
+    .fetch_all(pool)
+    .await?;
+    let saved_cards: Vec<(i64, i64, bool)> = sqlx::query_as(
+        "SELECT card_id, position, requeued FROM study_session_cards
+        WHERE session_id = ? ORDER BY position",
+    )
+    .bind(session_id)
+    .fetch_all(pool)
+    .await?;
+
+    let mode: StudyMode = mode.parse().unwrap_or_default();
+    let snapshot = StudySessionSnapshot {
+        session_id: session_id as u32,
+        mode,
+        decks: decks
+            .into_iter()
+            .map(|(deck_id, answered)| (deck_id as u32, answered as u32))
+            .collect(),
+        cards: saved_cards
+            .into_iter()
+            .map(|(card_id, position, requeued)| SessionCard {
+                card_id: card_id as u32,
+                position,
+                requeued,
+            })
+            .collect(),
+        removed_card_ids: Vec::new(),
+        answered: answered as u32,
+        correct: correct as u32,
+        elapsed_ms: elapsed_ms as u64,
+        started_at,
+    };
+
+    let card_ids: Vec<u32> = snapshot.cards.iter().map(|card| card.card_id).collect();
+    let mut cards: Vec<Card> = get_cards_by_ids(pool, &card_ids)
+        .await?
+        .into_iter()
+        .filter(|card| is_studiable(mode, card, now))
+        .collect();
+    annotate_cards(&mut cards, fsrs_params, now);
+
+    Ok(Some(StudySession::restore(snapshot, cards, now)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_session(card_ids: &[u32]) -> StudySession {
+        let cards = card_ids
+            .iter()
+            .map(|&card_id| Card {
+                card_id,
+                ..Default::default()
+            })
+            .collect();
+        let mut session = StudySession::new(1, vec![1], StudyMode::Normal, cards, Utc::now());
+        session.changed.clear();
+        session
+    }
+
+    fn card(card_id: u32) -> Card {
+        Card {
+            card_id,
+            ..Default::default()
+        }
+    }
+
+    fn positions(session: &StudySession) -> Vec<i64> {
+        session
+            .queue
+            .iter()
+            .map(|card| session.positions[&card.card_id])
+            .collect()
+    }
+
+    #[test]
+    fn insert_card_takes_the_middle_position() {
+        let mut session = new_session(&[1, 2]);
+        session.insert_card(1, card(3));
+        assert_eq!(positions(&session), [0, POSITION_GAP / 2, POSITION_GAP]);
+        assert_eq!(session.changed, HashSet::from([3]));
+    }
+
+    #[test]
+    fn insert_card_at_either_end() {
+        let mut session = new_session(&[1, 2]);
+        session.insert_card(2, card(3));
+        session.insert_card(0, card(4));
+        assert_eq!(
+            positions(&session),
+            [-POSITION_GAP, 0, POSITION_GAP, 2 * POSITION_GAP]
+        );
+
+        let mut empty = new_session(&[]);
+        empty.insert_card(0, card(5));
+        assert_eq!(positions(&empty), [0]);
+    }
+
+    #[test]
+    fn insert_card_renumbers_the_queue_without_a_free_position() {
+        let mut session = new_session(&[1, 2]);
+        session.positions.insert(2, 1);
+        session.insert_card(1, card(3));
+        assert_eq!(positions(&session), [0, POSITION_GAP, 2 * POSITION_GAP]);
+        assert_eq!(session.changed, HashSet::from([1, 2, 3]));
+    }
+}
@@ -0,0 +1,335 @@
+use crate::config::DESIRED_RETENTION_RANGE;
+use crate::controller::statistics_controller::{
+    deck_condition, get_later_day_reviews, local_day_start,
+};
+use crate::models::{
+    RetentionRecommendation, SimulationOptions, WorkloadDay, WorkloadSimulation, WorkloadSummary,
+};
+use chrono::{DateTime, Duration, Local, Utc};
+use fsrs::{simulate, Card, SimulatorConfig};
+use sqlx::{Result, SqlitePool};
+use std::collections::HashMap;
+
+/// 模拟最多的天数
+const MAX_SIMULATION_DAYS: u32 = 3650;
+
+/// 估算新卡片学习速度使用的天数
+const NEW_CARD_RATE_DAYS: i64 = 30;
+
+/// 使用复习记录估算评分分布所需的最少复习次数，不足时使用 FSRS 的默认分布
+const MIN_RATING_SAMPLES: i64 = 50;
+
+/// 扫描的记忆留存率范围（百分比），与 FSRS 计算最佳留存率的范围一致
+const SWEEP_RETENTION_PERCENT: (u32, u32) = (70, 95);
+
+/// 扫描时每个留存率模拟的次数，取平均值以减小随机波动
+const SWEEP_SEEDS: u64 = 3;
+
+/// 工作量模拟的输入
+///
+/// 由卡片集合中的卡片状态和复习记录得到，同一份输入可以用不同的留存率多次模拟
+pub struct SimulationInput {
+    days: u32,
+    cards: Vec<Card>,
+    new_card_count: usize,
+    new_cards_per_day: u32,
+    max_reviews_per_day: Option<u32>,
+    first_rating_prob: Option<[f32; 4]>,
+    review_rating_prob: Option<[f32; 3]>,
+}
+
+/// 按评分统计的次数转换为概率，样本不足时返回 None
+fn rating_prob<const N: usize>(rows: &[(i64, i64)], first_rating: i64) -> Option<[f32; N]> {
+    let mut counts = [0i64; N];
+    for &(rating, count) in rows {
+        if let Some(slot) = usize::try_from(rating - first_rating)
+            .ok()
+            .and_then(|index| counts.get_mut(index))
+        {
+            *slot += count;
+        }
+    }
+    let total: i64 = counts.iter().sum();
+    if total < MIN_RATING_SAMPLES {
+        return None;
+    }
+    Some(counts.map(|count| count as f32 / total as f32))
+}
+
+/// 读取工作量模拟的输入
+///
+/// 已复习的卡片按当前的稳定性、难度和到期时间参与模拟，未复习的卡片按每天学习的新卡片数量依次加入。
+/// 未指定每天学习的新卡片数量时，使用最近 30 天平均每天学习的新卡片数量
+pub async fn load_simulation_input(
+    pool: &SqlitePool,
+    options: &SimulationOptions,
+) -> Result<SimulationInput> {
+    let deck_condition = deck_condition(&options.deck_ids);
+    let today = Local::now().date_naive();
+
+    let query = format!(
+        "SELECT c.stability, c.difficulty, c.scheduled_days, c.last_review, c.due,
+            (SELECT COUNT(*) FROM reviews r WHERE r.card_id = c.card_id AND r.rating = 1)
+        FROM cards c
+        WHERE c.last_review IS NOT NULL AND c.stability IS NOT NULL
+            AND c.difficulty IS NOT NULL AND {}",
+        deck_condition
+    );
+    let rows = sqlx::query_as::<_, (f32, f32, i64, DateTime<Utc>, DateTime<Utc>, i64)>(&query)
+        .fetch_all(pool)
+        .await?;
+    let cards = rows
+        .into_iter()
+        .enumerate()
+        .map(
+            |(i, (stability, difficulty, scheduled_days, last_review, due, lapses))| {
+                let last_review = last_review.with_timezone(&Local).date_naive();
+                let due = due.with_timezone(&Local).date_naive();
+                Card {
+                    id: i as i64 + 1,
+                    difficulty,
+                    stability,
+                    last_date: -((today - last_review).num_days().max(0) as f32),
+                    due: (due - today).num_days().max(0) as f32,
+                    interval: scheduled_days as f32,
+                    lapses: lapses as u32,
+                }
+            },
+        )
+        .collect();
+
+    let query = format!(
+        "SELECT COUNT(*) FROM cards c WHERE c.last_review IS NULL AND {}",
+        deck_condition
+    );
+    let (new_card_count,): (i64,) = sqlx::query_as(&query).fetch_one(pool).await?;
+
+    let new_cards_per_day = match options.new_cards_per_day {
+        Some(new_cards_per_day) => new_cards_per_day,
+        None => {
+            let query = format!(
+                "SELECT COUNT(*) FROM (
+                    SELECT MIN(r.review_date) AS first_review
+                    FROM reviews r
+                    JOIN cards c ON c.card_id = r.card_id
+                    WHERE {}
+                    GROUP BY r.card_id
+                )
+                WHERE first_review >= ?",
+                deck_condition
+            );
+            let (learned,): (i64,) = sqlx::query_as(&query)
+                .bind(local_day_start(today - Duration::days(NEW_CARD_RATE_DAYS)))
+                .fetch_one(pool)
+                .await?;
+            (learned as f32 / NEW_CARD_RATE_DAYS as f32).round() as u32
+        }
+    };
+
+    // 每张卡片第一次复习的评分
+    let query = format!(
+        "SELECT r.rating, COUNT(*)
+        FROM reviews r
+        JOIN (SELECT MIN(review_id) AS review_id FROM reviews GROUP BY card_id) f
+            ON f.review_id = r.review_id
+        JOIN cards c ON c.card_id = r.card_id
+        WHERE {}
+        GROUP BY r.rating",
+        deck_condition
+    );
+    let first_ratings = sqlx::query_as::<_, (i64, i64)>(&query)
+        .fetch_all(pool)
+        .await?;
+
+    // 距上次复习至少一天且没有遗忘的复习的评分
+    let mut rating_counts: HashMap<i64, i64> = HashMap::new();
+    for (_, rating) in get_later_day_reviews(pool, &deck_condition).await? {
+        if rating > 1 {
+            *rating_counts.entry(rating as i64).or_insert(0) += 1;
+        }
+    }
+    let review_ratings: Vec<(i64, i64)> = rating_counts.into_iter().collect();
+
+    Ok(SimulationInput {
+        days: options.days.clamp(1, MAX_SIMULATION_DAYS),
+        cards,
+        new_card_count: new_card_count as usize,
+        new_cards_per_day,
+        max_reviews_per_day: options.max_reviews_per_day,
+        first_rating_prob: rating_prob(&first_ratings, 1),
+        review_rating_prob: rating_prob(&review_ratings, 2),
+    })
+}
+
+fn summarize(
+    desired_retention: f32,
+    reviews: &[u32],
+    new_cards: &[u32],
+    costs: &[f32],
+    memorized: f32,
+) -> WorkloadSummary {
+    let total_reviews = reviews.iter().sum::<u32>();
+    let total_cost_seconds = costs.iter().sum::<f32>();
+    WorkloadSummary {
+        desired_retention,
+        total_reviews,
+        total_new_cards: new_cards.iter().sum(),
+        average_reviews_per_day: total_reviews as f32 / reviews.len().max(1) as f32,
+        total_cost_seconds,
+        memorized,
+        cost_per_memorized: if memorized > 0.0 {
+            Some(total_cost_seconds / memorized)
+        } else {
+            None
+        },
+    }
+}
+
+/// 使用给定的记忆留存率模拟未来每天的工作量
+///
+/// 返回每天的复习次数、学习的新卡片数量、预计用时和预计记住的卡片数量，
+/// `seed` 相同时结果相同
+pub fn simulate_workload(
+    input: &SimulationInput,
+    fsrs_params: &[f32],
+    desired_retention: f32,
+    seed: u64,
+) -> Result<WorkloadSimulation> {
+    let today = Local::now().date_naive();
+    let deck_size = input.cards.len() + input.new_card_count;
+    let days = input.days as usize;
+
+    let (reviews, new_cards, costs, memorized) = if deck_size == 0 {
+        (
+            vec![0; days],
+            vec![0; days],
+            vec![0.0; days],
+            vec![0.0; days],
+        )
+    } else {
+        let default_config = SimulatorConfig::default();
+        let config = SimulatorConfig {
+            deck_size,
+            learn_span: days,
+            // 不限制每天的学习时间，只按每天的数量限制
+            max_cost_perday: f32::INFINITY,
+            learn_limit: input.new_cards_per_day as usize,
+            review_limit: input
+                .max_reviews_per_day
+                .map_or(usize::MAX, |limit| limit as usize),
+            first_rating_prob: input
+                .first_rating_prob
+                .unwrap_or(default_config.first_rating_prob),
+            review_rating_prob: input
+                .review_rating_prob
+                .unwrap_or(default_config.review_rating_prob),
+            ..default_config
+        };
+        let result = simulate(
+            &config,
+            fsrs_params,
+            desired_retention,
+            Some(seed),
+            Some(input.cards.clone()),
+        )
+        .map_err(|e| sqlx::Error::Io(std::io::Error::other(e.to_string())))?;
+        (
+            result
+                .review_cnt_per_day
+                .into_iter()
+                .map(|count| count as u32)
+                .collect(),
+            result
+                .learn_cnt_per_day
+                .into_iter()
+                .map(|count| count as u32)
+                .collect(),
+            result.cost_per_day,
+            result.memorized_cnt_per_day,
+        )
+    };
+
+    let summary = summarize(
+        desired_retention,
+        &reviews,
+        &new_cards,
+        &costs,
+        memorized.last().copied().unwrap_or(0.0),
+    );
+    let days = (0..days)
+        .map(|i| WorkloadDay {
+            date: today + Duration::days(i as i64),
+            reviews: reviews[i],
+            new_cards: new_cards[i],
+            cost_seconds: costs[i],
+            memorized: memorized[i],
+        })
+        .collect();
+
+    Ok(WorkloadSimulation { summary, days })
+}
+
+/// 扫描记忆留存率并推荐工作量最小的留存率
+///
+/// 在 70% 到 95% 之间按 1% 的步长模拟，每个留存率取多次模拟的平均值，
+/// 推荐每记住一张卡片所需学习时间最少的留存率
+pub fn recommend_retention(
+    input: &SimulationInput,
+    fsrs_params: &[f32],
+) -> Result<RetentionRecommendation> {
+    let (min_retention, max_retention) = DESIRED_RETENTION_RANGE;
+    let mut points = Vec::new();
+    for percent in SWEEP_RETENTION_PERCENT.0..=SWEEP_RETENTION_PERCENT.1 {
+        let desired_retention = percent as f32 / 100.0;
+        if !(min_retention..=max_retention).contains(&desired_retention) {
+            continue;
+        }
+
+        let mut reviews = vec![0; input.days as usize];
+        let mut new_cards = vec![0; input.days as usize];
+        let mut costs = vec![0.0; input.days as usize];
+        let mut memorized = 0.0;
+        for seed in 0..SWEEP_SEEDS {
+            let simulation = simulate_workload(input, fsrs_params, desired_retention, seed + 42)?;
+            for (i, day) in simulation.days.iter().enumerate() {
+                reviews[i] += day.reviews;
+                new_cards[i] += day.new_cards;
+                costs[i] += day.cost_seconds;
+            }
+            memorized += simulation.summary.memorized;
+        }
+
+        let runs = SWEEP_SEEDS as f32;
+        let reviews: Vec<u32> = reviews
+            .into_iter()
+            .map(|count| (count as f32 / runs).round() as u32)
+            .collect();
+        let new_cards: Vec<u32> = new_cards
+            .into_iter()
+            .map(|count| (count as f32 / runs).round() as u32)
+            .collect();
+        let costs: Vec<f32> = costs.into_iter().map(|cost| cost / runs).collect();
+        points.push(summarize(
+            desired_retention,
+            &reviews,
+            &new_cards,
+            &costs,
+            memorized / runs,
+        ));
+    }
+
+    let recommended = points
+        .iter()
+        .filter_map(|point| {
+            point
+                .cost_per_memorized
+                .map(|cost| (point.desired_retention, cost))
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(desired_retention, _)| desired_retention);
+
+    Ok(RetentionRecommendation {
+        recommended,
+        points,
+    })
+}
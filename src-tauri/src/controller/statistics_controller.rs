@@ -0,0 +1,453 @@
+use crate::controller::review_controller::{current_retrievability, fsrs_decay};
+use crate::models::{
+    AnswerTimeStats, CardStateCounts, DailyAnswerTime, DailyReviewCount, DayCount, Distribution,
+    DueForecast, HistogramBin, MemoryDistributions, PeriodRetention, StatisticsFilter,
+    StatisticsPeriod,
+};
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, Utc};
+use sqlx::{Result, SqlitePool};
+use std::collections::{BTreeMap, HashMap};
+
+/// 到期预测默认的天数
+const DEFAULT_FORECAST_DAYS: i64 = 30;
+
+/// 到期预测最多的天数
+const MAX_FORECAST_DAYS: i64 = 3650;
+
+/// 成熟卡片的最小间隔天数
+const MATURE_INTERVAL_DAYS: i64 = 21;
+
+/// 按本地日期统计时，数据库先按 15 分钟的时间段分组，再在这里转换为本地日期
+///
+/// 各时区的偏移都是 15 分钟的整数倍，同一时间段内的时间属于同一个本地日期，
+/// 每个时间段按其所在时刻的时区偏移（包括夏令时）转换
+const QUARTER_HOUR_SECONDS: i64 = 900;
+
+/// 时间列所在的 15 分钟时间段的 SQL 表达式
+pub(crate) fn quarter_hour(column: &str) -> String {
+    format!(
+        "CAST(strftime('%s', {}) AS INTEGER) / {}",
+        column, QUARTER_HOUR_SECONDS
+    )
+}
+
+/// 15 分钟时间段对应的本地日期
+pub(crate) fn quarter_hour_date(quarter: i64) -> NaiveDate {
+    local_date(DateTime::from_timestamp(quarter * QUARTER_HOUR_SECONDS, 0).unwrap_or_default())
+}
+
+/// UTC 时间对应的本地日期
+pub(crate) fn local_date(time: DateTime<Utc>) -> NaiveDate {
+    time.with_timezone(&Local).date_naive()
+}
+
+/// 本地日期零点对应的 UTC 时间
+pub(crate) fn local_day_start(date: NaiveDate) -> DateTime<Utc> {
+    let midnight = date.and_hms_opt(0, 0, 0).unwrap();
+    midnight
+        .and_local_timezone(Local)
+        .earliest()
+        .map(|time| time.with_timezone(&Utc))
+        .unwrap_or_else(|| midnight.and_utc())
+}
+
+/// 按卡组筛选卡片的条件，卡片表的别名为 c
+///
+/// 卡组ID为整数，直接写入 SQL
+pub(crate) fn deck_condition(deck_ids: &[u32]) -> String {
+    if deck_ids.is_empty() {
+        return "1".to_string();
+    }
+    let deck_ids = deck_ids
+        .iter()
+        .map(|deck_id| deck_id.to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!("c.deck_id IN ({})", deck_ids)
+}
+
+/// 统计查询的筛选条件
+///
+/// 时间范围转换为 UTC 时间后按顺序绑定
+struct Scope {
+    condition: String,
+    bounds: Vec<DateTime<Utc>>,
+}
+
+impl Scope {
+    /// 根据筛选条件构造 WHERE 子句的条件，`date_column` 为按时间范围筛选的列，
+    /// 为 None 时忽略时间范围
+    fn new(filter: &StatisticsFilter, date_column: Option<&str>) -> Self {
+        let mut conditions = Vec::new();
+        let mut bounds = Vec::new();
+
+        if !filter.deck_ids.is_empty() {
+            conditions.push(deck_condition(&filter.deck_ids));
+        }
+
+        if let Some(date_column) = date_column {
+            if let Some(start) = filter.start {
+                conditions.push(format!("{} >= ?", date_column));
+                bounds.push(local_day_start(start));
+            }
+            if let Some(end) = filter.end {
+                conditions.push(format!("{} < ?", date_column));
+                bounds.push(local_day_start(end + Duration::days(1)));
+            }
+        }
+
+        let condition = if conditions.is_empty() {
+            "1".to_string()
+        } else {
+            conditions.join(" AND ")
+        };
+        Self { condition, bounds }
+    }
+}
+
+/// 获取每日复习数量
+///
+/// 按本地日期统计筛选范围内每天各评分的复习次数，没有复习的日期不返回
+pub async fn get_daily_reviews(
+    pool: &SqlitePool,
+    filter: &StatisticsFilter,
+) -> Result<Vec<DailyReviewCount>> {
+    let scope = Scope::new(filter, Some("r.review_date"));
+    let query = format!(
+        "SELECT {} AS quarter,
+            SUM(r.rating = 1), SUM(r.rating = 2), SUM(r.rating = 3), SUM(r.rating = 4)
+        FROM reviews r
+        JOIN cards c ON c.card_id = r.card_id
+        WHERE {}
+        GROUP BY quarter",
+        quarter_hour("r.review_date"),
+        scope.condition
+    );
+
+    let mut query_builder = sqlx::query_as::<_, (i64, i64, i64, i64, i64)>(&query);
+    for bound in scope.bounds {
+        query_builder = query_builder.bind(bound);
+    }
+    let rows = query_builder.fetch_all(pool).await?;
+
+    let mut days: BTreeMap<NaiveDate, DailyReviewCount> = BTreeMap::new();
+    for (quarter, again, hard, good, easy) in rows {
+        let date = quarter_hour_date(quarter);
+        let day = days.entry(date).or_insert(DailyReviewCount {
+            date,
+            again: 0,
+            hard: 0,
+            good: 0,
+            easy: 0,
+        });
+        day.again += again as u32;
+        day.hard += hard as u32;
+        day.good += good as u32;
+        day.easy += easy as u32;
+    }
+    Ok(days.into_values().collect())
+}
+
+/// 获取距离同一卡片上一次复习至少一个本地日期的复习
+///
+/// 按卡片和复习时间顺序比较相邻两次复习的本地日期，同一天内的重复复习和首次学习不返回。
+/// `deck_condition` 为按卡组筛选卡片的条件，返回 (复习时间, 评分)
+pub(crate) async fn get_later_day_reviews(
+    pool: &SqlitePool,
+    deck_condition: &str,
+) -> Result<Vec<(DateTime<Utc>, u32)>> {
+    let query = format!(
+        "SELECT r.card_id, r.review_date, r.rating
+        FROM reviews r
+        JOIN cards c ON c.card_id = r.card_id
+        WHERE {}
+        ORDER BY r.card_id, r.review_date",
+        deck_condition
+    );
+    let rows = sqlx::query_as::<_, (i64, DateTime<Utc>, i64)>(&query)
+        .fetch_all(pool)
+        .await?;
+
+    let mut reviews = Vec::new();
+    let mut previous: Option<(i64, NaiveDate)> = None;
+    for (card_id, review_date, rating) in rows {
+        let day = local_date(review_date);
+        if previous.is_some_and(|(prev_card, prev_day)| prev_card == card_id && prev_day < day) {
+            reviews.push((review_date, rating as u32));
+        }
+        previous = Some((card_id, day));
+    }
+    Ok(reviews)
+}
+
+/// 获取真实留存率
+///
+/// 只统计距离同一卡片上一次复习至少一天的复习，评分不是“重来”即视为记住，
+/// 同一天内的重复复习和首次学习不计入。按日、周（周一开始）或月汇总
+pub async fn get_true_retention(
+    pool: &SqlitePool,
+    filter: &StatisticsFilter,
+    period: StatisticsPeriod,
+) -> Result<Vec<PeriodRetention>> {
+    // 上一次复习的日期需要在全部复习记录上计算，时间范围在之后筛选
+    let scope = Scope::new(filter, None);
+    let start = filter.start.map(local_day_start);
+    let end = filter
+        .end
+        .map(|end| local_day_start(end + Duration::days(1)));
+
+    let mut periods: BTreeMap<String, (u32, u32)> = BTreeMap::new();
+    for (review_date, rating) in get_later_day_reviews(pool, &scope.condition).await? {
+        if start.is_some_and(|start| review_date < start)
+            || end.is_some_and(|end| review_date >= end)
+        {
+            continue;
+        }
+        let day = local_date(review_date);
+        let period = match period {
+            StatisticsPeriod::Day => day.to_string(),
+            StatisticsPeriod::Week => {
+                (day - Duration::days(day.weekday().num_days_from_monday() as i64)).to_string()
+            }
+            StatisticsPeriod::Month => day.format("%Y-%m").to_string(),
+        };
+        let (reviews, passed) = periods.entry(period).or_insert((0, 0));
+        *reviews += 1;
+        if rating > 1 {
+            *passed += 1;
+        }
+    }
+
+    Ok(periods
+        .into_iter()
+        .map(|(period, (reviews, passed))| PeriodRetention {
+            period,
+            reviews,
+            passed,
+            retention: passed as f32 / reviews as f32,
+        })
+        .collect())
+}
+
+/// 获取到期预测
+///
+/// 统计时间范围内每天到期的已复习卡片数量，默认从今天开始预测 30 天，
+/// 范围开始之前到期的卡片计入 overdue
+pub async fn get_due_forecast(pool: &SqlitePool, filter: &StatisticsFilter) -> Result<DueForecast> {
+    let start = filter.start.unwrap_or_else(|| Local::now().date_naive());
+    let end = filter
+        .end
+        .unwrap_or(start + Duration::days(DEFAULT_FORECAST_DAYS - 1))
+        .min(start + Duration::days(MAX_FORECAST_DAYS - 1));
+    if end < start {
+        return Ok(DueForecast {
+            overdue: 0,
+            days: Vec::new(),
+        });
+    }
+
+    let scope = Scope::new(filter, None);
+    let query = format!(
+        "SELECT CASE WHEN c.due < ? THEN NULL ELSE {} END AS quarter, COUNT(*)
+        FROM cards c
+        WHERE c.last_review IS NOT NULL AND c.due < ? AND {}
+        GROUP BY quarter",
+        quarter_hour("c.due"),
+        scope.condition
+    );
+    let rows = sqlx::query_as::<_, (Option<i64>, i64)>(&query)
+        .bind(local_day_start(start))
+        .bind(local_day_start(end + Duration::days(1)))
+        .fetch_all(pool)
+        .await?;
+
+    let mut overdue = 0;
+    let mut counts = HashMap::new();
+    for (quarter, count) in rows {
+        match quarter {
+            Some(quarter) => {
+                *counts.entry(quarter_hour_date(quarter)).or_insert(0) += count as u32;
+            }
+            None => overdue = count as u32,
+        }
+    }
+
+    let days = start
+        .iter_days()
+        .take_while(|date| *date <= end)
+        .map(|date| DayCount {
+            date,
+            count: counts.get(&date).copied().unwrap_or(0),
+        })
+        .collect();
+
+    Ok(DueForecast { overdue, days })
+}
+
+/// 按区间边界统计分布，最后一个区间没有上界
+fn distribution(values: &[f32], edges: &[f32]) -> Distribution {
+    let mut bins: Vec<HistogramBin> = edges
+        .iter()
+        .enumerate()
+        .map(|(i, &start)| HistogramBin {
+            start,
+            end: edges.get(i + 1).copied(),
+            count: 0,
+        })
+        .collect();
+    for &value in values {
+        let index = edges.partition_point(|&edge| edge <= value).max(1) - 1;
+        bins[index].count += 1;
+    }
+
+    Distribution {
+        bins,
+        count: values.len() as u32,
+        average: if values.is_empty() {
+            None
+        } else {
+            Some(values.iter().sum::<f32>() / values.len() as f32)
+        },
+    }
+}
+
+/// 获取记忆状态分布
+///
+/// 统计已复习卡片的稳定性、难度和当前可提取性分布，时间范围按最后复习时间筛选。
+/// 稳定性按 1、2、4……天倍增分组，难度按整数分组，可提取性按 5% 分组
+pub async fn get_memory_distributions(
+    pool: &SqlitePool,
+    filter: &StatisticsFilter,
+    fsrs_params: &[f32],
+) -> Result<MemoryDistributions> {
+    let scope = Scope::new(filter, Some("c.last_review"));
+    let query = format!(
+        "SELECT c.stability, c.difficulty, c.last_review
+        FROM cards c
+        WHERE c.stability IS NOT NULL AND c.difficulty IS NOT NULL
+            AND c.last_review IS NOT NULL AND {}",
+        scope.condition
+    );
+    let mut query_builder = sqlx::query_as::<_, (f32, f32, DateTime<Utc>)>(&query);
+    for bound in scope.bounds {
+        query_builder = query_builder.bind(bound);
+    }
+    let rows = query_builder.fetch_all(pool).await?;
+
+    let now = Utc::now();
+    let decay = fsrs_decay(fsrs_params);
+    let stabilities: Vec<f32> = rows.iter().map(|row| row.0).collect();
+    let difficulties: Vec<f32> = rows.iter().map(|row| row.1).collect();
+    let retrievabilities: Vec<f32> = rows
+        .iter()
+        .filter(|row| row.0 > 0.0)
+        .map(|(stability, _, last_review)| {
+            let elapsed_days = (now - *last_review).num_seconds() as f32 / 86400.0;
+            current_retrievability(*stability, elapsed_days, decay)
+        })
+        .collect();
+
+    let stability_edges: Vec<f32> = std::iter::once(0.0)
+        .chain((0..12).map(|i| 2f32.powi(i)))
+        .collect();
+    let difficulty_edges: Vec<f32> = (1..=10).map(|i| i as f32).collect();
+    let retrievability_edges: Vec<f32> = (0..20).map(|i| i as f32 * 0.05).collect();
+
+    Ok(MemoryDistributions {
+        stability: distribution(&stabilities, &stability_edges),
+        difficulty: distribution(&difficulties, &difficulty_edges),
+        retrievability: distribution(&retrievabilities, &retrievability_edges),
+    })
+}
+
+/// 获取各状态的卡片数量
+///
+/// 未复习的卡片为新卡片，间隔为 0 天的为学习中，间隔小于 21 天的为年轻卡片，其余为成熟卡片。
+/// 指定时间范围时只统计最后复习时间在范围内的卡片，此时不包含新卡片
+pub async fn get_card_state_counts(
+    pool: &SqlitePool,
+    filter: &StatisticsFilter,
+) -> Result<CardStateCounts> {
+    let scope = Scope::new(filter, Some("c.last_review"));
+    let query = format!(
+        "SELECT
+            COALESCE(SUM(c.last_review IS NULL), 0),
+            COALESCE(SUM(c.last_review IS NOT NULL AND c.scheduled_days = 0), 0),
+            COALESCE(SUM(c.last_review IS NOT NULL AND c.scheduled_days > 0
+                AND c.scheduled_days < ?), 0),
+            COALESCE(SUM(c.last_review IS NOT NULL AND c.scheduled_days >= ?), 0),
+            COUNT(*)
+        FROM cards c
+        WHERE {}",
+        scope.condition
+    );
+    let mut query_builder = sqlx::query_as::<_, (i64, i64, i64, i64, i64)>(&query)
+        .bind(MATURE_INTERVAL_DAYS)
+        .bind(MATURE_INTERVAL_DAYS);
+    for bound in scope.bounds {
+        query_builder = query_builder.bind(bound);
+    }
+    let (new, learning, young, mature, total) = query_builder.fetch_one(pool).await?;
+
+    Ok(CardStateCounts {
+        new: new as u32,
+        learning: learning as u32,
+        young: young as u32,
+        mature: mature as u32,
+        total: total as u32,
+    })
+}
+
+/// 获取回答用时统计
+///
+/// 只统计记录了用时的复习，返回总体平均用时和每个本地日期的平均用时
+pub async fn get_answer_time_stats(
+    pool: &SqlitePool,
+    filter: &StatisticsFilter,
+) -> Result<AnswerTimeStats> {
+    let scope = Scope::new(filter, Some("r.review_date"));
+    let query = format!(
+        "SELECT {} AS quarter, COUNT(*), SUM(r.duration_ms)
+        FROM reviews r
+        JOIN cards c ON c.card_id = r.card_id
+        WHERE r.duration_ms IS NOT NULL AND {}
+        GROUP BY quarter",
+        quarter_hour("r.review_date"),
+        scope.condition
+    );
+    let mut query_builder = sqlx::query_as::<_, (i64, i64, i64)>(&query);
+    for bound in scope.bounds {
+        query_builder = query_builder.bind(bound);
+    }
+    let rows = query_builder.fetch_all(pool).await?;
+
+    let mut review_count = 0;
+    let mut total_ms = 0;
+    let mut day_totals: BTreeMap<NaiveDate, (u32, i64)> = BTreeMap::new();
+    for (quarter, count, sum_ms) in rows {
+        review_count += count as u32;
+        total_ms += sum_ms;
+        let (day_count, day_ms) = day_totals.entry(quarter_hour_date(quarter)).or_default();
+        *day_count += count as u32;
+        *day_ms += sum_ms;
+    }
+    let days = day_totals
+        .into_iter()
+        .map(|(date, (count, sum_ms))| DailyAnswerTime {
+            date,
+            count,
+            average_ms: sum_ms as f64 / count as f64,
+        })
+        .collect();
+
+    Ok(AnswerTimeStats {
+        review_count,
+        total_ms,
+        average_ms: if review_count == 0 {
+            None
+        } else {
+            Some(total_ms as f64 / review_count as f64)
+        },
+        days,
+    })
+}
@@ -1,6 +1,22 @@
+use crate::controller::card_controller::{merge_template_fields, split_template_fields};
+use crate::controller::invalid_input;
+use crate::controller::media_controller::media_field_file_name;
 use crate::models::Template;
 use crate::models::TemplateField;
-use sqlx::{Result, SqlitePool};
+use crate::models::{FieldType, TemplateConfig, TemplateConversion, TemplateInfo, TemplateUsage};
+use chrono::Utc;
+use regex::Regex;
+use sqlx::{Result, SqliteConnection, SqliteExecutor, SqlitePool};
+use std::collections::{HashMap, HashSet};
+use std::sync::{LazyLock, Mutex};
+
+/// 填空字段中的填空，格式为 {{c1::答案}}
+static CLOZE_DELETION: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?s)\{\{c[1-9]\d*::.+?\}\}").unwrap());
+
+/// 已编译的字段校验规则，以规则文本为键，避免每次校验都重新编译
+static FIELD_PATTERNS: LazyLock<Mutex<HashMap<String, Regex>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
 
 /// 根据模板ID或名称获取模板信息
 ///
@@ -216,28 +232,674 @@ pub async fn parse_template(pool: &SqlitePool, template_id: u32) -> Result<Templ
 }
 
 pub async fn get_template_fields(
-    pool: &SqlitePool,
+    executor: impl SqliteExecutor<'_>,
     template_id: u32,
 ) -> Result<Vec<TemplateField>> {
-    let fields = sqlx::query!(
-        "SELECT fields_id, template_id, name, is_front 
-        FROM template_fields 
-        WHERE template_id =? ORDER BY fields_id",
-        template_id
+    let fields = sqlx::query_as::<_, (i64, i64, String, bool, String, bool, Option<String>)>(
+        "SELECT fields_id, template_id, name, is_front, field_type, required, pattern
+        FROM template_fields
+        WHERE template_id = ? ORDER BY fields_id",
     )
-    .fetch_all(pool)
+    .bind(template_id as i64)
+    .fetch_all(executor)
     .await?;
 
     let mut template_fields = Vec::new();
 
-    for field in fields {
+    for (field_id, template_id, name, is_front, field_type, required, pattern) in fields {
         template_fields.push(TemplateField {
-            field_id: field.fields_id as u32,
-            template_id: field.template_id as u32,
-            name: field.name,
-            is_front: field.is_front,
+            field_id: field_id as u32,
+            template_id: template_id as u32,
+            name,
+            is_front,
+            field_type: field_type.parse().unwrap_or_default(),
+            required,
+            pattern,
         });
     }
 
     Ok(template_fields)
 }
+
+/// 更新模板字段的类型和校验约束
+///
+/// 设置字段的类型、是否必填以及内容需要匹配的正则表达式，空的正则表达式视为不设置。
+/// 新的约束只作用于之后添加或修改的卡片
+pub async fn update_template_field(
+    pool: &SqlitePool,
+    template_id: u32,
+    field_id: u32,
+    field_type: FieldType,
+    required: bool,
+    pattern: Option<String>,
+) -> Result<()> {
+    let pattern = pattern.filter(|p| !p.is_empty());
+    if let Some(pattern) = &pattern {
+        Regex::new(pattern)
+            .map_err(|e| invalid_input(format!("正则表达式 {} 无效: {}", pattern, e)))?;
+    }
+
+    let result = sqlx::query(
+        "UPDATE template_fields
+        SET field_type = ?, required = ?, pattern = ?
+        WHERE template_id = ? AND fields_id = ?",
+    )
+    .bind(field_type.as_str())
+    .bind(required)
+    .bind(pattern)
+    .bind(template_id as i64)
+    .bind(field_id as i64)
+    .execute(pool)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(invalid_input(format!(
+            "模板 {} 中不存在字段 {}",
+            template_id, field_id
+        )));
+    }
+
+    Ok(())
+}
+
+/// 校验卡片内容
+///
+/// 检查字段数量是否与模板一致，并按字段类型和约束逐个校验字段内容
+pub async fn validate_card_fields(
+    pool: &SqlitePool,
+    template_id: u32,
+    template_fields: &[String],
+) -> Result<()> {
+    let fields = get_template_fields(pool, template_id).await?;
+    if fields.is_empty() {
+        return Err(invalid_input(format!("模板 {} 不存在", template_id)));
+    }
+    if fields.len() != template_fields.len() {
+        return Err(invalid_input(format!(
+            "模板需要 {} 个字段，但提供了 {} 个",
+            fields.len(),
+            template_fields.len()
+        )));
+    }
+
+    for (field, content) in fields.iter().zip(template_fields) {
+        check_field_content(field, content).map_err(invalid_input)?;
+    }
+
+    Ok(())
+}
+
+/// 校验单个字段的内容
+///
+/// 返回的错误信息中包含字段名称，可以直接展示给用户
+pub fn check_field_content(
+    field: &TemplateField,
+    content: &str,
+) -> std::result::Result<(), String> {
+    let name = &field.name;
+
+    if content.contains('\u{001F}') {
+        return Err(format!("字段“{}”包含非法字符", name));
+    }
+    if content.trim().is_empty() {
+        return if field.required {
+            Err(format!("字段“{}”为必填项", name))
+        } else {
+            Ok(())
+        };
+    }
+
+    match field.field_type {
+        FieldType::Text | FieldType::Markdown => {}
+        FieldType::Html => {
+            check_html(content).map_err(|e| format!("字段“{}”不是合法的 HTML: {}", name, e))?;
+        }
+        FieldType::Choice => {
+            let options = content
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .count();
+            if options < 2 {
+                return Err(format!("字段“{}”至少需要两个选项，每行一个", name));
+            }
+        }
+        FieldType::Integer => {
+            content
+                .trim()
+                .parse::<i64>()
+                .map_err(|_| format!("字段“{}”必须是整数", name))?;
+        }
+        FieldType::Media => {
+            if media_field_file_name(content).is_none() {
+                return Err(format!(
+                    "字段“{}”必须是媒体文件名或 [media:文件名] 形式的引用",
+                    name
+                ));
+            }
+        }
+        FieldType::Cloze => {
+            let deletions = CLOZE_DELETION.find_iter(content).count();
+            if deletions == 0 {
+                return Err(format!(
+                    "字段“{}”至少需要一个填空，格式为 {{{{c1::答案}}}}",
+                    name
+                ));
+            }
+            if content.matches("{{c").count() != deletions {
+                return Err(format!("字段“{}”中存在格式错误的填空", name));
+            }
+        }
+    }
+
+    if let Some(pattern) = &field.pattern {
+        let mut patterns = FIELD_PATTERNS.lock().unwrap();
+        let regex = match patterns.get(pattern) {
+            Some(regex) => regex,
+            None => {
+                let regex = Regex::new(pattern)
+                    .map_err(|_| format!("字段“{}”的校验规则 {} 无效", name, pattern))?;
+                patterns.entry(pattern.clone()).or_insert(regex)
+            }
+        };
+        if !regex.is_match(content) {
+            return Err(format!("字段“{}”的内容不符合格式要求 {}", name, pattern));
+        }
+    }
+
+    Ok(())
+}
+
+/// 检查 HTML 标签是否正确闭合
+///
+/// 只检查标签结构，不检查标签名和属性是否合法。属性值中的 '>' 不会结束标签，
+/// 可以省略闭合标签的元素（如 `<p>`、`<li>`）在父元素闭合或内容结束时视为已闭合
+fn check_html(content: &str) -> std::result::Result<(), String> {
+    const VOID_ELEMENTS: [&str; 14] = [
+        "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param",
+        "source", "track", "wbr",
+    ];
+    const OPTIONAL_END_TAGS: [&str; 17] = [
+        "body", "colgroup", "dd", "dt", "head", "html", "li", "optgroup", "option", "p", "rp",
+        "rt", "tbody", "td", "tfoot", "th", "thead",
+    ];
+    let optional = |tag: &str| OPTIONAL_END_TAGS.contains(&tag);
+
+    let mut open_tags: Vec<String> = Vec::new();
+    let mut rest = content;
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start + 1..];
+
+        // 注释
+        if let Some(comment) = rest.strip_prefix("!--") {
+            let end = comment.find("-->").ok_or("注释未闭合")?;
+            rest = &comment[end + 3..];
+            continue;
+        }
+        // 不是标签的 '<'
+        if !rest.starts_with(|c: char| c.is_ascii_alphabetic() || c == '/' || c == '!') {
+            continue;
+        }
+
+        let end = tag_end(rest).ok_or("标签缺少 '>'")?;
+        let tag = &rest[..end];
+        rest = &rest[end + 1..];
+        if tag.starts_with('!') {
+            continue;
+        }
+
+        let closing = tag.starts_with('/');
+        let tag_name: String = tag
+            .trim_start_matches('/')
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric())
+            .collect::<String>()
+            .to_ascii_lowercase();
+
+        if closing {
+            // 闭合标签之前未闭合的可省略闭合标签的元素视为已闭合
+            while open_tags
+                .last()
+                .is_some_and(|open| *open != tag_name && optional(open))
+            {
+                open_tags.pop();
+            }
+            match open_tags.pop() {
+                Some(open) if open == tag_name => {}
+                Some(open) => return Err(format!("<{}> 与 </{}> 不匹配", open, tag_name)),
+                None if optional(&tag_name) => {}
+                None => return Err(format!("多余的闭合标签 </{}>", tag_name)),
+            }
+        } else if !tag.ends_with('/') && !VOID_ELEMENTS.contains(&tag_name.as_str()) {
+            open_tags.push(tag_name);
+        }
+    }
+
+    match open_tags.into_iter().rev().find(|open| !optional(open)) {
+        Some(open) => Err(format!("<{}> 未闭合", open)),
+        None => Ok(()),
+    }
+}
+
+/// 标签结束的 '>' 的位置，跳过引号中的属性值
+fn tag_end(tag: &str) -> Option<usize> {
+    let mut quote = None;
+    for (index, c) in tag.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), c) if q == c => quote = None,
+            (None, '>') => return Some(index),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// 统计模板的使用情况
+///
+/// 返回使用该模板的卡片数量，以及这些卡片分布在多少个牌组中
+pub async fn get_template_usage(pool: &SqlitePool, template_id: u32) -> Result<TemplateUsage> {
+    let result = sqlx::query!(
+        "SELECT COUNT(*) as card_count, COUNT(DISTINCT deck_id) as deck_count
+        FROM cards WHERE template_id = ?",
+        template_id
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(TemplateUsage {
+        template_id,
+        card_count: result.card_count as u32,
+        deck_count: result.deck_count as u32,
+    })
+}
+
+/// 检查字段映射是否合法
+///
+/// 映射的键必须是源模板的字段ID，值必须是目标模板的字段ID，且每个目标字段至多被映射一次
+pub fn check_field_mapping(
+    source_fields: &[TemplateField],
+    target_fields: &[TemplateField],
+    field_mapping: &HashMap<u32, u32>,
+) -> Result<()> {
+    let mut mapped_targets = HashSet::new();
+    for (&source_id, &target_id) in field_mapping {
+        if !source_fields.iter().any(|f| f.field_id == source_id) {
+            return Err(invalid_input(format!("源模板中不存在字段 {}", source_id)));
+        }
+        if !target_fields.iter().any(|f| f.field_id == target_id) {
+            return Err(invalid_input(format!("目标模板中不存在字段 {}", target_id)));
+        }
+        if !mapped_targets.insert(target_id) {
+            return Err(invalid_input(format!(
+                "目标字段 {} 被映射了多次",
+                target_id
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// 按字段映射转换卡片内容
+///
+/// 返回按目标模板字段顺序排列的新内容，未被映射的目标字段留空
+pub fn map_template_fields(
+    content: &[String],
+    source_fields: &[TemplateField],
+    target_fields: &[TemplateField],
+    field_mapping: &HashMap<u32, u32>,
+) -> Vec<String> {
+    target_fields
+        .iter()
+        .map(|target| {
+            field_mapping
+                .iter()
+                .find(|(_, &target_id)| target_id == target.field_id)
+                .and_then(|(source_id, _)| {
+                    source_fields.iter().position(|f| f.field_id == *source_id)
+                })
+                .and_then(|index| content.get(index).cloned())
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+/// 准备模板转换
+///
+/// 在转换所在的事务中读取源模板和目标模板的字段并校验字段映射，返回 (源模板字段, 目标模板字段)
+async fn prepare_conversion(
+    conn: &mut SqliteConnection,
+    source_template_id: u32,
+    conversion: &TemplateConversion,
+) -> Result<(Vec<TemplateField>, Vec<TemplateField>)> {
+    if conversion.target_template_id == source_template_id {
+        return Err(invalid_input("目标模板不能与源模板相同".to_string()));
+    }
+
+    let source_fields = get_template_fields(&mut *conn, source_template_id).await?;
+    let target_fields = get_template_fields(&mut *conn, conversion.target_template_id).await?;
+    if target_fields.is_empty() {
+        return Err(invalid_input(format!(
+            "目标模板 {} 不存在",
+            conversion.target_template_id
+        )));
+    }
+    check_field_mapping(&source_fields, &target_fields, &conversion.field_mapping)?;
+
+    Ok((source_fields, target_fields))
+}
+
+/// 在事务中转换卡片的模板
+///
+/// 只改写卡片的 template_id 和 template_fields 并更新修改时间，卡片的调度状态和复习记录保持不变。
+/// 转换后的内容按目标模板的字段约束校验，任意一张卡片不符合时整个转换失败。
+/// 返回实际被转换的卡片数量
+async fn convert_cards(
+    conn: &mut SqliteConnection,
+    cards: &[(i64, String)], // (Card ID, Template Fields)
+    source_template_id: u32,
+    source_fields: &[TemplateField],
+    target_fields: &[TemplateField],
+    conversion: &TemplateConversion,
+) -> Result<u32> {
+    let mut converted_count = 0;
+    for (card_id, template_fields) in cards {
+        let content = split_template_fields(template_fields);
+        let converted = map_template_fields(
+            &content,
+            source_fields,
+            target_fields,
+            &conversion.field_mapping,
+        );
+        for (field, content) in target_fields.iter().zip(&converted) {
+            check_field_content(field, content)
+                .map_err(|e| invalid_input(format!("卡片 {} 转换后{}", card_id, e)))?;
+        }
+        let merged_fields = merge_template_fields(converted);
+        converted_count += sqlx::query(
+            "UPDATE cards SET template_id = ?, template_fields = ?, modified_at = ?
+            WHERE card_id = ? AND template_id = ?",
+        )
+        .bind(conversion.target_template_id)
+        .bind(merged_fields)
+        .bind(Utc::now())
+        .bind(card_id)
+        .bind(source_template_id)
+        .execute(&mut *conn)
+        .await?
+        .rows_affected();
+    }
+
+    Ok(converted_count as u32)
+}
+
+/// 更换卡片模板
+///
+/// 将一组使用相同模板的卡片按字段映射转换到目标模板，
+/// 保留卡片的记忆状态和复习记录，返回被转换的卡片数量
+pub async fn change_cards_template(
+    pool: &SqlitePool,
+    card_ids: &[u32],
+    conversion: &TemplateConversion,
+) -> Result<u32> {
+    let card_ids: Vec<u32> = card_ids
+        .iter()
+        .copied()
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    if card_ids.is_empty() {
+        return Ok(0);
+    }
+
+    // 开启事务
+    let mut tx = pool.begin().await?;
+
+    let placeholders = card_ids.iter().map(|_| "?").collect::<Vec<_>>().join(", ");
+    let query = format!(
+        "SELECT card_id, template_id, template_fields FROM cards WHERE card_id IN ({})",
+        placeholders
+    );
+    let mut query_builder = sqlx::query_as::<_, (i64, i64, String)>(&query);
+    for &card_id in &card_ids {
+        query_builder = query_builder.bind(card_id as i64);
+    }
+    let rows = query_builder.fetch_all(&mut *tx).await?;
+
+    if rows.len() != card_ids.len() {
+        return Err(invalid_input("部分卡片不存在".to_string()));
+    }
+    let source_template_ids: HashSet<i64> = rows.iter().map(|row| row.1).collect();
+    if source_template_ids.len() > 1 {
+        return Err(invalid_input("所选卡片必须使用相同的模板".to_string()));
+    }
+    let source_template_id = rows[0].1 as u32;
+
+    let (source_fields, target_fields) =
+        prepare_conversion(&mut tx, source_template_id, conversion).await?;
+    let cards: Vec<(i64, String)> = rows
+        .into_iter()
+        .map(|(card_id, _, template_fields)| (card_id, template_fields))
+        .collect();
+
+    let converted_count = convert_cards(
+        &mut tx,
+        &cards,
+        source_template_id,
+        &source_fields,
+        &target_fields,
+        conversion,
+    )
+    .await?;
+
+    // 提交事务
+    tx.commit().await?;
+
+    Ok(converted_count)
+}
+
+/// 删除模板
+///
+/// 模板未被任何卡片使用时直接删除；否则必须提供 `conversion`，
+/// 先将使用该模板的卡片按字段映射转换到目标模板，再删除模板。
+/// 返回被转换的卡片数量
+pub async fn delete_template_by_id(
+    pool: &SqlitePool,
+    template_id: u32,
+    conversion: Option<&TemplateConversion>,
+) -> Result<u32> {
+    // 开启事务
+    let mut tx = pool.begin().await?;
+
+    let prepared = match conversion {
+        Some(conversion) => Some(prepare_conversion(&mut tx, template_id, conversion).await?),
+        None => None,
+    };
+
+    // 1. 转换仍在使用该模板的卡片
+    let cards = sqlx::query_as::<_, (i64, String)>(
+        "SELECT card_id, template_fields FROM cards WHERE template_id = ?",
+    )
+    .bind(template_id as i64)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let mut converted_count = 0;
+    if !cards.is_empty() {
+        let (conversion, (source_fields, target_fields)) = match (conversion, prepared) {
+            (Some(conversion), Some(prepared)) => (conversion, prepared),
+            _ => {
+                return Err(invalid_input(format!(
+                    "模板仍被 {} 张卡片使用，请先将这些卡片转换到其他模板",
+                    cards.len()
+                )))
+            }
+        };
+
+        converted_count = convert_cards(
+            &mut tx,
+            &cards,
+            template_id,
+            &source_fields,
+            &target_fields,
+            conversion,
+        )
+        .await?;
+    }
+
+    // 2. 删除模板字段
+    sqlx::query!(
+        "DELETE FROM template_fields WHERE template_id = ?",
+        template_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    // 3. 删除模板渲染配置
+    sqlx::query("DELETE FROM template_renderers WHERE template_id = ?")
+        .bind(template_id as i64)
+        .execute(&mut *tx)
+        .await?;
+
+    // 4. 删除模板本身
+    sqlx::query!("DELETE FROM templates WHERE template_id = ?", template_id)
+        .execute(&mut *tx)
+        .await?;
+
+    // 提交事务
+    tx.commit().await?;
+
+    Ok(converted_count)
+}
+
+/// 获取所有模板的渲染配置
+///
+/// 返回每个模板在前端使用的模板类、导入路径和渲染选项，没有渲染配置的模板不包含在内
+pub async fn get_template_renderers(pool: &SqlitePool) -> Result<TemplateConfig> {
+    let rows = sqlx::query_as::<_, (i64, String, String, String, String)>(
+        "SELECT r.template_id, t.name, r.class_name, r.import_path, r.options
+        FROM template_renderers r
+        JOIN templates t ON t.template_id = r.template_id
+        ORDER BY r.template_id",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let templates = rows
+        .into_iter()
+        .map(
+            |(template_id, template_name, class_name, import_path, options)| TemplateInfo {
+                template_id: template_id as u32,
+                template_name,
+                class_name,
+                import_path,
+                options: serde_json::from_str(&options).unwrap_or_default(),
+            },
+        )
+        .collect();
+
+    Ok(TemplateConfig { templates })
+}
+
+/// 设置模板的渲染配置
+///
+/// 模板已有渲染配置时覆盖原配置，未提供渲染选项时保存为空对象
+pub async fn set_template_renderer(pool: &SqlitePool, renderer: &TemplateInfo) -> Result<()> {
+    if get_template(pool, renderer.template_id).await?.is_none() {
+        return Err(invalid_input(format!(
+            "模板 {} 不存在",
+            renderer.template_id
+        )));
+    }
+    if renderer.class_name.trim().is_empty() {
+        return Err(invalid_input("模板类名不能为空"));
+    }
+
+    sqlx::query(
+        "INSERT INTO template_renderers (template_id, class_name, import_path, options)
+        VALUES (?, ?, ?, ?)
+        ON CONFLICT(template_id) DO UPDATE SET
+        class_name = excluded.class_name,
+        import_path = excluded.import_path,
+        options = excluded.options",
+    )
+    .bind(renderer.template_id as i64)
+    .bind(&renderer.class_name)
+    .bind(&renderer.import_path)
+    .bind(match &renderer.options {
+        serde_json::Value::Null => "{}".to_string(),
+        options => options.to_string(),
+    })
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// 导入旧版 templateConfig.json 中的模板渲染配置
+///
+/// 旧配置通过模板名称与模板关联，导入时按名称查找模板ID，找不到的条目会被跳过。
+/// 仅在数据库中还没有任何渲染配置时导入，返回导入的条目数量
+pub async fn import_template_renderers(pool: &SqlitePool, config: &TemplateConfig) -> Result<u32> {
+    let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM template_renderers")
+        .fetch_one(pool)
+        .await?;
+    if count > 0 {
+        return Ok(0);
+    }
+
+    let mut imported = 0;
+    for info in &config.templates {
+        let Some(template) = get_template(pool, info.template_name.as_str()).await? else {
+            println!("模板 {} 不存在，跳过其渲染配置", info.template_name);
+            continue;
+        };
+
+        set_template_renderer(
+            pool,
+            &TemplateInfo {
+                template_id: template.template_id,
+                ..info.clone()
+            },
+        )
+        .await?;
+        imported += 1;
+    }
+
+    Ok(imported)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_html_accepts_balanced_tags() {
+        assert!(check_html("<div><b>粗体</b><br><img src=\"a.png\"/></div>").is_ok());
+        assert!(check_html("a < b <!-- 注释 <i> --> 文字").is_ok());
+    }
+
+    #[test]
+    fn check_html_accepts_optional_end_tags() {
+        assert!(check_html("<p>第一段<p>第二段").is_ok());
+        assert!(check_html("<ul><li>一<li>二</ul>").is_ok());
+        assert!(check_html("<table><tr><td>1<td>2</tr></table>").is_ok());
+        assert!(check_html("<div><p>段落</div>").is_ok());
+    }
+
+    #[test]
+    fn check_html_ignores_gt_in_attribute_values() {
+        assert!(check_html(r#"<span title="a > b">x</span>"#).is_ok());
+        assert!(check_html("<a href='x?a>b'>链接</a>").is_ok());
+        assert!(check_html(r#"<span title="a > b">x"#).is_err());
+    }
+
+    #[test]
+    fn check_html_rejects_mismatched_tags() {
+        assert!(check_html("<div><span></div></span>").is_err());
+        assert!(check_html("<b>未闭合").is_err());
+        assert!(check_html("</div>").is_err());
+        assert!(check_html("<div").is_err());
+        assert!(check_html("<!-- 未闭合").is_err());
+    }
+}
@@ -1,11 +1,137 @@
 use crate::controller::card_controller::create_card;
 use crate::controller::deck_controller::create_deck;
-use crate::controller::template_controller::{create_template, get_template};
-use crate::models::Template;
-use sqlx::{sqlite::SqlitePool, Result};
+use crate::controller::template_controller::{
+    create_template, get_template, set_template_renderer, update_template_field,
+};
+use crate::models::{FieldType, Template, TemplateConfig, TemplateInfo};
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool};
+use sqlx::Result;
 use std::fs;
 use std::io::ErrorKind;
 use std::path::PathBuf;
+use std::str::FromStr;
+
+/// 初始表结构，编译时嵌入，新建的数据库不依赖数据库目录中的 init.sql
+const INIT_SQL: &str = include_str!("../db/init.sql");
+
+/// 数据库迁移脚本
+///
+/// init.sql 只负责创建最初的表结构，之后的表结构变更都追加在这里。
+/// 第 i 个脚本将数据库从版本 i 升级到版本 i + 1，当前版本记录在 PRAGMA user_version 中
+const MIGRATIONS: &[&str] = &[
+    // 1: 模板字段类型与校验约束
+    "
+    ALTER TABLE template_fields ADD COLUMN field_type TEXT NOT NULL DEFAULT 'text';
+    ALTER TABLE template_fields ADD COLUMN required BOOLEAN NOT NULL DEFAULT 0;
+    ALTER TABLE template_fields ADD COLUMN pattern TEXT;
+    UPDATE template_fields SET required = 1
+    WHERE name IN ('问题', '答案')
+    AND template_id IN (SELECT template_id FROM templates WHERE name = '选择题卡片');
+    UPDATE template_fields SET field_type = 'choice', required = 1
+    WHERE name = '选项'
+    AND template_id IN (SELECT template_id FROM templates WHERE name = '选择题卡片');
+    UPDATE template_fields SET pattern = '^[A-Z]$'
+    WHERE name = '答案'
+    AND template_id IN (SELECT template_id FROM templates WHERE name = '选择题卡片');
+    UPDATE template_fields SET required = 1
+    WHERE name = '正面'
+    AND template_id IN (SELECT template_id FROM templates WHERE name = '正反面卡片');
+    ",
+    // 2: 模板的正面、背面格式与 CSS，格式为空时使用根据字段生成的默认格式
+    "
+    ALTER TABLE templates ADD COLUMN front_format TEXT;
+    ALTER TABLE templates ADD COLUMN back_format TEXT;
+    ALTER TABLE templates ADD COLUMN css TEXT NOT NULL DEFAULT '';
+    ",
+    // 3: 模板渲染配置，原先保存在 templateConfig.json 中
+    "
+    CREATE TABLE IF NOT EXISTS template_renderers (
+        template_id INTEGER PRIMARY KEY,
+        class_name TEXT NOT NULL,
+        import_path TEXT NOT NULL,
+        options TEXT NOT NULL DEFAULT '{}',
+        FOREIGN KEY (template_id) REFERENCES templates(template_id)
+    );
+    ",
+    // 4: 复习用时与统计查询使用的索引
+    "
+    ALTER TABLE reviews ADD COLUMN duration_ms INTEGER;
+    CREATE INDEX IF NOT EXISTS idx_reviews_card_date ON reviews(card_id, review_date);
+    CREATE INDEX IF NOT EXISTS idx_reviews_date ON reviews(review_date);
+    ",
+    // 5: SM-2 难度系数（如 Anki 的 2.5），回答时按评分调整，复习记录无法重放时用于估算记忆状态
+    "
+    ALTER TABLE cards ADD COLUMN ease_factor REAL;
+    ",
+    // 6: FSRS 参数的历史记录，参数以 JSON 数组保存
+    "
+    CREATE TABLE IF NOT EXISTS fsrs_params_history (
+        history_id INTEGER PRIMARY KEY AUTOINCREMENT,
+        params TEXT NOT NULL,
+        source TEXT NOT NULL,
+        created_at DATETIME NOT NULL,
+        review_count INTEGER,
+        log_loss REAL,
+        rmse_bins REAL,
+        reverted_from INTEGER,
+        FOREIGN KEY (reverted_from) REFERENCES fsrs_params_history(history_id)
+    );
+    ",
+    // 7: 复习时的学习模式，设置排除时筛选学习和临时抱佛脚的复习不参与 FSRS 训练
+    "
+    ALTER TABLE reviews ADD COLUMN study_mode TEXT NOT NULL DEFAULT 'normal';
+    ",
+    // 8: 牌组的学习队列选项，包括新卡片顺序、复习顺序以及新卡片与复习的混合方式
+    "
+    ALTER TABLE decks ADD COLUMN new_card_order TEXT NOT NULL DEFAULT 'added';
+    ALTER TABLE decks ADD COLUMN review_order TEXT NOT NULL DEFAULT 'due';
+    ALTER TABLE decks ADD COLUMN new_review_mix TEXT NOT NULL DEFAULT 'interleaved';
+    ",
+    // 9: 进行中的学习会话，应用重启后可以继续学习。一个会话可以包含多个牌组，
+    // 会话的牌组及各牌组的答题数、剩余卡片在队列中的位置分别保存在单独的表中，答题后只更新改变的卡片
+    "
+    CREATE TABLE IF NOT EXISTS study_sessions (
+        session_id INTEGER PRIMARY KEY AUTOINCREMENT,
+        study_mode TEXT NOT NULL DEFAULT 'normal',
+        answered INTEGER NOT NULL DEFAULT 0,
+        correct INTEGER NOT NULL DEFAULT 0,
+        elapsed_ms INTEGER NOT NULL DEFAULT 0,
+        started_at DATETIME NOT NULL,
+        updated_at DATETIME NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS study_session_decks (
+        session_id INTEGER NOT NULL,
+        deck_id INTEGER NOT NULL,
+        answered INTEGER NOT NULL DEFAULT 0,
+        PRIMARY KEY (session_id, deck_id),
+        FOREIGN KEY (deck_id) REFERENCES decks(deck_id)
+    );
+    CREATE TABLE IF NOT EXISTS study_session_cards (
+        session_id INTEGER NOT NULL,
+        card_id INTEGER NOT NULL,
+        position INTEGER NOT NULL,
+        requeued INTEGER NOT NULL DEFAULT 0,
+        PRIMARY KEY (session_id, card_id)
+    );
+    ",
+    // 10: 大量卡片时牌组统计和学习队列查询使用的索引
+    "
+    CREATE INDEX IF NOT EXISTS idx_cards_deck_due ON cards(deck_id, due);
+    CREATE INDEX IF NOT EXISTS idx_cards_last_review ON cards(last_review);
+    ",
+    // 11: 卡片最后修改时间，用于卡片浏览器排序，已有卡片以最后复习时间或添加时的到期时间为准
+    "
+    ALTER TABLE cards ADD COLUMN modified_at DATETIME;
+    UPDATE cards SET modified_at = COALESCE(last_review, due);
+    CREATE INDEX IF NOT EXISTS idx_cards_due ON cards(due);
+    CREATE INDEX IF NOT EXISTS idx_cards_modified_at ON cards(modified_at);
+    ",
+    // 12: 牌组每天学习的新卡片数和复习数上限，为空时不限制
+    "
+    ALTER TABLE decks ADD COLUMN new_cards_per_day INTEGER;
+    ALTER TABLE decks ADD COLUMN max_reviews_per_day INTEGER;
+    ",
+];
 
 pub async fn initialize_database(db_url: &str) -> Result<SqlitePool> {
     println!("数据库连接URL: {}", db_url);
@@ -30,31 +156,67 @@ pub async fn initialize_database(db_url: &str) -> Result<SqlitePool> {
     let db_file_exists = db_file_path.exists();
     println!("数据库文件{}存在", if db_file_exists { "" } else { "不" });
 
-    // 创建SQLite连接池
-    let pool = SqlitePool::connect(&db_url).await?;
+    // 创建SQLite连接池，数据库文件不存在时自动创建
+    let options = SqliteConnectOptions::from_str(db_url)?.create_if_missing(true);
+    let pool = SqlitePool::connect_with(options).await?;
 
-    // 读取初始化SQL文件
-    let init_sql_path = PathBuf::from(db_dir).join("init.sql");
-    let sql = match fs::read_to_string(init_sql_path) {
-        Ok(sql) => sql,
-        Err(e) => {
-            println!("读取初始化SQL文件失败: {}", e.to_string());
-            String::from("")
-        }
-    };
-
-    // 执行初始化SQL（如果数据库文件不存在或者强制执行）
-    if !sql.is_empty() && !db_file_exists {
+    // 执行初始化SQL（如果数据库文件不存在）
+    if !db_file_exists {
         println!("数据库文件不存在，执行初始化SQL...");
-        sqlx::query(&sql).execute(&pool).await?;
+        sqlx::query(INIT_SQL).execute(&pool).await?;
         println!("初始化SQL执行完成");
-    } else if !sql.is_empty() {
+    } else {
         println!("数据库文件已存在，跳过初始化SQL");
     }
 
+    run_migrations(&pool).await?;
+
     Ok(pool)
 }
 
+/// 执行数据库迁移
+///
+/// 依次执行尚未应用的迁移脚本，每个脚本与版本号的更新在同一个事务中完成
+pub async fn run_migrations(pool: &SqlitePool) -> Result<()> {
+    let (version,): (i64,) = sqlx::query_as("PRAGMA user_version")
+        .fetch_one(pool)
+        .await?;
+
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        let target_version = index + 1;
+        println!("执行数据库迁移，版本 {} -> {}", index, target_version);
+
+        let mut tx = pool.begin().await?;
+        sqlx::query(migration).execute(&mut *tx).await?;
+        sqlx::query(&format!("PRAGMA user_version = {}", target_version))
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+    }
+
+    Ok(())
+}
+
+/// 内置模板的渲染配置
+///
+/// 与随应用发布的 templateConfig.json 内容一致，旧配置文件无法读取时以此为准
+pub fn builtin_template_renderers() -> TemplateConfig {
+    let renderer = |template_name: &str, class_name: &str| TemplateInfo {
+        template_id: 0,
+        template_name: template_name.to_string(),
+        class_name: class_name.to_string(),
+        import_path: format!("@/CardMemo/templates/{}", class_name),
+        options: serde_json::Value::default(),
+    };
+
+    TemplateConfig {
+        templates: vec![
+            renderer("选择题卡片", "SelectionCardTemplate"),
+            renderer("正反面卡片", "TextCardTemplate"),
+        ],
+    }
+}
+
 pub async fn initialize_decks(pool: &SqlitePool) -> Result<()> {
     // 检查数据库中是否已有卡组
     let decks_count: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM decks")
@@ -88,7 +250,22 @@ pub async fn initialize_decks(pool: &SqlitePool) -> Result<()> {
                     ("解析".to_string(), false),
                 ],
             };
-            create_template(pool, &template).await?
+            let template_id = create_template(pool, &template).await?;
+
+            // 设置字段类型和校验约束
+            update_template_field(pool, template_id, 0, FieldType::Text, true, None).await?;
+            update_template_field(pool, template_id, 1, FieldType::Choice, true, None).await?;
+            update_template_field(
+                pool,
+                template_id,
+                2,
+                FieldType::Text,
+                true,
+                Some("^[A-Z]$".to_string()),
+            )
+            .await?;
+            set_builtin_renderer(pool, template_id, choice_template_name).await?;
+            template_id
         }
     };
 
@@ -103,7 +280,12 @@ pub async fn initialize_decks(pool: &SqlitePool) -> Result<()> {
                 template_name: basic_template_name.to_string(),
                 template_fields: vec![("正面".to_string(), true), ("反面".to_string(), false)],
             };
-            create_template(pool, &template).await?
+            let template_id = create_template(pool, &template).await?;
+
+            // 设置字段类型和校验约束
+            update_template_field(pool, template_id, 0, FieldType::Text, true, None).await?;
+            set_builtin_renderer(pool, template_id, basic_template_name).await?;
+            template_id
         }
     };
 
@@ -129,3 +311,29 @@ pub async fn initialize_decks(pool: &SqlitePool) -> Result<()> {
 
     Ok(())
 }
+
+/// 为新创建的内置模板设置渲染配置
+async fn set_builtin_renderer(
+    pool: &SqlitePool,
+    template_id: u32,
+    template_name: &str,
+) -> Result<()> {
+    let config = builtin_template_renderers();
+    match config
+        .templates
+        .into_iter()
+        .find(|info| info.template_name == template_name)
+    {
+        Some(info) => {
+            set_template_renderer(
+                pool,
+                &TemplateInfo {
+                    template_id,
+                    ..info
+                },
+            )
+            .await
+        }
+        None => Ok(()),
+    }
+}
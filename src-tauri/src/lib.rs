@@ -1,117 +1,179 @@
+#[cfg(feature = "benchmark")]
+pub mod benchmark;
 mod commands;
+mod config;
 mod controller;
 mod database;
 mod models;
+mod profile;
 use std::{collections::HashMap, fs};
 
 use commands::cardedit::{
-    add_card, add_deck, add_template, add_template_config, card_filter, delete_card, get_fields,
-    get_template_config, template_display, update_card_content,
+    add_card, add_deck, add_template, add_template_config, card_browser_page, card_detail,
+    card_filter, change_card_template, delete_card, delete_template, get_fields,
+    get_template_config, set_template_field, set_template_format, template_display,
+    template_format, template_usage, update_card_content,
 };
 use commands::cardmemo::{
-    card_count_learned_today, decks_display, delete_deck, emit_card_review, get_loaded_template,
-    get_next_card, load_next_state,
+    answer_study_card, card_count_learned_today, deck_queue_options, decks_display, delete_deck,
+    end_study_session, get_loaded_template, next_study_card, render_card, resumable_study_session,
+    resume_study_session, set_deck_queue_options, start_study_session, suspend_study_session,
+};
+use commands::media::{check_media, import_media, import_media_file};
+use commands::profile::{
+    create_profile, current_profile, delete_profile, list_profiles, rename_profile, switch_profile,
 };
 use commands::settings::{
-    get_desired_retention, get_fsrs_params, set_desired_retention, train_fsrs_model,
+    apply_trained_fsrs_params, auto_optimize_fsrs, cancel_fsrs_training, get_auto_optimize,
+    get_config_warnings, get_deck_presets, get_desired_retention, get_exclude_filtered_reviews,
+    get_fsrs_params, get_fsrs_params_history, get_last_optimization, recommend_desired_retention,
+    recompute_card_memory_states, reschedule_all_cards, reset_fsrs_params, revert_fsrs_params,
+    set_auto_optimize, set_deck_presets, set_desired_retention, set_exclude_filtered_reviews,
+    set_fsrs_params, simulate_retention_workload, train_fsrs_model,
+};
+use commands::statistics::{
+    answer_time_statistics, card_state_statistics, due_forecast, memory_distributions,
+    retention_statistics, review_statistics,
 };
-use database::{initialize_database, initialize_decks};
-use fsrs::NextStates;
-use models::{Card, Template};
-use serde::{Deserialize, Serialize};
+use config::ConfigStore;
+use controller::media_controller::{media_mime_type, read_media};
+use controller::session_controller::StudySessions;
+use database::builtin_template_renderers;
+use fsrs::CombinedProgressState;
+use models::{Template, TemplateConfig};
+use profile::{open_collection, Collection, ProfileManager};
 use sqlx::sqlite::SqlitePool;
 use tauri::path::BaseDirectory;
 use tauri::Manager;
 
-use std::sync::{Arc, Mutex};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, RwLock};
 
 pub type Safe<T> = Arc<Mutex<T>>;
 pub type SafeHashMap<T, E> = Safe<HashMap<T, E>>;
 
 pub struct AppState {
-    pool: SqlitePool,
+    profiles: ProfileManager,
+    collection: RwLock<Collection>,
     loaded_template: SafeHashMap<u32, Template>,
-    loaded_card: Safe<Option<Card>>,
-    loaded_next_states: Safe<Option<NextStates>>,
-    fsrs_params: Safe<[f32; 19]>,
-    desired_retention: Safe<f32>,
+    sessions: Safe<StudySessions>, // 进行中的学习会话
+    training: Safe<Option<Arc<Mutex<CombinedProgressState>>>>, // 正在进行的FSRS训练或重新计算卡片的进度
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct Config {
-    database_url: String,
-    pub fsrs_params: [f32; 19],
-    pub desired_retention: f32,
+impl AppState {
+    /// 当前配置文件的数据库连接池
+    pub fn pool(&self) -> SqlitePool {
+        self.collection.read().unwrap().pool.clone()
+    }
+
+    /// 当前配置文件的媒体文件夹
+    pub fn media_dir(&self) -> PathBuf {
+        self.collection.read().unwrap().media_dir.clone()
+    }
+
+    /// 当前配置文件的设置
+    pub fn config(&self) -> Arc<ConfigStore> {
+        self.collection.read().unwrap().config.clone()
+    }
+
+    /// 切换到另一个卡片集合
+    ///
+    /// 替换数据库连接池、媒体文件夹和设置，清空与原集合相关的缓存并关闭原数据库连接
+    pub async fn replace_collection(&self, collection: Collection) {
+        let previous = std::mem::replace(&mut *self.collection.write().unwrap(), collection);
+        self.loaded_template.lock().unwrap().clear();
+        self.sessions.lock().unwrap().clear();
+        self.cancel_training();
+        previous.pool.close().await;
+    }
+
+    /// 取消正在进行的FSRS训练或重新计算卡片
+    ///
+    /// 没有正在进行的任务时返回 false
+    pub fn cancel_training(&self) -> bool {
+        match self.training.lock().unwrap().as_ref() {
+            Some(training) => {
+                training.lock().unwrap().want_abort = true;
+                true
+            }
+            None => false,
+        }
+    }
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
         .setup(|app| {
-            let conf_path = app.path().resolve("conf.json", BaseDirectory::Resource)?;
-            let config_json = fs::read_to_string(conf_path)?;
-            let config: Config = serde_json::from_str(&config_json)?;
+            // 加载配置文件列表，首次运行时将已有设置和旧版 conf.json 迁移到默认配置文件
+            let legacy_path = app
+                .path()
+                .resolve("conf.json", BaseDirectory::Resource)
+                .ok();
+            let profiles = ProfileManager::load(
+                &app.path().app_config_dir()?,
+                &app.path().app_data_dir()?,
+                legacy_path.as_deref(),
+            )?;
+            let config_store = profiles.open_config(&profiles.current().id)?;
+
+            // 旧版 templateConfig.json 中的模板渲染配置，数据库中没有渲染配置时迁移到数据库
+            let legacy_config = app
+                .path()
+                .resolve("templateConfig.json", BaseDirectory::Resource)
+                .ok()
+                .and_then(|path| fs::read_to_string(path).ok())
+                .and_then(|json| serde_json::from_str::<TemplateConfig>(&json).ok())
+                .unwrap_or_else(builtin_template_renderers);
+
             // 使用tokio运行时执行异步初始化数据库操作
             let runtime = tokio::runtime::Runtime::new().unwrap();
 
-            let pool = runtime.block_on(async {
-                match initialize_database(&config.database_url).await {
-                    Ok(pool) => pool,
+            let collection = runtime.block_on(async {
+                match open_collection(config_store, &legacy_config).await {
+                    Ok(collection) => collection,
                     Err(e) => {
-                        println!("database connect error: {}", e.to_string());
-                        panic!("database connect error");
+                        println!("collection open error: {}", e);
+                        panic!("collection open error");
                     }
                 }
             });
 
-            runtime.block_on(async {
-                match initialize_decks(&pool).await {
-                    Ok(_) => (),
-                    Err(e) => {
-                        println!("decks init error: {}", e.to_string());
-                        panic!("decks init error");
-                    }
-                }
-            }); // 初始化decks
-
             app.manage(AppState {
-                pool,
+                profiles,
+                collection: RwLock::new(collection),
                 loaded_template: Arc::new(Mutex::new(HashMap::new())),
-                loaded_card: Arc::new(Mutex::new(None)),
-                loaded_next_states: Arc::new(Mutex::new(None)),
-                fsrs_params: Arc::new(Mutex::new(config.fsrs_params)),
-                desired_retention: Arc::new(Mutex::new(config.desired_retention)),
+                sessions: Arc::new(Mutex::new(StudySessions::default())),
+                training: Arc::new(Mutex::new(None)),
+            });
+
+            // 按设置在后台自动重新训练FSRS模型
+            let handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                if let Err(e) = auto_optimize_fsrs(handle).await {
+                    println!("自动训练FSRS模型失败: {}", e);
+                }
             });
             Ok(())
         })
-        .on_window_event(|window, event| {
-            if let tauri::WindowEvent::CloseRequested { .. } = event {
-                // 获取应用状态
-                let state = window.state::<AppState>();
-                let handle = window.app_handle();
-                let conf_path = handle
-                    .path()
-                    .resolve("conf.json", BaseDirectory::Resource)
-                    .unwrap();
-
-                // 读取现有配置
-                if let Ok(config_json) = fs::read_to_string(&conf_path) {
-                    if let Ok(mut config) = serde_json::from_str::<Config>(&config_json) {
-                        // 只更新 FSRS 参数
-                        let fsrs_params = state.fsrs_params.lock().unwrap();
-                        let desired_retention = state.desired_retention.lock().unwrap();
-                        config.fsrs_params = *fsrs_params;
-                        config.desired_retention = *desired_retention;
-
-                        // 将更新后的配置写回文件
-                        if let Ok(updated_json) = serde_json::to_string_pretty(&config) {
-                            if let Err(e) = fs::write(&conf_path, updated_json) {
-                                println!("Failed to save config file: {}", e);
-                            } else {
-                                println!("FSRS parameters saved to config file");
-                            }
-                        }
-                    }
+        .register_uri_scheme_protocol("media", |ctx, request| {
+            // 通过 media 协议向前端提供媒体文件夹中的文件
+            let state = ctx.app_handle().state::<AppState>();
+            let file_name = request.uri().path().trim_start_matches('/');
+            match read_media(&state.media_dir(), file_name) {
+                Ok(data) => tauri::http::Response::builder()
+                    .header(
+                        tauri::http::header::CONTENT_TYPE,
+                        media_mime_type(file_name),
+                    )
+                    .body(data)
+                    .unwrap(),
+                Err(e) => {
+                    println!("Failed to read media file {}: {}", file_name, e);
+                    tauri::http::Response::builder()
+                        .status(tauri::http::StatusCode::NOT_FOUND)
+                        .body(Vec::new())
+                        .unwrap()
                 }
             }
         })
@@ -119,25 +181,73 @@ pub fn run() {
         .invoke_handler(tauri::generate_handler![
             decks_display,
             card_count_learned_today,
-            get_next_card,
+            start_study_session,
+            next_study_card,
+            answer_study_card,
+            end_study_session,
+            resumable_study_session,
+            resume_study_session,
+            suspend_study_session,
             get_loaded_template,
-            emit_card_review,
-            load_next_state,
             delete_deck,
+            deck_queue_options,
+            set_deck_queue_options,
             card_filter,
+            card_browser_page,
+            card_detail,
             template_display,
             add_card,
             get_fields,
             train_fsrs_model,
+            apply_trained_fsrs_params,
+            get_fsrs_params_history,
+            revert_fsrs_params,
+            cancel_fsrs_training,
             get_fsrs_params,
+            set_fsrs_params,
+            reset_fsrs_params,
             set_desired_retention,
+            get_exclude_filtered_reviews,
+            set_exclude_filtered_reviews,
+            get_auto_optimize,
+            set_auto_optimize,
+            get_last_optimization,
+            get_config_warnings,
+            get_deck_presets,
+            set_deck_presets,
             get_desired_retention,
+            simulate_retention_workload,
+            recommend_desired_retention,
+            reschedule_all_cards,
+            recompute_card_memory_states,
             update_card_content,
             add_template,
             add_deck,
             get_template_config,
             add_template_config,
             delete_card,
+            template_usage,
+            delete_template,
+            change_card_template,
+            set_template_field,
+            import_media,
+            import_media_file,
+            check_media,
+            render_card,
+            template_format,
+            set_template_format,
+            list_profiles,
+            current_profile,
+            create_profile,
+            switch_profile,
+            rename_profile,
+            delete_profile,
+            review_statistics,
+            retention_statistics,
+            due_forecast,
+            memory_distributions,
+            card_state_statistics,
+            answer_time_statistics,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
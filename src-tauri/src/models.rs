@@ -1,6 +1,8 @@
 use chrono::{DateTime, NaiveDate, Utc};
 use fsrs::MemoryState;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::str::FromStr;
 
 mod memory_state_serde {
     use super::*;
@@ -44,6 +46,11 @@ pub struct Card {
     pub memory_state: Option<MemoryState>,
     pub scheduled_days: u32,
     pub last_review: Option<DateTime<Utc>>,
+    // Computed when the card is queried, not stored
+    #[serde(default)]
+    pub retrievability: Option<f32>, // Current recall probability, None for new cards
+    #[serde(default)]
+    pub days_overdue: Option<i64>, // Local days past due, negative if not yet due, None for new cards
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -70,12 +77,619 @@ pub struct Review {
     pub rating: u32,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum FieldType {
+    #[default]
+    Text, // 纯文本
+    Html,     // 富文本（HTML）
+    Markdown, // Markdown
+    Choice,   // 选项列表，每行一个选项
+    Integer,  // 整数
+    Media,    // 媒体文件引用
+    Cloze,    // 填空，形如 {{c1::答案}}
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct TemplateField {
     pub field_id: u32,
     pub template_id: u32,
     pub name: String,
     pub is_front: bool,
+    pub field_type: FieldType,
+    pub required: bool,
+    pub pattern: Option<String>, // Regex the content must match
+}
+
+impl FieldType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FieldType::Text => "text",
+            FieldType::Html => "html",
+            FieldType::Markdown => "markdown",
+            FieldType::Choice => "choice",
+            FieldType::Integer => "integer",
+            FieldType::Media => "media",
+            FieldType::Cloze => "cloze",
+        }
+    }
+}
+
+impl FromStr for FieldType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(FieldType::Text),
+            "html" => Ok(FieldType::Html),
+            "markdown" => Ok(FieldType::Markdown),
+            "choice" => Ok(FieldType::Choice),
+            "integer" => Ok(FieldType::Integer),
+            "media" => Ok(FieldType::Media),
+            "cloze" => Ok(FieldType::Cloze),
+            _ => Err(format!("Unknown field type: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TemplateUsage {
+    pub template_id: u32,
+    pub card_count: u32,
+    pub deck_count: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TemplateConversion {
+    pub target_template_id: u32,
+    pub field_mapping: HashMap<u32, u32>, // (Source Field ID, Target Field ID)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MissingMedia {
+    pub file_name: String,
+    pub card_ids: Vec<u32>, // Cards referencing the missing file
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MediaCheckReport {
+    pub missing: Vec<MissingMedia>,
+    pub unused: Vec<String>,
+    pub deleted: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TemplateFormat {
+    pub template_id: u32,
+    pub front_format: String,
+    pub back_format: String,
+    pub css: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RenderedCard {
+    pub card_id: u32,
+    pub template_id: u32,
+    pub front: String, // HTML
+    pub back: String,  // HTML
+    pub css: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct TemplateConfig {
+    pub templates: Vec<TemplateInfo>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TemplateInfo {
+    #[serde(default)]
+    pub template_id: u32, // Absent in the legacy templateConfig.json
+    pub template_name: String,
+    pub class_name: String,
+    pub import_path: String,
+    #[serde(default)]
+    pub options: serde_json::Value, // Renderer options
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct StatisticsFilter {
+    #[serde(default)]
+    pub deck_ids: Vec<u32>, // Empty for all decks
+    pub start: Option<NaiveDate>, // Local date, inclusive
+    pub end: Option<NaiveDate>,   // Local date, inclusive
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StatisticsPeriod {
+    Day,
+    Week, // Weeks start on Monday
+    Month,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DailyReviewCount {
+    pub date: NaiveDate,
+    pub again: u32,
+    pub hard: u32,
+    pub good: u32,
+    pub easy: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PeriodRetention {
+    pub period: String, // First day of the period, or YYYY-MM for months
+    pub reviews: u32,
+    pub passed: u32,
+    pub retention: f32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DayCount {
+    pub date: NaiveDate,
+    pub count: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DueForecast {
+    pub overdue: u32, // Due before the first forecast day
+    pub days: Vec<DayCount>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HistogramBin {
+    pub start: f32,
+    pub end: Option<f32>, // None for the open-ended last bin
+    pub count: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Distribution {
+    pub bins: Vec<HistogramBin>,
+    pub count: u32,
+    pub average: Option<f32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MemoryDistributions {
+    pub stability: Distribution,      // Days
+    pub difficulty: Distribution,     // 1 to 10
+    pub retrievability: Distribution, // 0 to 1, as of now
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct CardStateCounts {
+    pub new: u32,
+    pub learning: u32, // Reviewed, scheduled for the same day
+    pub young: u32,    // Interval shorter than 21 days
+    pub mature: u32,   // Interval of 21 days or more
+    pub total: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DailyAnswerTime {
+    pub date: NaiveDate,
+    pub count: u32,
+    pub average_ms: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AnswerTimeStats {
+    pub review_count: u32, // Reviews with a recorded answer time
+    pub total_ms: i64,
+    pub average_ms: Option<f64>,
+    pub days: Vec<DailyAnswerTime>,
+}
+
+fn default_simulation_days() -> u32 {
+    365
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SimulationOptions {
+    #[serde(default)]
+    pub deck_ids: Vec<u32>, // Empty for all decks
+    #[serde(default = "default_simulation_days")]
+    pub days: u32,
+    pub new_cards_per_day: Option<u32>, // None for the average of the last 30 days
+    pub max_reviews_per_day: Option<u32>, // None for no limit
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WorkloadDay {
+    pub date: NaiveDate,
+    pub reviews: u32,
+    pub new_cards: u32,
+    pub cost_seconds: f32,
+    pub memorized: f32, // Expected number of cards remembered on this day
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WorkloadSummary {
+    pub desired_retention: f32,
+    pub total_reviews: u32,
+    pub total_new_cards: u32,
+    pub average_reviews_per_day: f32,
+    pub total_cost_seconds: f32,
+    pub memorized: f32,                  // At the end of the simulation
+    pub cost_per_memorized: Option<f32>, // Seconds of study per memorized card
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WorkloadSimulation {
+    pub summary: WorkloadSummary,
+    pub days: Vec<WorkloadDay>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RetentionRecommendation {
+    pub recommended: Option<f32>, // None when nothing can be memorized
+    pub points: Vec<WorkloadSummary>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct JobProgress {
+    pub current: u32,
+    pub total: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct RescheduleSummary {
+    pub total: u32,       // Cards in scope
+    pub rescheduled: u32, // Cards whose memory state and due date were recomputed
+    pub earlier: u32,     // Now due on an earlier day
+    pub later: u32,       // Now due on a later day
+    pub unchanged: u32,
+    pub skipped: u32, // New cards, or cards whose history cannot be replayed
+    pub reviewed_during_job: u32, // Reviewed while the job was running, left as they were
+    pub cancelled: bool, // Cancelled before any card was written
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct RecomputeSummary {
+    pub total: u32,               // Cards in scope
+    pub from_history: u32,        // Memory state replayed from reviews
+    pub from_legacy: u32,         // Memory state approximated from interval and ease factor
+    pub skipped: u32,             // New cards, or cards without usable data
+    pub reviewed_during_job: u32, // Reviewed while the job was running, left as they were
+    pub cancelled: bool,          // Cancelled before any card was written
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct ParameterMetrics {
+    pub log_loss: f32,
+    pub rmse_bins: f32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TrainingSkipReason {
+    NotEnoughReviews, // Fewer review sequences than training needs
+    Cancelled,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TrainingResult {
+    pub current_params: Vec<f32>,
+    pub candidate_params: Vec<f32>,
+    pub current: Option<ParameterMetrics>, // None when training was skipped
+    pub candidate: Option<ParameterMetrics>,
+    pub improved: bool, // Lower log loss and no higher RMSE than the current parameters
+    pub applied: bool,
+    pub dataset: TrainingDataset,
+    pub skipped: Option<TrainingSkipReason>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum StudyMode {
+    #[default]
+    Normal, // Scheduled study of the cards due today
+    Filtered, // Studying reviewed cards that are not due yet, ahead of their schedule
+    Cram,     // Cramming every reviewed card regardless of its schedule
+}
+
+impl StudyMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StudyMode::Normal => "normal",
+            StudyMode::Filtered => "filtered",
+            StudyMode::Cram => "cram",
+        }
+    }
+}
+
+impl FromStr for StudyMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "normal" => Ok(StudyMode::Normal),
+            "filtered" => Ok(StudyMode::Filtered),
+            "cram" => Ok(StudyMode::Cram),
+            _ => Err(format!("Unknown study mode: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum CardSortOrder {
+    #[default]
+    Due, // Earliest due first
+    RetrievabilityAscending, // Most at risk of being forgotten first, new cards last
+    DaysOverdue,             // Most overdue first, new cards last
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum CardBrowserSort {
+    #[default]
+    Due,
+    Created, // In the order the cards were added
+    Modified,
+    Deck,     // By deck name
+    Template, // By template name
+    Stability,
+    Difficulty,
+    Retrievability,
+    Lapses,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct CardBrowserQuery {
+    pub template_ids: Vec<u32>, // Empty for all templates
+    pub deck_ids: Vec<u32>,     // Empty for all decks
+    pub status_bit_filter: u8,  // Same bits as card_filter, 0 for all cards
+    pub sort: CardBrowserSort,
+    pub descending: bool,
+    pub page: u32, // Starting from 0
+    pub page_size: u32,
+    pub after: Option<CardBrowserCursor>, // Continue after this card instead of skipping pages
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum CardBrowserSortKey {
+    Number(f64),
+    Text(String),
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct CardBrowserCursor {
+    pub sort_key: Option<CardBrowserSortKey>, // None if the card has no value, or when sorted by creation
+    pub card_id: u32,
+    pub queried_at: DateTime<Utc>, // Time of the first page, keeps the retrievability order stable
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CardBrowserRow {
+    pub card_id: u32,
+    pub deck_id: u32,
+    pub deck_name: String,
+    pub template_id: u32,
+    pub template_name: String,
+    pub sort_field: String, // First template field
+    pub due: DateTime<Utc>,
+    pub modified_at: DateTime<Utc>,
+    pub stability: Option<f32>,
+    pub difficulty: Option<f32>,
+    pub retrievability: Option<f32>, // None for new cards
+    pub days_overdue: Option<i64>,   // None for new cards
+    pub lapses: u32,                 // Again ratings after the first review
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CardBrowserPage {
+    pub rows: Vec<CardBrowserRow>,
+    pub total: u32, // Cards matching the filter on all pages
+    pub page: u32,
+    pub page_size: u32,
+    pub next_cursor: Option<CardBrowserCursor>, // None on the last page
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum NewCardOrder {
+    #[default]
+    Added, // In the order the cards were added
+    Random,
+    Template, // Grouped by template, then in added order
+}
+
+impl NewCardOrder {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NewCardOrder::Added => "added",
+            NewCardOrder::Random => "random",
+            NewCardOrder::Template => "template",
+        }
+    }
+}
+
+impl FromStr for NewCardOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "added" => Ok(NewCardOrder::Added),
+            "random" => Ok(NewCardOrder::Random),
+            "template" => Ok(NewCardOrder::Template),
+            _ => Err(format!("Unknown new card order: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ReviewOrder {
+    #[default]
+    Due, // Earliest due first
+    RetrievabilityAscending, // Most at risk of being forgotten first
+    Random,
+    Deck, // Earliest due first, studied as one block instead of alternating with other decks
+}
+
+impl ReviewOrder {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReviewOrder::Due => "due",
+            ReviewOrder::RetrievabilityAscending => "retrievability_ascending",
+            ReviewOrder::Random => "random",
+            ReviewOrder::Deck => "deck",
+        }
+    }
+}
+
+impl FromStr for ReviewOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "due" => Ok(ReviewOrder::Due),
+            "retrievability_ascending" => Ok(ReviewOrder::RetrievabilityAscending),
+            "random" => Ok(ReviewOrder::Random),
+            "deck" => Ok(ReviewOrder::Deck),
+            _ => Err(format!("Unknown review order: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum NewReviewMix {
+    Before, // All new cards before the reviews
+    After,  // All new cards after the reviews
+    #[default]
+    Interleaved, // New cards spread evenly among the reviews
+}
+
+impl NewReviewMix {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NewReviewMix::Before => "before",
+            NewReviewMix::After => "after",
+            NewReviewMix::Interleaved => "interleaved",
+        }
+    }
+}
+
+impl FromStr for NewReviewMix {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "before" => Ok(NewReviewMix::Before),
+            "after" => Ok(NewReviewMix::After),
+            "interleaved" => Ok(NewReviewMix::Interleaved),
+            _ => Err(format!("Unknown new/review mix: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub struct QueueOptions {
+    pub new_card_order: NewCardOrder,
+    pub review_order: ReviewOrder,
+    pub new_review_mix: NewReviewMix,
+    #[serde(default)]
+    pub new_cards_per_day: Option<u32>, // None for no limit
+    #[serde(default)]
+    pub max_reviews_per_day: Option<u32>, // None for no limit, cards first studied today not counted
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default)]
+pub struct NextIntervals {
+    pub again: u32,
+    pub hard: u32,
+    pub good: u32,
+    pub easy: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub struct StudyCounts {
+    pub new_count: u32,      // New cards not yet answered in the session
+    pub learning_count: u32, // Cards answered in the session and due again today
+    pub review_count: u32,   // Review cards not yet answered in the session
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DeckStudyProgress {
+    pub deck_id: u32,
+    pub counts: StudyCounts, // Remaining cards of this deck, including the card currently shown
+    pub answered: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StudyProgress {
+    pub session_id: u32,
+    pub mode: StudyMode,
+    pub deck_ids: Vec<u32>,
+    pub decks: Vec<DeckStudyProgress>, // Same order as deck_ids
+    pub counts: StudyCounts,           // Including the card currently shown
+    pub answered: u32,                 // Answers given, a card shown again today counts again
+    pub correct: u32,                  // Answers rated hard or better
+    pub elapsed_ms: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StudyCard {
+    pub card: Card,
+    pub intervals: NextIntervals, // Interval in days for each rating
+    pub progress: StudyProgress,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct TrainingDataset {
+    pub item_count: u32,   // Review sequences whose last review is on a later day
+    pub card_count: u32,   // Cards contributing at least one sequence
+    pub review_count: u32, // Reviews used, without filtered and cram reviews if excluded
+    pub first_day: Option<NaiveDate>, // Local day of the earliest review used
+    pub last_day: Option<NaiveDate>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ParamsSource {
+    Default, // FSRS default parameters
+    Trained, // Optimized on the review history
+    Manual,  // Entered by the user, or of unknown origin
+}
+
+impl ParamsSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ParamsSource::Default => "default",
+            ParamsSource::Trained => "trained",
+            ParamsSource::Manual => "manual",
+        }
+    }
+}
+
+impl FromStr for ParamsSource {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "default" => Ok(ParamsSource::Default),
+            "trained" => Ok(ParamsSource::Trained),
+            "manual" => Ok(ParamsSource::Manual),
+            _ => Err(format!("Unknown parameter source: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ParamsHistoryEntry {
+    pub history_id: u32,
+    pub params: Vec<f32>,
+    pub source: ParamsSource,
+    pub created_at: DateTime<Utc>,
+    pub review_count: Option<u32>, // Reviews the parameters were trained on
+    pub metrics: Option<ParameterMetrics>,
+    pub reverted_from: Option<u32>, // Entry whose parameters were restored
 }
 
 impl Default for Template {
@@ -99,6 +713,8 @@ impl Default for Card {
             memory_state: None,
             scheduled_days: 0,
             last_review: None,
+            retrievability: None,
+            days_overdue: None,
         }
     }
 }
@@ -132,6 +748,9 @@ impl Default for TemplateField {
             template_id: 0,
             name: String::new(),
             is_front: false,
+            field_type: FieldType::Text,
+            required: false,
+            pattern: None,
         }
     }
 }
@@ -0,0 +1,342 @@
+use crate::config::{write_atomic, Config, ConfigStore, CONFIG_FILE_NAME};
+use crate::controller::media_controller::media_dir_for_database;
+use crate::controller::template_controller::import_template_renderers;
+use crate::database::{initialize_database, initialize_decks};
+use crate::models::TemplateConfig;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// 配置文件列表的文件名，位于应用配置目录下
+pub const PROFILES_FILE_NAME: &str = "profiles.json";
+
+/// 当前配置文件列表版本
+pub const PROFILES_VERSION: u32 = 1;
+
+/// 从单一数据库升级时创建的默认配置文件
+const DEFAULT_PROFILE_ID: &str = "default";
+const DEFAULT_PROFILE_NAME: &str = "默认";
+
+/// 配置文件目录中的数据库文件名，媒体文件夹为同目录下的 collection.media
+const COLLECTION_FILE_NAME: &str = "collection.db";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Profile {
+    pub id: String,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ProfileRegistry {
+    version: u32,
+    current: String,
+    profiles: Vec<Profile>,
+}
+
+/// 配置文件管理
+///
+/// 每个配置文件拥有独立的目录，其中保存该配置文件的设置，没有沿用原有数据库时数据库和媒体文件夹也在其中。
+/// 配置文件列表与当前使用的配置文件记录在应用配置目录下的 profiles.json 中
+pub struct ProfileManager {
+    registry_path: PathBuf,
+    profiles_dir: PathBuf,
+    registry: Mutex<ProfileRegistry>,
+}
+
+impl ProfileManager {
+    /// 加载配置文件列表
+    ///
+    /// 首次运行时创建默认配置文件，并将 `config_dir` 下已有的设置或旧版配置文件 `legacy_path`
+    /// 迁移到默认配置文件中，原有数据库的位置保持不变
+    pub fn load(
+        config_dir: &Path,
+        data_dir: &Path,
+        legacy_path: Option<&Path>,
+    ) -> Result<Self, String> {
+        let registry_path = config_dir.join(PROFILES_FILE_NAME);
+        let profiles_dir = data_dir.join("profiles");
+
+        let mut first_run = false;
+        let registry = match fs::read_to_string(&registry_path) {
+            Ok(json) => {
+                let registry: ProfileRegistry = serde_json::from_str(&json)
+                    .map_err(|e| format!("解析配置文件列表失败: {}", e))?;
+                if registry.version != PROFILES_VERSION {
+                    return Err(format!("不支持的配置文件列表版本 {}", registry.version));
+                }
+                registry
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                let settings_path = profiles_dir.join(DEFAULT_PROFILE_ID).join(CONFIG_FILE_NAME);
+                let existing_settings = config_dir.join(CONFIG_FILE_NAME);
+                if existing_settings.exists() && !settings_path.exists() {
+                    fs::create_dir_all(settings_path.parent().unwrap())
+                        .and_then(|_| fs::rename(&existing_settings, &settings_path))
+                        .map_err(|e| format!("迁移设置失败: {}", e))?;
+                }
+                first_run = true;
+
+                ProfileRegistry {
+                    version: PROFILES_VERSION,
+                    current: DEFAULT_PROFILE_ID.to_string(),
+                    profiles: vec![Profile {
+                        id: DEFAULT_PROFILE_ID.to_string(),
+                        name: DEFAULT_PROFILE_NAME.to_string(),
+                        created_at: Utc::now(),
+                    }],
+                }
+            }
+            Err(e) => return Err(format!("读取配置文件列表失败: {}", e)),
+        };
+
+        let manager = Self {
+            registry_path,
+            profiles_dir,
+            registry: Mutex::new(registry.clone()),
+        };
+        if first_run {
+            manager.load_config(DEFAULT_PROFILE_ID, legacy_path)?;
+        }
+        manager.save(&registry)?;
+        Ok(manager)
+    }
+
+    fn save(&self, registry: &ProfileRegistry) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(registry)
+            .map_err(|e| format!("序列化配置文件列表失败: {}", e))?;
+        write_atomic(&self.registry_path, json.as_bytes())
+            .map_err(|e| format!("写入配置文件列表失败: {}", e))
+    }
+
+    /// 修改配置文件列表，写入文件成功后才会生效
+    fn update<T>(
+        &self,
+        modify: impl FnOnce(&mut ProfileRegistry) -> Result<T, String>,
+    ) -> Result<T, String> {
+        let mut registry = self.registry.lock().unwrap();
+        let mut updated = registry.clone();
+        let result = modify(&mut updated)?;
+        self.save(&updated)?;
+        *registry = updated;
+        Ok(result)
+    }
+
+    /// 获取所有配置文件
+    pub fn list(&self) -> Vec<Profile> {
+        self.registry.lock().unwrap().profiles.clone()
+    }
+
+    /// 获取当前使用的配置文件
+    pub fn current(&self) -> Profile {
+        let registry = self.registry.lock().unwrap();
+        registry
+            .profiles
+            .iter()
+            .find(|profile| profile.id == registry.current)
+            .cloned()
+            .expect("current profile must exist")
+    }
+
+    /// 根据ID获取配置文件
+    pub fn get(&self, id: &str) -> Result<Profile, String> {
+        self.registry
+            .lock()
+            .unwrap()
+            .profiles
+            .iter()
+            .find(|profile| profile.id == id)
+            .cloned()
+            .ok_or_else(|| format!("配置文件 {} 不存在", id))
+    }
+
+    /// 配置文件的目录
+    ///
+    /// 配置文件ID会作为目录名，不能为空，也不能包含路径分隔符或 ".."
+    fn profile_dir(&self, id: &str) -> Result<PathBuf, String> {
+        if id.is_empty() || id.contains(['/', '\\']) || id.contains("..") {
+            return Err(format!("配置文件ID {} 无效", id));
+        }
+        Ok(self.profiles_dir.join(id))
+    }
+
+    /// 打开配置文件的设置
+    pub fn open_config(&self, id: &str) -> Result<ConfigStore, String> {
+        self.load_config(id, None)
+    }
+
+    /// 加载配置文件的设置，`legacy_path` 为设置不存在时迁移的旧版配置文件
+    ///
+    /// 设置不存在或无效时使用配置文件目录中的数据库，不会回退到其他配置文件的数据库。
+    /// 设置中的数据库为相对路径时，若该数据库在当前目录下存在则改为绝对路径，
+    /// 否则改为配置文件目录中的数据库，数据库位置不再取决于应用的启动目录
+    fn load_config(&self, id: &str, legacy_path: Option<&Path>) -> Result<ConfigStore, String> {
+        let profile_dir = self.profile_dir(id)?;
+        let collection_path = profile_dir.join(COLLECTION_FILE_NAME);
+        let default = Config {
+            database_url: format!("sqlite://{}", collection_path.display()),
+            ..Config::default()
+        };
+        let store = ConfigStore::load(profile_dir.join(CONFIG_FILE_NAME), legacy_path, default)?;
+
+        let database_url = store.get().database_url;
+        let database_path = Path::new(database_url.trim_start_matches("sqlite://"));
+        if database_path.is_relative() {
+            let resolved = match std::env::current_dir() {
+                Ok(dir) if database_path.exists() => dir.join(database_path),
+                _ => collection_path,
+            };
+            store.update(|config| {
+                config.database_url = format!("sqlite://{}", resolved.display());
+            })?;
+        }
+        Ok(store)
+    }
+
+    fn check_name(registry: &ProfileRegistry, name: &str, except_id: &str) -> Result<(), String> {
+        if name.is_empty() {
+            return Err("配置文件名称不能为空".to_string());
+        }
+        if registry
+            .profiles
+            .iter()
+            .any(|profile| profile.name == name && profile.id != except_id)
+        {
+            return Err(format!("配置文件名称 '{}' 已存在", name));
+        }
+        Ok(())
+    }
+
+    /// 创建配置文件
+    ///
+    /// 在配置文件目录中创建设置，数据库在第一次切换到该配置文件时创建
+    pub fn create(&self, name: &str) -> Result<Profile, String> {
+        let name = name.trim();
+        self.update(|registry| {
+            Self::check_name(registry, name, "")?;
+
+            let created_at = Utc::now();
+            let mut id = format!("profile-{}", created_at.timestamp_millis());
+            while registry.profiles.iter().any(|profile| profile.id == id)
+                || self.profiles_dir.join(&id).exists()
+            {
+                id.push('0');
+            }
+
+            // 新配置文件的设置使用配置文件目录中的数据库
+            self.open_config(&id)?;
+
+            let profile = Profile {
+                id,
+                name: name.to_string(),
+                created_at,
+            };
+            registry.profiles.push(profile.clone());
+            Ok(profile)
+        })
+    }
+
+    /// 重命名配置文件
+    pub fn rename(&self, id: &str, name: &str) -> Result<Profile, String> {
+        let name = name.trim();
+        self.update(|registry| {
+            Self::check_name(registry, name, id)?;
+            let profile = registry
+                .profiles
+                .iter_mut()
+                .find(|profile| profile.id == id)
+                .ok_or_else(|| format!("配置文件 {} 不存在", id))?;
+            profile.name = name.to_string();
+            Ok(profile.clone())
+        })
+    }
+
+    /// 设置当前使用的配置文件
+    pub fn set_current(&self, id: &str) -> Result<(), String> {
+        self.update(|registry| {
+            if !registry.profiles.iter().any(|profile| profile.id == id) {
+                return Err(format!("配置文件 {} 不存在", id));
+            }
+            registry.current = id.to_string();
+            Ok(())
+        })
+    }
+
+    /// 删除配置文件
+    ///
+    /// 不能删除当前使用的配置文件。配置文件目录及其中的设置、数据库和媒体文件夹会一并删除，
+    /// 不在配置文件目录中的数据库（例如默认配置文件沿用的原有数据库）保持不变
+    pub fn delete(&self, id: &str) -> Result<(), String> {
+        self.get(id)?;
+        let profile_dir = self.profile_dir(id)?;
+        self.update(|registry| {
+            if registry.current == id {
+                return Err("不能删除当前使用的配置文件，请先切换到其他配置文件".to_string());
+            }
+            registry.profiles.retain(|profile| profile.id != id);
+            Ok(())
+        })?;
+
+        // 删除数据库及其日志文件、媒体文件夹和配置文件目录
+        let database_path = profile_dir.join(COLLECTION_FILE_NAME);
+        for suffix in ["", "-wal", "-shm", "-journal"] {
+            let mut path = database_path.clone().into_os_string();
+            path.push(suffix);
+            remove_if_exists(fs::remove_file(path))?;
+        }
+        remove_if_exists(fs::remove_dir_all(media_dir_for_database(
+            &database_path.to_string_lossy(),
+        )))?;
+        remove_if_exists(fs::remove_dir_all(&profile_dir))?;
+
+        Ok(())
+    }
+}
+
+fn remove_if_exists(result: io::Result<()>) -> Result<(), String> {
+    match result {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => {
+            Err(format!("删除配置文件数据失败: {}", e))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// 配置文件对应的卡片集合
+///
+/// 包含数据库连接池、媒体文件夹和设置，切换配置文件时整体替换
+pub struct Collection {
+    pub pool: SqlitePool,
+    pub media_dir: PathBuf,
+    pub config: Arc<ConfigStore>,
+}
+
+/// 打开卡片集合
+///
+/// 初始化数据库并执行迁移，新数据库会创建示例卡组，
+/// `template_config` 为数据库中还没有模板渲染配置时导入的配置
+pub async fn open_collection(
+    config: ConfigStore,
+    template_config: &TemplateConfig,
+) -> Result<Collection, String> {
+    let database_url = config.get().database_url;
+    let pool = initialize_database(&database_url)
+        .await
+        .map_err(|e| format!("打开数据库失败: {}", e))?;
+    initialize_decks(&pool)
+        .await
+        .map_err(|e| format!("初始化卡组失败: {}", e))?;
+    import_template_renderers(&pool, template_config)
+        .await
+        .map_err(|e| format!("迁移模板渲染配置失败: {}", e))?;
+
+    Ok(Collection {
+        pool,
+        media_dir: media_dir_for_database(&database_url),
+        config: Arc::new(config),
+    })
+}
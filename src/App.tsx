@@ -8,7 +8,11 @@ import Box from "@mui/material/Box";
 import ImportContactsOutlinedIcon from "@mui/icons-material/ImportContactsOutlined";
 import EditOutlinedIcon from "@mui/icons-material/EditOutlined";
 import SettingsOutlinedIcon from "@mui/icons-material/SettingsOutlined";
+import Snackbar from "@mui/material/Snackbar";
+import Alert from "@mui/material/Alert";
+import { useEffect, useState } from "react";
 import { useTabStore } from "./store/tabStore";
+import { getConfigWarnings } from "./api/Settings";
 
 interface TabPanelProps {
   children?: React.ReactNode;
@@ -47,6 +51,14 @@ function a11yProps(index: number) {
 function App() {
   // 使用全局状态管理标签页选择
   const { activeTab, setActiveTab } = useTabStore();
+  // 加载设置时被重置为默认值的配置项
+  const [configWarnings, setConfigWarnings] = useState<string[]>([]);
+
+  useEffect(() => {
+    getConfigWarnings()
+      .then(setConfigWarnings)
+      .catch(() => setConfigWarnings([]));
+  }, []);
 
   const handleChange = (_: React.SyntheticEvent, newValue: number) => {
     setActiveTab(newValue);
@@ -86,6 +98,18 @@ function App() {
       <TabPanel value={activeTab} index={2}>
         <SettingsMain />
       </TabPanel>
+
+      {/* 设置文件中有无效配置时提示用户 */}
+      <Snackbar
+        open={configWarnings.length > 0}
+        anchorOrigin={{ vertical: "top", horizontal: "center" }}
+      >
+        <Alert severity="warning" onClose={() => setConfigWarnings([])}>
+          {configWarnings.map((warning) => (
+            <div key={warning}>{warning}</div>
+          ))}
+        </Alert>
+      </Snackbar>
     </Box>
   );
 }
@@ -4,65 +4,41 @@ import TableCell from "@mui/material/TableCell";
 import TableContainer from "@mui/material/TableContainer";
 import TableHead from "@mui/material/TableHead";
 import TableRow from "@mui/material/TableRow";
+import TablePagination from "@mui/material/TablePagination";
+import TableSortLabel from "@mui/material/TableSortLabel";
 import Paper from "@mui/material/Paper";
 import Grid from "@mui/material/Grid2";
-import { useState, useMemo, useEffect } from "react";
+import { useState, useMemo, useEffect, useRef } from "react";
 import Box from "@mui/material/Box";
 import Button from "@mui/material/Button";
+import IconButton from "@mui/material/IconButton";
+import Tooltip from "@mui/material/Tooltip";
 import AddIcon from "@mui/icons-material/Add";
+import ArrowUpwardIcon from "@mui/icons-material/ArrowUpward";
+import ArrowDownwardIcon from "@mui/icons-material/ArrowDownward";
 import CardEditHeader, { FilterOptions, DeckOption } from "./CardEditHeader";
 import { useNavigate } from "react-router-dom";
 import Typography from "@mui/material/Typography";
-import { CardData, filterCards } from "@/api/Card";
+import FormControl from "@mui/material/FormControl";
+import InputLabel from "@mui/material/InputLabel";
+import Select from "@mui/material/Select";
+import MenuItem from "@mui/material/MenuItem";
+import {
+  CardData,
+  CardBrowserCursor,
+  CardBrowserRow,
+  CardBrowserSort,
+  cardBrowserSortLabels,
+  browseCards,
+  getCardDetail,
+} from "@/api/Card";
 import { getAllTemplates } from "@/api/Template";
 import { fetchDecks, DeckData } from "@/api/Deck";
 import CircularProgress from "@mui/material/CircularProgress";
 import CardPreview from "@/CardEdit/CardPreview";
 
-// 定义卡片编辑专属的卡片数据视图接口
-export interface CardDataEditView {
-  cardId: number;
-  content: string; // 卡片完整内容
-  summary: string; // 卡片概要，从卡片内容中提取的前几个字符
-  template: string;
-  templateId: number;
-  deckName: string;
-  deckId: number;
-  dueDate?: Date; // 预计复习时间
-  templateFieldsContent: string[]; // 模板字段内容
-}
-
-function createCardDataEditView(
-  cardId: number,
-  content: string,
-  templateId: number,
-  template: string,
-  deckId: number,
-  deckName: string,
-  dueDate?: Date,
-  templateFieldsContent: string[] = []
-): CardDataEditView {
-  // 从卡片内容中提取概要
-  const summary = extractSummary(content);
-  return {
-    cardId,
-    content,
-    summary,
-    templateId,
-    template,
-    deckId,
-    deckName,
-    dueDate,
-    templateFieldsContent,
-  };
-}
-
-// 从卡片内容中提取概要的辅助函数
-const extractSummary = (content: string, maxLength: number = 30): string => {
-  if (!content) return "";
-  if (content.length <= maxLength) return content;
-  return content.substring(0, maxLength) + "...";
-};
+// 每页卡片数量的可选值
+const PAGE_SIZE_OPTIONS = [25, 50, 100];
 
 // 截断文本并添加省略号的辅助函数
 const truncateText = (text: string, maxLength: number = 30): string => {
@@ -70,27 +46,87 @@ const truncateText = (text: string, maxLength: number = 30): string => {
   return text.substring(0, maxLength) + "...";
 };
 
-// 获取卡片的题目描述
-const getCardQuestion = (card: CardDataEditView): string => {
-  // 如果没有模板字段内容，则返回卡片内容
-  if (!card.templateFieldsContent || card.templateFieldsContent.length === 0) {
-    return card.content;
-  }
+/**
+ * 自定义日期格式化函数
+ * @param date 要格式化的日期对象
+ * @param formatStr 格式化字符串，例如 "yyyy-MM-dd"
+ * @returns 格式化后的日期字符串
+ */
+const formatDate = (date: Date, formatStr: string): string => {
+  const year = date.getFullYear();
+  const month = date.getMonth() + 1; // getMonth() 返回 0-11
+  const day = date.getDate();
+  const hours = date.getHours();
+  const minutes = date.getMinutes();
+  const seconds = date.getSeconds();
+
+  // 补零函数
+  const padZero = (num: number): string => {
+    return num < 10 ? `0${num}` : `${num}`;
+  };
+
+  // 替换格式化字符串中的占位符
+  return formatStr
+    .replace(/yyyy/g, `${year}`)
+    .replace(/MM/g, padZero(month))
+    .replace(/dd/g, padZero(day))
+    .replace(/HH/g, padZero(hours))
+    .replace(/mm/g, padZero(minutes))
+    .replace(/ss/g, padZero(seconds));
+};
 
-  return card.templateFieldsContent[0] || card.content;
+// 格式化日期显示
+const formatDueDate = (due?: string) => {
+  if (!due) return "未设置";
+  return formatDate(new Date(due), "yyyy-MM-dd");
 };
 
+// 格式化可提取性和逾期天数显示
+const formatRetrievability = (row: CardBrowserRow) => {
+  if (row.retrievability === null) return "-";
+  const percent = `${(row.retrievability * 100).toFixed(1)}%`;
+  if (row.days_overdue !== null && row.days_overdue > 0) {
+    return `${percent}（逾期 ${row.days_overdue} 天）`;
+  }
+  return percent;
+};
+
+// 卡片表格的列，点击表头按对应的列排序，卡片内容按添加时间排序
+const columns: { label: string; sort: CardBrowserSort }[] = [
+  { label: "卡片内容", sort: "created" },
+  { label: "卡片模板", sort: "template" },
+  { label: "预计复习时间", sort: "due" },
+  { label: "可提取性", sort: "retrievability" },
+  { label: "所属牌组", sort: "deck" },
+];
+
 // 卡片表格组件
 const CardTable = ({
   rows,
+  total,
+  page,
+  pageSize,
+  sort,
+  descending,
   onCardSelect,
+  onSortChange,
+  onPageChange,
+  onPageSizeChange,
   isLoading,
 }: {
-  rows: CardDataEditView[];
-  onCardSelect: (card: CardDataEditView) => void;
+  rows: CardBrowserRow[];
+  total: number;
+  page: number;
+  pageSize: number;
+  sort: CardBrowserSort;
+  descending: boolean;
+  onCardSelect: (row: CardBrowserRow) => void;
+  onSortChange: (sort: CardBrowserSort, descending: boolean) => void;
+  onPageChange: (page: number) => void;
+  onPageSizeChange: (pageSize: number) => void;
   isLoading: boolean;
 }) => {
-  if (isLoading) {
+  if (isLoading && rows.length === 0) {
     return (
       <Box
         sx={{
@@ -120,107 +156,76 @@ const CardTable = ({
     );
   }
 
-  /**
-   * 自定义日期格式化函数
-   * @param date 要格式化的日期对象
-   * @param formatStr 格式化字符串，例如 "yyyy-MM-dd"
-   * @returns 格式化后的日期字符串
-   */
-  const formatDate = (date: Date, formatStr: string): string => {
-    const year = date.getFullYear();
-    const month = date.getMonth() + 1; // getMonth() 返回 0-11
-    const day = date.getDate();
-    const hours = date.getHours();
-    const minutes = date.getMinutes();
-    const seconds = date.getSeconds();
-
-    // 补零函数
-    const padZero = (num: number): string => {
-      return num < 10 ? `0${num}` : `${num}`;
-    };
-
-    // 替换格式化字符串中的占位符
-    return formatStr
-      .replace(/yyyy/g, `${year}`)
-      .replace(/MM/g, padZero(month))
-      .replace(/dd/g, padZero(day))
-      .replace(/HH/g, padZero(hours))
-      .replace(/mm/g, padZero(minutes))
-      .replace(/ss/g, padZero(seconds));
-  };
-
-  // 格式化日期显示
-  const formatDueDate = (date?: Date) => {
-    if (!date) return "未设置";
-    return formatDate(date, "yyyy-MM-dd");
-  };
-
   return (
-    <TableContainer component={Paper}>
-      <Table sx={{ minWidth: 500 }} size="small" aria-label="card table">
-        <TableHead>
-          <TableRow>
-            <TableCell>卡片内容</TableCell>
-            <TableCell align="center">卡片模板</TableCell>
-            <TableCell align="center">预计复习时间</TableCell>
-            <TableCell align="center">所属牌组</TableCell>
-          </TableRow>
-        </TableHead>
-        <TableBody>
-          {rows.map((row: CardDataEditView) => (
-            <TableRow
-              key={row.cardId}
-              sx={{
-                "&:last-child td, &:last-child th": { border: 0 },
-                cursor: "pointer",
-              }}
-              onClick={() => onCardSelect(row)}
-              hover
-            >
-              <TableCell component="th" scope="row">
-                {truncateText(getCardQuestion(row))}
-              </TableCell>
-              <TableCell align="center">{row.template}</TableCell>
-              <TableCell align="center">{formatDueDate(row.dueDate)}</TableCell>
-              <TableCell align="center">{row.deckName}</TableCell>
+    <Paper>
+      <TableContainer>
+        <Table sx={{ minWidth: 500 }} size="small" aria-label="card table">
+          <TableHead>
+            <TableRow>
+              {columns.map((column, index) => (
+                <TableCell
+                  key={column.label}
+                  align={index === 0 ? "left" : "center"}
+                >
+                  <TableSortLabel
+                    active={sort === column.sort}
+                    direction={
+                      sort === column.sort && descending ? "desc" : "asc"
+                    }
+                    onClick={() =>
+                      onSortChange(
+                        column.sort,
+                        sort === column.sort ? !descending : false
+                      )
+                    }
+                  >
+                    {column.label}
+                  </TableSortLabel>
+                </TableCell>
+              ))}
             </TableRow>
-          ))}
-        </TableBody>
-      </Table>
-    </TableContainer>
-  );
-};
-
-// 将后端卡片数据转换为前端卡片数据的函数
-const convertBackendCardToFrontend = (
-  backendCard: CardData,
-  templateMap: Map<number, string>,
-  deckMap: Map<number, DeckOption>
-): CardDataEditView => {
-  // 从模板字段内容中提取第一个字段作为卡片内容
-  const content = backendCard.template_fields_content[0] || "";
-  // 获取模板名称
-  const template = templateMap.get(backendCard.template_id) || "未知模板";
-  // 获取牌组信息
-  const deck = deckMap.get(backendCard.deck_id);
-  const deckName = deck ? deck.name : "未知牌组";
-  const deckId = backendCard.deck_id;
-
-  // 如果有due日期，转换为Date对象
-  let dueDate: Date | undefined = undefined;
-  if (backendCard.due) {
-    dueDate = new Date(backendCard.due);
-  }
-
-  return createCardDataEditView(
-    backendCard.card_id,
-    content,
-    backendCard.template_id,
-    template,
-    deckId,
-    deckName,
-    dueDate,
-    backendCard.template_fields_content
+          </TableHead>
+          <TableBody>
+            {rows.map((row: CardBrowserRow) => (
+              <TableRow
+                key={row.card_id}
+                sx={{
+                  "&:last-child td, &:last-child th": { border: 0 },
+                  cursor: "pointer",
+                }}
+                onClick={() => onCardSelect(row)}
+                hover
+              >
+                <TableCell component="th" scope="row">
+                  {truncateText(row.sort_field)}
+                </TableCell>
+                <TableCell align="center">{row.template_name}</TableCell>
+                <TableCell align="center">{formatDueDate(row.due)}</TableCell>
+                <TableCell align="center">
+                  {formatRetrievability(row)}
+                </TableCell>
+                <TableCell align="center">{row.deck_name}</TableCell>
+              </TableRow>
+            ))}
+          </TableBody>
+        </Table>
+      </TableContainer>
+      <TablePagination
+        component="div"
+        count={total}
+        page={page}
+        rowsPerPage={pageSize}
+        rowsPerPageOptions={PAGE_SIZE_OPTIONS}
+        labelRowsPerPage="每页"
+        labelDisplayedRows={({ from, to, count }) =>
+          `${from}-${to}，共 ${count} 张`
+        }
+        onPageChange={(_, newPage) => onPageChange(newPage)}
+        onRowsPerPageChange={(event) =>
+          onPageSizeChange(parseInt(event.target.value, 10))
+        }
+      />
+    </Paper>
   );
 };
 
@@ -234,13 +239,17 @@ const statusBitMap: Record<string, number> = {
 function CardEditMain() {
   const navigate = useNavigate();
   const [isLoading, setIsLoading] = useState<boolean>(false);
-  const [cards, setCards] = useState<CardDataEditView[]>([]);
 
-  // 模板和牌组映射数据
-  const [templateMap, setTemplateMap] = useState<Map<number, string>>(
-    new Map()
-  );
-  const [deckMap, setDeckMap] = useState<Map<number, DeckOption>>(new Map());
+  // 当前页的卡片和符合条件的卡片总数
+  const [rows, setRows] = useState<CardBrowserRow[]>([]);
+  const [total, setTotal] = useState(0);
+  const [page, setPage] = useState(0);
+  const [pageSize, setPageSize] = useState(PAGE_SIZE_OPTIONS[1]);
+
+  // 各页的翻页游标，由前一页的结果得到，筛选、排序或每页数量改变时清空
+  const pageCursors = useRef(new Map<number, CardBrowserCursor>());
+
+  // 模板和牌组数据
   const [templates, setTemplates] = useState<{ id: number; name: string }[]>(
     []
   );
@@ -256,35 +265,20 @@ function CardEditMain() {
     templates: [],
   });
 
+  // 卡片排序方式
+  const [sort, setSort] = useState<CardBrowserSort>("due");
+  const [descending, setDescending] = useState(false);
+
+  // 列表刷新计数，删除卡片后递增以重新获取当前页
+  const [refreshCount, setRefreshCount] = useState(0);
+
   // 从后端获取模板和牌组数据
   useEffect(() => {
     const fetchTemplatesAndDecks = async () => {
       setIsLoading(true);
       try {
-        // 获取所有模板
-        const templatesData = await getAllTemplates();
-        setTemplates(templatesData);
-
-        // 创建模板ID到模板名称的映射
-        const templateMapData = new Map<number, string>();
-        templatesData.forEach((template) => {
-          templateMapData.set(template.id, template.name);
-        });
-        setTemplateMap(templateMapData);
-
-        // 获取所有牌组
-        const decksData = await fetchDecks();
-        setDecks(decksData);
-
-        // 创建牌组ID到牌组信息的映射
-        const deckMapData = new Map<number, DeckOption>();
-        decksData.forEach((deck) => {
-          deckMapData.set(deck.deckId, {
-            id: deck.deckId,
-            name: deck.deckName,
-          });
-        });
-        setDeckMap(deckMapData);
+        setTemplates(await getAllTemplates());
+        setDecks(await fetchDecks());
       } catch (error) {
         console.error("获取模板和牌组数据失败:", error);
       } finally {
@@ -308,27 +302,37 @@ function CardEditMain() {
     return templates.map((template) => template.name);
   }, [templates]);
 
-  // 处理筛选条件变化
+  // 处理筛选条件变化，回到第一页
   const handleFilterChange = (newFilters: FilterOptions) => {
     setFilterOptions(newFilters);
+    pageCursors.current.clear();
+    setPage(0);
   };
 
-  // 存储原始的后端卡片数据，用于在选择卡片时获取完整的 CardData
-  const [backendCardsMap, setBackendCardsMap] = useState<Map<number, CardData>>(
-    new Map()
-  );
+  // 处理排序方式变化，回到第一页
+  const handleSortChange = (
+    newSort: CardBrowserSort,
+    newDescending: boolean
+  ) => {
+    setSort(newSort);
+    setDescending(newDescending);
+    pageCursors.current.clear();
+    setPage(0);
+  };
 
-  // 根据筛选条件获取卡片
-  useEffect(() => {
-    const fetchFilteredCards = async () => {
-      // 如果模板和牌组数据还未加载完成，则不进行筛选
-      if (templates.length === 0 || decks.length === 0) {
-        return;
-      }
+  // 处理每页数量变化，回到第一页
+  const handlePageSizeChange = (newPageSize: number) => {
+    setPageSize(newPageSize);
+    pageCursors.current.clear();
+    setPage(0);
+  };
 
+  // 根据筛选条件、排序方式和分页获取当前页的卡片
+  useEffect(() => {
+    const fetchPage = async () => {
       setIsLoading(true);
 
-      // 转换模板名称为模板ID
+      // 转换模板名称为模板ID，没有选择时不筛选
       const templateIds = filterOptions.templates
         .map((templateName) => {
           const template = templates.find((t) => t.name === templateName);
@@ -336,143 +340,71 @@ function CardEditMain() {
         })
         .filter((id) => id !== -1);
 
-      // 获取选中的牌组ID
-      const deckIds = filterOptions.decks;
-
-      // 计算状态位过滤器
+      // 计算状态位过滤器，0 表示所有状态
       let statusBitFilter = 0;
       filterOptions.statuses.forEach((status) => {
         statusBitFilter |= statusBitMap[status] || 0;
       });
 
-      // 如果没有选择任何筛选条件，则使用所有模板和牌组
-      const finalTemplateIds =
-        templateIds.length > 0 ? templateIds : templates.map((t) => t.id);
-      const finalDeckIds =
-        deckIds.length > 0 ? deckIds : decks.map((d) => d.deckId);
-      const finalStatusBitFilter = statusBitFilter > 0 ? statusBitFilter : 7; // 7 = 0111，表示所有状态
-
       try {
-        const backendCards = await filterCards(
-          finalTemplateIds,
-          finalDeckIds,
-          finalStatusBitFilter
-        );
-
-        if (backendCards) {
-          // 创建一个 Map 用于存储原始的后端卡片数据，以便在选择卡片时获取完整的 CardData
-          const cardsMap = new Map<number, CardData>();
-          backendCards.forEach((card) => {
-            cardsMap.set(card.card_id, card);
-          });
-          setBackendCardsMap(cardsMap);
-
-          // 将后端卡片数据转换为前端卡片数据（用于表格显示）
-          const frontendCards = backendCards.map((card) =>
-            convertBackendCardToFrontend(card, templateMap, deckMap)
-          );
-          setCards(frontendCards);
-        } else {
-          setBackendCardsMap(new Map());
-          setCards([]);
+        const result = await browseCards({
+          template_ids: templateIds,
+          deck_ids: filterOptions.decks,
+          status_bit_filter: statusBitFilter,
+          sort,
+          descending,
+          page,
+          page_size: pageSize,
+          // 有游标时从前一页的最后一张卡片之后开始，否则按页码跳过前面的卡片
+          after: pageCursors.current.get(page) ?? null,
+        });
+        if (result?.next_cursor) {
+          pageCursors.current.set(page + 1, result.next_cursor);
+        }
+        setRows(result ? result.rows : []);
+        setTotal(result ? result.total : 0);
+        // 删除卡片后当前页可能已超出范围，回到最后一页
+        if (result && result.rows.length === 0 && page > 0) {
+          setPage(Math.max(0, Math.ceil(result.total / pageSize) - 1));
         }
       } catch (error) {
         console.error("获取卡片失败:", error);
-        setBackendCardsMap(new Map());
-        setCards([]);
+        setRows([]);
+        setTotal(0);
       } finally {
         setIsLoading(false);
       }
     };
 
-    fetchFilteredCards();
-  }, [filterOptions, templates, decks, templateMap, deckMap]);
+    fetchPage();
+  }, [
+    filterOptions,
+    sort,
+    descending,
+    page,
+    pageSize,
+    templates,
+    refreshCount,
+  ]);
 
   // 跳转到添加卡片页面
   const handleAddCard = () => {
     navigate("/card-add");
   };
 
-  // 处理卡片选择 - 使用原始的 CardData 对象
-  const handleCardSelect = (card: CardDataEditView) => {
-    // 从 backendCardsMap 中获取对应的原始 CardData 对象
-    const backendCard = backendCardsMap.get(card.cardId);
-    if (backendCard) {
-      setSelectedCard(backendCard);
+  // 处理卡片选择 - 获取卡片的完整内容用于预览和编辑
+  const handleCardSelect = async (row: CardBrowserRow) => {
+    const card = await getCardDetail(row.card_id);
+    if (card) {
+      setSelectedCard(card);
     }
   };
 
   // 处理卡片删除后的回调
   const handleCardDeleted = () => {
-    // 清空选中的卡片
+    // 清空选中的卡片并重新获取当前页
     setSelectedCard(null);
-    // 重新获取卡片列表
-    const fetchFilteredCards = async () => {
-      // 如果模板和牌组数据还未加载完成，则不进行筛选
-      if (templates.length === 0 || decks.length === 0) {
-        return;
-      }
-
-      setIsLoading(true);
-
-      // 转换模板名称为模板ID
-      const templateIds = filterOptions.templates
-        .map((templateName) => {
-          const template = templates.find((t) => t.name === templateName);
-          return template ? template.id : -1;
-        })
-        .filter((id) => id !== -1);
-
-      // 获取选中的牌组ID
-      const deckIds = filterOptions.decks;
-
-      // 计算状态位过滤器
-      let statusBitFilter = 0;
-      filterOptions.statuses.forEach((status) => {
-        statusBitFilter |= statusBitMap[status] || 0;
-      });
-
-      // 如果没有选择任何筛选条件，则使用所有模板和牌组
-      const finalTemplateIds =
-        templateIds.length > 0 ? templateIds : templates.map((t) => t.id);
-      const finalDeckIds =
-        deckIds.length > 0 ? deckIds : decks.map((d) => d.deckId);
-      const finalStatusBitFilter = statusBitFilter > 0 ? statusBitFilter : 7; // 7 = 0111，表示所有状态
-
-      try {
-        const backendCards = await filterCards(
-          finalTemplateIds,
-          finalDeckIds,
-          finalStatusBitFilter
-        );
-
-        if (backendCards) {
-          // 创建一个 Map 用于存储原始的后端卡片数据，以便在选择卡片时获取完整的 CardData
-          const cardsMap = new Map<number, CardData>();
-          backendCards.forEach((card) => {
-            cardsMap.set(card.card_id, card);
-          });
-          setBackendCardsMap(cardsMap);
-
-          // 将后端卡片数据转换为前端卡片数据（用于表格显示）
-          const frontendCards = backendCards.map((card) =>
-            convertBackendCardToFrontend(card, templateMap, deckMap)
-          );
-          setCards(frontendCards);
-        } else {
-          setBackendCardsMap(new Map());
-          setCards([]);
-        }
-      } catch (error) {
-        console.error("获取卡片失败:", error);
-        setBackendCardsMap(new Map());
-        setCards([]);
-      } finally {
-        setIsLoading(false);
-      }
-    };
-
-    fetchFilteredCards();
+    setRefreshCount((count) => count + 1);
   };
 
   return (
@@ -505,6 +437,41 @@ function CardEditMain() {
             alignItems: "center",
           }}
         >
+          <FormControl size="small" sx={{ minWidth: 140 }}>
+            <InputLabel id="sort-select-label">排序方式</InputLabel>
+            <Select
+              labelId="sort-select-label"
+              value={sort}
+              label="排序方式"
+              onChange={(event) =>
+                handleSortChange(
+                  event.target.value as CardBrowserSort,
+                  descending
+                )
+              }
+            >
+              {(Object.keys(cardBrowserSortLabels) as CardBrowserSort[]).map(
+                (option) => (
+                  <MenuItem key={option} value={option}>
+                    {cardBrowserSortLabels[option]}
+                  </MenuItem>
+                )
+              )}
+            </Select>
+          </FormControl>
+          <Tooltip title={descending ? "降序" : "升序"}>
+            <IconButton
+              size="small"
+              onClick={() => handleSortChange(sort, !descending)}
+              sx={{ ml: 1 }}
+            >
+              {descending ? (
+                <ArrowDownwardIcon fontSize="small" />
+              ) : (
+                <ArrowUpwardIcon fontSize="small" />
+              )}
+            </IconButton>
+          </Tooltip>
           <Button
             variant="contained"
             color="primary"
@@ -528,8 +495,16 @@ function CardEditMain() {
       <Grid container spacing={2} sx={{ flexGrow: 1 }}>
         <Grid size={5} sx={{ md: 7 }}>
           <CardTable
-            rows={cards}
+            rows={rows}
+            total={total}
+            page={page}
+            pageSize={pageSize}
+            sort={sort}
+            descending={descending}
             onCardSelect={handleCardSelect}
+            onSortChange={handleSortChange}
+            onPageChange={setPage}
+            onPageSizeChange={handlePageSizeChange}
             isLoading={isLoading}
           />
         </Grid>
@@ -169,12 +169,8 @@ function TemplateAdd() {
 
     setLoading(true);
     try {
-      // 1. 获取文件内容
+      // 1. 模板类由上传的文件名确定
       const fileName = uploadedFile!.name;
-      const fileContent = await uploadedFile!.text();
-
-      // 注意：由于浏览器安全限制，无法直接写入本地文件系统
-      // 文件内容将通过 API 传递给后端处理
 
       // 2. 准备字段数据
       const templateFields: [string, boolean][] = fields.map((field) => [
@@ -182,14 +178,13 @@ function TemplateAdd() {
         field.isFront,
       ]);
 
-      // 3. 调用API添加模板（包含文件内容）
+      // 3. 调用API添加模板并保存渲染配置
       const importPath = `@/CardMemo/templates/${fileName.replace(".tsx", "")}`;
       const result = await addTemplate(
         templateName,
         templateFields,
         className,
-        importPath,
-        fileContent // 传递文件内容给后端
+        importPath
       );
 
       if (result.success) {
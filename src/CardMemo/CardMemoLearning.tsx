@@ -3,13 +3,17 @@ import ArrowBackIcon from "@mui/icons-material/ArrowBack";
 import { useLocation, useNavigate } from "react-router-dom";
 import { useEffect, useState } from "react";
 import {
-  loadCardsFromBackend,
-  loadNextState,
-  submitCardRating,
-  CardData,
+  answerStudyCard,
+  endStudySession,
+  nextStudyCard,
+  resumeStudySession,
+  startStudySession,
+  suspendStudySession,
   NextIntervals,
+  StudyCardData,
+  StudyProgress,
 } from "@/api/Card";
-import { createDeckData } from "@/api/Deck";
+import { DeckData, fetchDecks } from "@/api/Deck";
 import { loadTemplate, TemplateData } from "@/api/Template";
 import { TemplateFactory } from "@/CardMemo/templates/TemplateFactory";
 import { TemplateInterface } from "@/CardMemo/templates/TemplateInterface";
@@ -19,19 +23,21 @@ function CardMemoLearning() {
   const location = useLocation();
   const navigate = useNavigate();
 
-  const [deckData, setDeckData] = useState(createDeckData(0, "", 0, 0, 0));
+  const [deckName, setDeckName] = useState("");
+  // 所有牌组，一起学习多个牌组时用于显示各牌组的进度
+  const [decks, setDecks] = useState<DeckData[]>([]);
 
-  const [correctCount, setCorrectCount] = useState(0);
-  const [startTime, setStartTime] = useState(0);
+  // 后端学习会话的进度，会话ID用于获取卡片和提交评分
+  const [progress, setProgress] = useState<StudyProgress | null>(null);
+  // 当前卡片开始显示的时间，用于记录回答用时
+  const [cardShownAt, setCardShownAt] = useState(0);
 
-  // 卡片缓存和当前卡片状态
-  const [cardCache, setCardCache] = useState<CardData[]>([]);
-  const [currentCardIndex, setCurrentCardIndex] = useState(0);
+  // 当前学习的卡片
+  const [studyCard, setStudyCard] = useState<StudyCardData | null>(null);
   const [_, setCurrentTemplate] = useState<TemplateData | null>(null);
   const [nextIntervals, setNextIntervals] = useState<NextIntervals | null>(
     null
   );
-  const [__, setIsLoading] = useState(false);
 
   // 当前使用的模板实例
   const [templateInstance, setTemplateInstance] =
@@ -41,131 +47,123 @@ function CardMemoLearning() {
   const [parsedCardContent, setParsedCardContent] = useState<any>(null);
 
   // 返回主页的处理函数
-  const handleReturnHome = () => {
-    // 确认是否要返回主页
-    if (window.confirm("确定要返回主页吗？当前学习进度将不会保存。")) {
+  const handleReturnHome = async () => {
+    // 确认是否要返回主页，学习会话会被保存，之后可以继续
+    if (window.confirm("确定要返回主页吗？之后可以继续本次学习。")) {
+      // 保存失败时会话仍然打开，停留在当前页面
+      if (progress && !(await suspendStudySession(progress.session_id))) {
+        window.alert("保存学习会话失败，请稍后再试。");
+        return;
+      }
       navigate("/");
     }
   };
 
-  const processCurrentCard = async () => {
-    // 如果没有卡片或已经学习完所有卡片
-    if (cardCache.length === 0) {
+  // 结束学习会话并跳转到结束页面
+  const finishStudy = async (sessionId: number) => {
+    const finalProgress = await endStudySession(sessionId);
+    navigate("/card-memo-end", {
+      state: {
+        deckName,
+        totalCards: finalProgress?.answered ?? 0,
+        correctCount: finalProgress?.correct ?? 0,
+        studyTime: finalProgress?.elapsed_ms ?? 0,
+      },
+    });
+  };
+
+  // 从学习会话获取当前卡片并加载对应模板
+  const showNextCard = async (sessionId: number) => {
+    let next: StudyCardData | null;
+    try {
+      next = await nextStudyCard(sessionId);
+    } catch (error) {
+      console.error("获取卡片失败:", error);
       return;
     }
-
-    // 如果当前索引超出缓存范围，尝试加载更多卡片
-    if (currentCardIndex >= cardCache.length) {
-      const hasMoreCards = await loadCardsFromBackend(
-        deckData.deckId,
-        10,
-        setCardCache,
-        setCurrentCardIndex,
-        setIsLoading
-      );
-      if (!hasMoreCards) {
-        // 没有更多卡片，结束学习
-        const endTime = Date.now();
-        const totalStudyTime = endTime - startTime;
-
-        navigate("/card-memo-end", {
-          state: {
-            deckName: deckData.deckName,
-            totalCards:
-              deckData.toreview + deckData.learning + deckData.tolearn,
-            correctCount: correctCount,
-            studyTime: totalStudyTime,
-          },
-        });
-        return;
-      }
+    if (!next) {
+      await finishStudy(sessionId);
+      return;
     }
 
-    // 获取当前卡片
-    const card = cardCache[currentCardIndex];
-
-    // 加载模板和下一状态
-    const template = await loadTemplate(card.template_id, setCurrentTemplate);
-    await loadNextState(card, setNextIntervals);
+    setStudyCard(next);
+    setProgress(next.progress);
+    setNextIntervals(next.intervals);
 
+    const template = await loadTemplate(
+      next.card.template_id,
+      setCurrentTemplate
+    );
     if (template) {
       // 使用模板工厂创建对应的模板实例
       const instance = await TemplateFactory.createTemplate(template);
       setTemplateInstance(instance);
 
       // 使用模板实例解析卡片内容
-      const content = instance.parseCardContent(card, template);
+      const content = instance.parseCardContent(next.card, template);
       setParsedCardContent(content);
     }
+    setCardShownAt(Date.now());
   };
 
   // first render
   useEffect(() => {
-    const { deckId, deckName, tolearn, learning, toreview } =
-      location.state || {};
-
-    // 设置开始学习时间
-    setStartTime(Date.now());
-
-    setDeckData({
-      deckId: deckId,
-      deckName: deckName,
-      tolearn: tolearn,
-      learning: learning,
-      toreview: toreview,
-    });
-
-    // 从后端加载卡片
-    if (tolearn + learning + toreview > 0) {
-      loadCardsFromBackend(
-        deckId,
-        10,
-        setCardCache,
-        setCurrentCardIndex,
-        setIsLoading
-      );
-      return;
-    }
-    navigate("/card-memo-end", {
-      state: {
-        deckName: deckName,
-        totalCards: 0,
-        correctCount: 0,
-        studyTime: 0,
-      },
-    });
+    const { deckIds, deckName, mode, resumeSessionId } = location.state || {};
+    setDeckName(deckName);
+    fetchDecks().then(setDecks);
+
+    const start = async () => {
+      // 继续保存的学习会话，或开始新的学习会话
+      const initialProgress =
+        resumeSessionId !== undefined
+          ? await resumeStudySession(resumeSessionId)
+          : await startStudySession(deckIds, mode);
+      if (!initialProgress) {
+        navigate("/");
+        return;
+      }
+      setProgress(initialProgress);
+      await showNextCard(initialProgress.session_id);
+    };
+    start();
   }, []);
 
-  // 处理卡片缓存和当前卡片索引变化
-  useEffect(() => {
-    processCurrentCard();
-  }, [cardCache, currentCardIndex]);
-
   const handleCardRating = async (rating: number) => {
+    if (!progress || !studyCard) {
+      return;
+    }
     console.log(`卡片评分: ${rating}`);
 
-    // 调用 emitCorrect 函数，根据评分判断是否为正确答案
-    emitCorrect(rating);
-
     // 提交评分到后端
-    await submitCardRating(rating);
+    const answered = await answerStudyCard(
+      progress.session_id,
+      studyCard.card.card_id,
+      rating,
+      Date.now() - cardShownAt
+    );
+    if (answered) {
+      setProgress(answered);
+    }
 
-    // 移动到下一张卡片
-    setCurrentCardIndex(currentCardIndex + 1);
+    // 移动到下一张卡片。评分未保存时后端仍然展示当前卡片，
+    // 评分已保存但会话保存失败时继续下一张卡片
+    await showNextCard(progress.session_id);
   };
 
-  // 发出正确答案信号的函数
-  const emitCorrect = (rating: number) => {
-    // 只有当评分大于1（即不是"忘记"按钮）时才计为正确
-    if (rating > 1) {
-      setCorrectCount(correctCount + 1);
-    }
-  };
+  // 正确数由后端学习会话统计，这里保留给模板使用的回调
+  const emitCorrect = (_rating: number) => {};
 
   return (
     <Box sx={{ minWidth: 320, width: "100%" }}>
       {/* 返回主页按钮 */}
-      <Box sx={{ display: "flex", justifyContent: "flex-start", mb: 2 }}>
+      <Box
+        sx={{
+          display: "flex",
+          justifyContent: "space-between",
+          alignItems: "center",
+          mb: 2,
+        }}
+      >
         <Button
           variant="outlined"
           startIcon={<ArrowBackIcon />}
@@ -174,8 +172,43 @@ function CardMemoLearning() {
         >
           返回主页
         </Button>
+        {/* 剩余卡片数量 */}
+        {progress && (
+          <Box sx={{ color: "text.secondary", fontSize: 14 }}>
+            未学习 {progress.counts.new_count} · 学习中{" "}
+            {progress.counts.learning_count} · 待复习{" "}
+            {progress.counts.review_count}
+          </Box>
+        )}
       </Box>
 
+      {/* 一起学习多个牌组时各牌组的剩余卡片数量 */}
+      {progress && progress.decks.length > 1 && (
+        <Box
+          sx={{
+            display: "flex",
+            flexWrap: "wrap",
+            justifyContent: "flex-end",
+            gap: 2,
+            mb: 2,
+            color: "text.secondary",
+            fontSize: 12,
+          }}
+        >
+          {progress.decks.map((deck) => (
+            <span key={deck.deck_id}>
+              {decks.find((row) => row.deckId === deck.deck_id)?.deckName ??
+                "未知牌组"}
+              ：剩余{" "}
+              {deck.counts.new_count +
+                deck.counts.learning_count +
+                deck.counts.review_count}{" "}
+              · 已回答 {deck.answered}
+            </span>
+          ))}
+        </Box>
+      )}
+
       {templateInstance && parsedCardContent ? (
         // 使用模板实例渲染卡片
         templateInstance.renderCard({
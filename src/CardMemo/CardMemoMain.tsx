@@ -24,8 +24,20 @@ import CardMemoStart from "./CardMemoStart";
 import AddDeckDialog from "./AddDeckDialog";
 import Box from "@mui/material/Box";
 import { NavigateFunction, useNavigate } from "react-router-dom";
-import { DeckData, fetchDecks, deleteDeck } from "@/api/Deck";
-import { fetchLearningCount } from "@/api/Card";
+import {
+  DeckData,
+  createDeckData,
+  fetchDecks,
+  deleteDeck,
+  studyDecksName,
+} from "@/api/Deck";
+import {
+  endStudySession,
+  fetchLearningCount,
+  getResumableStudySession,
+  StudyMode,
+  StudyProgress,
+} from "@/api/Card";
 import IconButton from "@mui/material/IconButton";
 import DeleteIcon from "@mui/icons-material/Delete";
 
@@ -40,6 +52,9 @@ const TextButton = styled(Button)({
   justifyContent: "flex-start",
 });
 
+// 全部牌组的汇总行，deckId 为 0
+const ALL_DECKS_ID = 0;
+
 const DenseTable = ({ rows, navigate, refreshDecks }: DenseTableProps) => {
   const [open, setOpen] = useState(false);
   const [selectedDeck, setSelectedDeck] = useState<DeckData | null>(null);
@@ -55,13 +70,16 @@ const DenseTable = ({ rows, navigate, refreshDecks }: DenseTableProps) => {
     setOpen(false);
   };
 
-  const handleStartStudy = () => {
+  const handleStartStudy = (mode: StudyMode) => {
     console.log(`开始学习牌组: ${selectedDeck?.deckName}`);
     setOpen(false);
     if (selectedDeck) {
       navigate("/card-memo-learning", {
         state: {
-          deckId: selectedDeck.deckId,
+          mode,
+          // 空列表表示学习所有牌组
+          deckIds:
+            selectedDeck.deckId === ALL_DECKS_ID ? [] : [selectedDeck.deckId],
           deckName: selectedDeck.deckName,
           tolearn: selectedDeck.tolearn,
           learning: selectedDeck.learning,
@@ -101,6 +119,18 @@ const DenseTable = ({ rows, navigate, refreshDecks }: DenseTableProps) => {
     setDeckToDelete(null);
   };
 
+  // 有多个牌组时可以一起学习所有牌组今天到期的卡片
+  const allDecks =
+    rows.length > 1
+      ? createDeckData(
+          ALL_DECKS_ID,
+          "全部牌组",
+          rows.reduce((sum, row) => sum + row.tolearn, 0),
+          rows.reduce((sum, row) => sum + row.learning, 0),
+          rows.reduce((sum, row) => sum + row.toreview, 0)
+        )
+      : null;
+
   return (
     <>
       <TableContainer component={Paper}>
@@ -115,6 +145,19 @@ const DenseTable = ({ rows, navigate, refreshDecks }: DenseTableProps) => {
             </TableRow>
           </TableHead>
           <TableBody>
+            {allDecks && (
+              <TableRow>
+                <TableCell component="th" scope="row">
+                  <TextButton onClick={() => handleOpen(allDecks)}>
+                    <b>{allDecks.deckName}</b>
+                  </TextButton>
+                </TableCell>
+                <TableCell align="center">{allDecks.tolearn}</TableCell>
+                <TableCell align="center">{allDecks.learning}</TableCell>
+                <TableCell align="center">{allDecks.toreview}</TableCell>
+                <TableCell />
+              </TableRow>
+            )}
             {rows.map((row: DeckData) => (
               <TableRow
                 key={row.deckName}
@@ -165,6 +208,7 @@ const DenseTable = ({ rows, navigate, refreshDecks }: DenseTableProps) => {
         >
           {selectedDeck && (
             <CardMemoStart
+              deckId={selectedDeck.deckId}
               deckName={selectedDeck.deckName}
               tolearn={selectedDeck.tolearn}
               learning={selectedDeck.learning}
@@ -206,8 +250,29 @@ function CardMemoMain() {
   const [error, setError] = useState<string | null>(null);
   const [snackbarOpen, setSnackbarOpen] = useState(false);
   const [addDeckDialogOpen, setAddDeckDialogOpen] = useState(false);
+  // 上次未完成、可以继续的学习会话
+  const [resumable, setResumable] = useState<StudyProgress | null>(null);
   const navigate = useNavigate();
 
+  // 继续上次未完成的学习
+  const handleResume = () => {
+    if (!resumable) return;
+    navigate("/card-memo-learning", {
+      state: {
+        deckIds: resumable.deck_ids,
+        deckName: studyDecksName(resumable.deck_ids, rows),
+        resumeSessionId: resumable.session_id,
+      },
+    });
+  };
+
+  // 放弃上次未完成的学习，已回答的卡片不受影响
+  const handleDiscardResumable = async () => {
+    if (!resumable) return;
+    await endStudySession(resumable.session_id);
+    setResumable(await getResumableStudySession());
+  };
+
   // 处理错误提示关闭
   const handleSnackbarClose = () => {
     setSnackbarOpen(false);
@@ -234,6 +299,7 @@ function CardMemoMain() {
     try {
       const rowList = await fetchDecks();
       setRows(rowList);
+      setResumable(await getResumableStudySession());
       try {
         await fetchLearningCount(setLearningNumber);
       } catch (learningError) {
@@ -300,8 +366,41 @@ function CardMemoMain() {
   }
 
   if (rows.length !== 0) {
+    const resumableDecksName = resumable
+      ? studyDecksName(resumable.deck_ids, rows)
+      : "";
+    const resumableRemaining = resumable
+      ? resumable.counts.new_count +
+        resumable.counts.learning_count +
+        resumable.counts.review_count
+      : 0;
+
     return (
       <div>
+        {/* 上次未完成的学习 */}
+        {resumable && (
+          <Alert
+            severity="info"
+            sx={{ mb: 2 }}
+            action={
+              <>
+                <Button color="inherit" size="small" onClick={handleResume}>
+                  继续学习
+                </Button>
+                <Button
+                  color="inherit"
+                  size="small"
+                  onClick={handleDiscardResumable}
+                >
+                  放弃
+                </Button>
+              </>
+            }
+          >
+            "{resumableDecksName}" 的学习尚未完成：已回答 {resumable.answered} 次，还剩{" "}
+            {resumableRemaining} 张卡片。
+          </Alert>
+        )}
         <DenseTable
           navigate={navigate}
           rows={rows}
@@ -6,16 +6,106 @@ import Typography from "@mui/material/Typography";
 import Box from "@mui/material/Box";
 import Grid from "@mui/material/Grid2";
 import Paper from "@mui/material/Paper";
+import FormControl from "@mui/material/FormControl";
+import InputLabel from "@mui/material/InputLabel";
+import Select from "@mui/material/Select";
+import MenuItem from "@mui/material/MenuItem";
+import TextField from "@mui/material/TextField";
+import { useEffect, useState } from "react";
+import {
+  QueueOptions,
+  getDeckQueueOptions,
+  setDeckQueueOptions,
+  newCardOrderLabels,
+  reviewOrderLabels,
+  newReviewMixLabels,
+} from "@/api/Deck";
+import { StudyMode } from "@/api/Card";
 
 interface CardMemoStartProps {
+  deckId: number; // 为 0 时学习所有牌组
   deckName: string;
   tolearn: number;
   learning: number;
   toreview: number;
-  onStartStudy: () => void;
+  onStartStudy: (mode: StudyMode) => void;
+}
+
+// 学习队列选项的下拉框
+function QueueOptionSelect<K extends keyof QueueOptions>({
+  label,
+  value,
+  labels,
+  onChange,
+}: {
+  label: string;
+  value: QueueOptions[K];
+  labels: Record<QueueOptions[K], string>;
+  onChange: (value: QueueOptions[K]) => void;
+}) {
+  return (
+    <FormControl fullWidth size="small">
+      <InputLabel>{label}</InputLabel>
+      <Select
+        value={value}
+        label={label}
+        onChange={(event) => onChange(event.target.value as QueueOptions[K])}
+      >
+        {(Object.keys(labels) as QueueOptions[K][]).map((option) => (
+          <MenuItem key={option} value={option}>
+            {labels[option]}
+          </MenuItem>
+        ))}
+      </Select>
+    </FormControl>
+  );
+}
+
+// 每天学习上限的输入框，离开输入框时保存，留空表示不限制
+function DailyLimitField({
+  label,
+  value,
+  onChange,
+}: {
+  label: string;
+  value: number | null;
+  onChange: (value: number | null) => void;
+}) {
+  const [text, setText] = useState(value === null ? "" : String(value));
+
+  useEffect(() => {
+    setText(value === null ? "" : String(value));
+  }, [value]);
+
+  const save = () => {
+    const limit =
+      text.trim() === "" ? null : Math.max(0, Math.floor(Number(text)));
+    if (limit !== null && Number.isNaN(limit)) {
+      setText(value === null ? "" : String(value));
+      return;
+    }
+    if (limit !== value) {
+      onChange(limit);
+    }
+  };
+
+  return (
+    <TextField
+      fullWidth
+      size="small"
+      type="number"
+      label={label}
+      placeholder="不限制"
+      value={text}
+      onChange={(event) => setText(event.target.value)}
+      onBlur={save}
+      slotProps={{ htmlInput: { min: 0 } }}
+    />
+  );
 }
 
 function CardMemoStart({
+  deckId,
   deckName,
   tolearn,
   learning,
@@ -23,6 +113,25 @@ function CardMemoStart({
   onStartStudy,
 }: CardMemoStartProps) {
   const totalCards = tolearn + learning + toreview;
+  const [queueOptions, setQueueOptions] = useState<QueueOptions | null>(null);
+
+  useEffect(() => {
+    // 学习所有牌组时各牌组使用自己的学习队列选项
+    if (deckId === 0) {
+      setQueueOptions(null);
+      return;
+    }
+    getDeckQueueOptions(deckId).then(setQueueOptions);
+  }, [deckId]);
+
+  // 修改并保存学习队列选项
+  const updateQueueOptions = async (patch: Partial<QueueOptions>) => {
+    if (!queueOptions) return;
+    const options = { ...queueOptions, ...patch };
+    if (await setDeckQueueOptions(deckId, options)) {
+      setQueueOptions(options);
+    }
+  };
 
   return (
     <Card sx={{ maxWidth: 600, mx: "auto", mt: 4 }}>
@@ -86,22 +195,98 @@ function CardMemoStart({
           <Typography variant="subtitle1" align="center" sx={{ mt: 3 }}>
             今天共有 <b>{totalCards}</b> 张卡片需要学习
             <br />
+            {deckId === 0 && (
+              <>
+                各牌组按自己的学习队列选项排序和限制数量，并轮流出现
+                <br />
+              </>
+            )}
             （点击空白处退出）
           </Typography>
+
+          {queueOptions && (
+            <Grid container spacing={2} sx={{ mt: 3 }}>
+              <Grid size={4}>
+                <QueueOptionSelect<"new_card_order">
+                  label="新卡片顺序"
+                  value={queueOptions.new_card_order}
+                  labels={newCardOrderLabels}
+                  onChange={(value) =>
+                    updateQueueOptions({ new_card_order: value })
+                  }
+                />
+              </Grid>
+              <Grid size={4}>
+                <QueueOptionSelect<"review_order">
+                  label="复习顺序"
+                  value={queueOptions.review_order}
+                  labels={reviewOrderLabels}
+                  onChange={(value) =>
+                    updateQueueOptions({ review_order: value })
+                  }
+                />
+              </Grid>
+              <Grid size={4}>
+                <QueueOptionSelect<"new_review_mix">
+                  label="新卡片与复习"
+                  value={queueOptions.new_review_mix}
+                  labels={newReviewMixLabels}
+                  onChange={(value) =>
+                    updateQueueOptions({ new_review_mix: value })
+                  }
+                />
+              </Grid>
+              <Grid size={6}>
+                <DailyLimitField
+                  label="每天新卡片上限"
+                  value={queueOptions.new_cards_per_day}
+                  onChange={(value) =>
+                    updateQueueOptions({ new_cards_per_day: value })
+                  }
+                />
+              </Grid>
+              <Grid size={6}>
+                <DailyLimitField
+                  label="每天复习上限"
+                  value={queueOptions.max_reviews_per_day}
+                  onChange={(value) =>
+                    updateQueueOptions({ max_reviews_per_day: value })
+                  }
+                />
+              </Grid>
+            </Grid>
+          )}
         </Box>
       </CardContent>
 
-      <CardActions>
+      <CardActions disableSpacing sx={{ flexDirection: "column", gap: 1 }}>
         <Button
           variant="contained"
           color="primary"
           fullWidth
           size="large"
-          onClick={onStartStudy}
+          onClick={() => onStartStudy("normal")}
           disabled={totalCards === 0}
         >
           开始学习
         </Button>
+        {/* 额外的学习不按计划进行，复习记录可以在训练FSRS模型时排除 */}
+        <Box display="flex" width="100%" gap={1}>
+          <Button
+            variant="outlined"
+            fullWidth
+            onClick={() => onStartStudy("filtered")}
+          >
+            提前学习
+          </Button>
+          <Button
+            variant="outlined"
+            fullWidth
+            onClick={() => onStartStudy("cram")}
+          >
+            临时抱佛脚
+          </Button>
+        </Box>
       </CardActions>
     </Card>
   );
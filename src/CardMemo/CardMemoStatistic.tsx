@@ -1,4 +1,4 @@
-import { useState } from "react";
+import { useEffect, useState } from "react";
 import Card from "@mui/material/Card";
 import CardContent from "@mui/material/CardContent";
 import Typography from "@mui/material/Typography";
@@ -26,88 +26,125 @@ import {
   ForecastDataItem,
   reviewDataItem,
 } from "@/CardMemo/StatisticUtils";
+import { fetchDecks } from "@/api/Deck";
+import {
+  CardStateCounts,
+  getCardStateStatistics,
+  getDueForecast,
+  getReviewStatistics,
+  StatisticsFilter,
+  toLocalDateString,
+} from "@/api/Statistics";
 
-// 模拟牌组数据 - 实际应用中应从后端获取
-const generateDecks = (): DeckDataItem[] => [
-  { deckIndex: 0, name: "全部", tolearn: 396, learning: 15, toreview: 61 },
-  {
-    deckIndex: 1,
-    name: "示例卡组",
-    tolearn: 159,
-    learning: 6,
-    toreview: 24,
-  },
-  {
-    deckIndex: 2,
-    name: "英语单词",
-    tolearn: 237,
-    learning: 9,
-    toreview: 37,
-  },
-];
-
-// 模拟数据 - 实际应用中应从后端获取
-const generateForecastData = (): ForecastDataItem[] => {
-  const today = new Date();
-  const data = [];
+// 图表横轴显示的日期，如 6/24
+const formatDate = (date: string) => {
+  const [, month, day] = date.split("-");
+  return `${Number(month)}/${Number(day)}`;
+};
 
-  // 过去7天的数据
-  for (let i = 7; i > 0; i--) {
-    const date = new Date(today);
-    date.setDate(today.getDate() - i);
-    data.push({
-      date: `${date.getMonth() + 1}/${date.getDate()}`,
-      missed: Math.floor(Math.random() * 10),
-      type: "past",
-    });
-  }
+// 牌组选项，deckIndex 为牌组ID，0 表示全部牌组
+const loadDecks = async (): Promise<DeckDataItem[]> => {
+  const decks = await fetchDecks();
+  const total = (key: "tolearn" | "learning" | "toreview") =>
+    decks.reduce((sum, deck) => sum + deck[key], 0);
+  return [
+    {
+      deckIndex: 0,
+      name: "全部",
+      tolearn: total("tolearn"),
+      learning: total("learning"),
+      toreview: total("toreview"),
+    },
+    ...decks.map((deck) => ({
+      deckIndex: deck.deckId,
+      name: deck.deckName,
+      tolearn: deck.tolearn,
+      learning: deck.learning,
+      toreview: deck.toreview,
+    })),
+  ];
+};
 
-  // 今天和未来7天的数据
-  for (let i = 0; i < 8; i++) {
-    const date = new Date(today);
-    date.setDate(today.getDate() + i);
-    data.push({
-      date: `${date.getMonth() + 1}/${date.getDate()}`,
-      forecast: Math.floor(Math.random() * 20 + 5),
-      type: i === 0 ? "today" : "future",
-    });
+// 今天逾期未复习的卡片和今后7天需要复习的卡片
+const loadForecastData = async (
+  deckIds: number[]
+): Promise<ForecastDataItem[]> => {
+  const today = new Date();
+  const end = new Date(today);
+  end.setDate(today.getDate() + 7);
+  const forecast = await getDueForecast({
+    deck_ids: deckIds,
+    start: toLocalDateString(today),
+    end: toLocalDateString(end),
+  });
+  if (!forecast) {
+    return [];
   }
 
-  return data;
+  return forecast.days.map((day, index) => ({
+    date: formatDate(day.date),
+    missed: index === 0 ? forecast.overdue : undefined,
+    forecast: day.count,
+    type: index === 0 ? "today" : "future",
+  }));
 };
 
-const generateStatusData = (
-  tolearn: number = 0,
-  learning: number = 0,
-  toreview: number = 0
-) => [
-  { name: "未学习", value: tolearn, color: "#8884d8" },
-  { name: "学习中", value: learning, color: "#82ca9d" },
-  { name: "待复习", value: toreview, color: "#ff8042" },
+const generateStatusData = (counts: CardStateCounts | null) => [
+  { name: "未学习", value: counts?.new ?? 0, color: "#8884d8" },
+  { name: "学习中", value: counts?.learning ?? 0, color: "#82ca9d" },
+  { name: "短期记忆", value: counts?.young ?? 0, color: "#ffc658" },
+  { name: "长期记忆", value: counts?.mature ?? 0, color: "#ff8042" },
 ];
 
-const generateReviewData = (): reviewDataItem[] => {
+// 过去7天每天的复习次数，没有复习的日期为0
+const loadReviewData = async (
+  deckIds: number[]
+): Promise<reviewDataItem[]> => {
   const today = new Date();
-  const data = [];
-
+  const dates = [];
   for (let i = 6; i >= 0; i--) {
     const date = new Date(today);
     date.setDate(today.getDate() - i);
-    data.push({
-      date: `${date.getMonth() + 1}/${date.getDate()}`,
-      reviewed: Math.floor(Math.random() * 30 + 10),
-    });
+    dates.push(toLocalDateString(date));
   }
 
-  return data;
+  const reviews = await getReviewStatistics({
+    deck_ids: deckIds,
+    start: dates[0],
+    end: dates[dates.length - 1],
+  });
+  return dates.map((date) => {
+    const review = reviews.find((item) => item.date === date);
+    return {
+      date: formatDate(date),
+      reviewed: review
+        ? review.again + review.hard + review.good + review.easy
+        : 0,
+    };
+  });
 };
 
 function CardMemoStatistic() {
-  const [decks] = useState(generateDecks());
+  const [decks, setDecks] = useState<DeckDataItem[]>([]);
   const [selectedDeckId, setSelectedDeckId] = useState(0); // 默认选择"全部"
-  const [forecastData, setForecastData] = useState(generateForecastData());
-  const [statusData, setStatusData] = useState(generateStatusData());
-  const [reviewData, setReviewData] = useState(generateReviewData());
+  const [forecastData, setForecastData] = useState<ForecastDataItem[]>([]);
+  const [statusData, setStatusData] = useState(generateStatusData(null));
+  const [reviewData, setReviewData] = useState<reviewDataItem[]>([]);
+
+  useEffect(() => {
+    loadDecks().then(setDecks);
+  }, []);
+
+  // 根据选择的牌组从后端获取统计数据
+  useEffect(() => {
+    const deckIds = selectedDeckId === 0 ? [] : [selectedDeckId];
+    const filter: StatisticsFilter = { deck_ids: deckIds };
+    loadForecastData(deckIds).then(setForecastData);
+    getCardStateStatistics(filter).then((counts) =>
+      setStatusData(generateStatusData(counts))
+    );
+    loadReviewData(deckIds).then(setReviewData);
+  }, [selectedDeckId]);
 
   const handleDeckChange = (e: SelectChangeEvent<number>) => {
     if (e.target.value === null) {
@@ -115,36 +152,6 @@ function CardMemoStatistic() {
       return;
     }
     setSelectedDeckId(Number(e.target.value));
-    // TODO: 根据选择的牌组ID从后端获取相应的统计数据
-    // 这里使用模拟数据进行演示
-    if (e.target.value === 0) {
-      // 全部牌组数据
-      setForecastData(generateForecastData());
-      setStatusData(generateStatusData(23, 49, 32));
-      setReviewData(generateReviewData());
-    } else {
-      // 特定牌组数据 - 这里简单修改一下数据以示区别
-      const multiplier = Number(e.target.value) * 0.2 + 0.5;
-      setForecastData(
-        generateForecastData().map((item) => ({
-          ...item,
-          missed: item.missed ? Math.floor(item.missed * multiplier) : 0,
-          forecast: item.forecast ? Math.floor(item.forecast * multiplier) : 0,
-        }))
-      );
-      setStatusData(
-        generateStatusData(23, 49, 32).map((item) => ({
-          ...item,
-          value: Math.floor(item.value * multiplier),
-        }))
-      );
-      setReviewData(
-        generateReviewData().map((item) => ({
-          ...item,
-          reviewed: Math.floor(item.reviewed * multiplier),
-        }))
-      );
-    }
   };
 
   return (
@@ -172,7 +179,7 @@ function CardMemoStatistic() {
                   color="text.secondary"
                   sx={{ mb: 2 }}
                 >
-                  显示过去未复习的卡片和未来需要复习的卡片数量
+                  显示逾期未复习的卡片和未来需要复习的卡片数量
                 </Typography>
                 <Box sx={{ height: 300 }}>
                   {forecastData.length > 0 ? (
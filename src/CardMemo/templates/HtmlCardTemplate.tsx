@@ -0,0 +1,132 @@
+import { CardData, RenderedCardData, renderCard } from "@/api/Card";
+import { TemplateData } from "@/api/Template";
+import {
+  TemplateInterface,
+  TemplateProps,
+} from "@/CardMemo/templates/TemplateInterface";
+import { useState, useEffect, useRef } from "react";
+import Card from "@mui/material/Card";
+import CardContent from "@mui/material/CardContent";
+import CardActions from "@mui/material/CardActions";
+import Button from "@mui/material/Button";
+import Typography from "@mui/material/Typography";
+
+/**
+ * 在沙箱 iframe 中显示渲染后的卡片
+ *
+ * 卡片字段和模板格式中的 HTML 不能执行脚本，也无法调用应用的接口，
+ * 模板 CSS 只作用于 iframe 内的卡片。iframe 的高度随卡片内容变化
+ */
+function CardFrame({ html, css }: { html: string; css: string }) {
+  const frameRef = useRef<HTMLIFrameElement>(null);
+  const [height, setHeight] = useState(0);
+  const observerRef = useRef<ResizeObserver | null>(null);
+
+  // 不允许脚本，同源只用于读取内容高度
+  const srcDoc = `<!DOCTYPE html><html><head><meta charset="utf-8"><style>body{margin:0;}</style><style>${css}</style></head><body><div class="card">${html}</div></body></html>`;
+
+  const handleLoad = () => {
+    observerRef.current?.disconnect();
+    const body = frameRef.current?.contentDocument?.body;
+    if (!body) {
+      return;
+    }
+    const observer = new ResizeObserver(() => {
+      setHeight(body.scrollHeight);
+    });
+    observer.observe(body);
+    observerRef.current = observer;
+    setHeight(body.scrollHeight);
+  };
+
+  useEffect(() => () => observerRef.current?.disconnect(), []);
+
+  return (
+    <iframe
+      ref={frameRef}
+      title="卡片"
+      sandbox="allow-same-origin"
+      srcDoc={srcDoc}
+      onLoad={handleLoad}
+      style={{ width: "100%", height, border: "none", display: "block" }}
+    />
+  );
+}
+
+function HtmlCard({ cardContent, ratingButtons }: TemplateProps) {
+  const [rendered, setRendered] = useState<RenderedCardData | null>(null);
+  const [showAnswer, setShowAnswer] = useState(false);
+
+  // 当卡片内容变化时重新渲染卡片并隐藏答案
+  useEffect(() => {
+    setShowAnswer(false);
+    setRendered(null);
+    renderCard(cardContent.cardId).then(setRendered);
+  }, [cardContent]);
+
+  return (
+    <Card sx={{ width: "100%", maxWidth: 800, mx: "auto", my: 2 }}>
+      <CardContent>
+        {rendered ? (
+          <CardFrame
+            html={showAnswer ? rendered.back : rendered.front}
+            css={rendered.css}
+          />
+        ) : (
+          <Typography variant="body1">渲染卡片中...</Typography>
+        )}
+      </CardContent>
+
+      <CardActions sx={{ flexDirection: "column", alignItems: "center" }}>
+        {!showAnswer ? (
+          <Button
+            variant="contained"
+            color="primary"
+            onClick={() => {
+              setShowAnswer(true);
+            }}
+            disabled={!rendered}
+            sx={{ mb: 1, width: "50%" }}
+          >
+            显示答案
+          </Button>
+        ) : (
+          <>{ratingButtons}</>
+        )}
+      </CardActions>
+    </Card>
+  );
+}
+
+/**
+ * HTML卡片模板实现，卡片内容由后端按模板的正面、背面格式渲染
+ */
+export class HtmlCardTemplate extends TemplateInterface {
+  /**
+   * 解析卡片内容
+   * @param card 卡片数据
+   * @param _template 模板数据，渲染所需的格式由后端读取
+   * @returns 解析后的卡片内容，包含需要渲染的卡片ID
+   */
+  parseCardContent(card: CardData, _template: TemplateData) {
+    return {
+      cardId: card.card_id,
+      fields: card.template_fields_content,
+    };
+  }
+
+  /**
+   * 渲染卡片组件
+   * @param props 模板属性，包含解析后的卡片内容和评分按钮
+   * @returns JSX元素
+   */
+  renderCard(props: TemplateProps): JSX.Element {
+    return (
+      <HtmlCard
+        cardContent={props.cardContent}
+        emitCorrect={props.emitCorrect}
+        ratingButtons={props.ratingButtons}
+      />
+    );
+  }
+}
@@ -7,6 +7,7 @@ import { TemplateInterface } from "@/CardMemo/templates/TemplateInterface";
 import { TextCardTemplate } from "@/CardMemo/templates/TextCardTemplate";
 import { SelectionCardTemplate } from "@/CardMemo/templates/SelectionCardTemplate";
 import { SpellingCardTemplate } from "@/CardMemo/templates/SpellingCardTemplate";
+import { HtmlCardTemplate } from "@/CardMemo/templates/HtmlCardTemplate";
 
 // 定义模板类映射类型
 type TemplateClassMap = {
@@ -22,6 +23,7 @@ export class TemplateFactory {
     SelectionCardTemplate: SelectionCardTemplate,
     TextCardTemplate: TextCardTemplate,
     SpellingCardTemplate: SpellingCardTemplate,
+    HtmlCardTemplate: HtmlCardTemplate,
   };
 
   // 存储模板配置信息
@@ -35,18 +37,22 @@ export class TemplateFactory {
       this.templateConfig = await getTemplateConfig();
     } catch (error) {
       console.error("初始化模板配置失败:", error);
-      // 设置默认配置
+      // 设置默认配置，模板ID未知，按模板名称匹配内置模板
       this.templateConfig = {
         templates: [
           {
+            templateId: 0,
             templateName: "选择题卡片",
             className: "SelectionCardTemplate",
             importPath: "@/CardMemo/templates/SelectionCardTemplate",
+            options: {},
           },
           {
+            templateId: 0,
             templateName: "正反面卡片",
             className: "TextCardTemplate",
             importPath: "@/CardMemo/templates/TextCardTemplate",
+            options: {},
           },
         ],
       };
@@ -104,10 +110,12 @@ export class TemplateFactory {
     const config = await this.getTemplateConfig();
     console.log("config", config); // Add this line to log the config information
     console.log("template", template); // Add this line to log the template information
-    // 从配置中查找模板名称对应的类名
-    const templateInfo = config.templates.find(
-      (t) => t.templateName === template.template_name
-    );
+    // 从配置中查找模板ID对应的类名，默认配置中没有模板ID时按名称查找
+    const templateInfo =
+      config.templates.find((t) => t.templateId === template.template_id) ??
+      config.templates.find(
+        (t) => t.templateId === 0 && t.templateName === template.template_name
+      );
     console.log("templateInfo", templateInfo); // Add this line to log the templateInfo information
 
     // 如果找到对应的模板信息，则创建对应的模板实例
@@ -123,10 +131,10 @@ export class TemplateFactory {
       return await this.createTemplate(template, true);
     }
 
-    // 默认使用文本卡片模板
+    // 默认使用后端按模板格式渲染的HTML卡片模板
     console.warn(
-      `模板 "${template.template_name}" 未找到对应的实现，使用默认文本卡片模板`
+      `模板 "${template.template_name}" 未找到对应的实现，使用HTML卡片模板`
     );
-    return new TextCardTemplate();
+    return new HtmlCardTemplate();
   }
 }
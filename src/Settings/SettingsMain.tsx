@@ -11,22 +11,100 @@ import {
   Snackbar,
   Alert,
   Divider,
+  LinearProgress,
   Card,
   CardContent,
   CardHeader,
+  TextField,
+  FormControlLabel,
+  Switch,
+  FormControl,
+  InputLabel,
+  Select,
+  MenuItem,
+  Chip,
 } from "@mui/material";
 import {
   getFsrsParams,
+  setFsrsParams as saveFsrsParams,
+  resetFsrsParams,
   trainFsrsModel,
+  cancelFsrsTraining,
+  applyTrainedFsrsParams,
+  getFsrsParamsHistory,
+  revertFsrsParams,
+  ParamsHistoryEntry,
+  ParamsSource,
+  TrainingResult,
   getDesiredRetention,
+  getExcludeFilteredReviews,
+  getAutoOptimize,
+  setAutoOptimize,
+  getLastOptimization,
+  onAutoOptimizeFinished,
+  AutoOptimize,
+  OptimizationRun,
+  setExcludeFilteredReviews,
   setDesiredRetention,
+  simulateRetentionWorkload,
+  recommendDesiredRetention,
+  WorkloadSummary,
+  rescheduleCards,
+  recomputeMemoryStates,
+  JobProgress,
+  DeckPreset,
+  getDeckPresets,
+  setDeckPresets,
 } from "../api/Settings";
+import { fetchDecks, DeckData } from "../api/Deck";
+
+const PARAMS_SOURCE_LABELS: Record<ParamsSource, string> = {
+  default: "默认参数",
+  trained: "训练得到",
+  manual: "手动设置",
+};
+
+// 训练数据的概要
+const datasetSummary = (result: TrainingResult) =>
+  `${result.dataset.card_count} 张卡片的 ${result.dataset.review_count} 条复习记录，` +
+  `${result.dataset.first_day} 至 ${result.dataset.last_day}`;
+
+// 计算期间被复习而保持不变的卡片
+const reviewedDuringJobSummary = (count: number) =>
+  count > 0 ? `，${count} 张卡片在计算期间被复习，保持不变` : "";
 
 function SettingsMain() {
   const [fsrsParams, setFsrsParams] = useState<number[]>([]);
   const [retention, setRetention] = useState<number>(0.9); // 默认值
   const [loading, setLoading] = useState<boolean>(false);
   const [trainingLoading, setTrainingLoading] = useState<boolean>(false);
+  const [excludeFiltered, setExcludeFiltered] = useState<boolean>(true);
+  const [autoOptimize, setAutoOptimizeState] = useState<AutoOptimize | null>(
+    null
+  );
+  const [lastOptimization, setLastOptimization] =
+    useState<OptimizationRun | null>(null);
+  const [paramsInput, setParamsInput] = useState<string | null>(null); // 正在编辑的参数文本
+  const [paramsHistory, setParamsHistory] = useState<ParamsHistoryEntry[]>(
+    []
+  );
+  const [trainingProgress, setTrainingProgress] =
+    useState<JobProgress | null>(null);
+  const [trainingResult, setTrainingResult] = useState<TrainingResult | null>(
+    null
+  );
+  const [simulating, setSimulating] = useState<boolean>(false);
+  const [workload, setWorkload] = useState<WorkloadSummary | null>(null);
+  const [recommendation, setRecommendation] = useState<number | null>(null);
+  const [rescheduleProgress, setRescheduleProgress] =
+    useState<JobProgress | null>(null);
+  const [recomputeProgress, setRecomputeProgress] =
+    useState<JobProgress | null>(null);
+  const [decks, setDecks] = useState<DeckData[]>([]);
+  const [presets, setPresets] = useState<DeckPreset[]>([]);
+  const [reschedulePreset, setReschedulePreset] = useState<string>(""); // 为空时重新安排所有牌组
+  const [newPresetName, setNewPresetName] = useState<string>("");
+  const [newPresetDecks, setNewPresetDecks] = useState<number[]>([]);
   const [snackbar, setSnackbar] = useState<{
     open: boolean;
     message: string;
@@ -42,12 +120,14 @@ function SettingsMain() {
     const loadData = async () => {
       setLoading(true);
       try {
-        const [paramsData, retentionData] = await Promise.all([
+        const [paramsData, retentionData, excludeData] = await Promise.all([
           getFsrsParams(),
           getDesiredRetention(),
+          getExcludeFilteredReviews(),
         ]);
         setFsrsParams(paramsData);
         setRetention(retentionData);
+        setExcludeFiltered(excludeData);
       } catch (error) {
         console.error("加载设置数据失败:", error);
         setSnackbar({
@@ -61,19 +141,206 @@ function SettingsMain() {
     };
 
     loadData();
+    loadParamsHistory();
+    loadOptimization();
+    loadPresets();
+
+    // 后台自动训练完成后刷新参数和训练记录
+    const unlisten = onAutoOptimizeFinished((result) => {
+      if (result.applied) {
+        setFsrsParams(result.candidate_params);
+      }
+      loadParamsHistory();
+      loadOptimization();
+    });
+    return () => {
+      unlisten.then((unlisten) => unlisten());
+    };
   }, []);
 
-  // 训练FSRS模型
-  const handleTrainModel = async () => {
-    setTrainingLoading(true);
+  // 加载自动训练设置和最近一次训练
+  const loadOptimization = async () => {
+    try {
+      const [settings, lastRun] = await Promise.all([
+        getAutoOptimize(),
+        getLastOptimization(),
+      ]);
+      setAutoOptimizeState(settings);
+      setLastOptimization(lastRun);
+    } catch (error) {
+      console.error("加载自动训练设置失败:", error);
+    }
+  };
+
+  // 保存自动训练设置
+  const handleAutoOptimizeChange = async (settings: AutoOptimize) => {
+    try {
+      await setAutoOptimize(settings);
+      setAutoOptimizeState(settings);
+    } catch (error) {
+      setSnackbar({
+        open: true,
+        message: "保存自动训练设置失败",
+        severity: "error",
+      });
+    }
+  };
+
+  // 加载牌组和牌组预设
+  const loadPresets = async () => {
+    try {
+      const [deckData, presetData] = await Promise.all([
+        fetchDecks(),
+        getDeckPresets(),
+      ]);
+      setDecks(deckData);
+      setPresets(presetData);
+    } catch (error) {
+      console.error("加载牌组预设失败:", error);
+    }
+  };
+
+  // 保存牌组预设，失败时保留原有的预设
+  const savePresets = async (updated: DeckPreset[]) => {
     try {
-      const optimizedParams = await trainFsrsModel();
-      setFsrsParams(optimizedParams);
+      await setDeckPresets(updated);
+      setPresets(updated);
+      if (!updated.some((preset) => preset.name === reschedulePreset)) {
+        setReschedulePreset("");
+      }
+      return true;
+    } catch (error) {
       setSnackbar({
         open: true,
-        message: "FSRS模型训练成功",
+        message: `保存牌组预设失败：${error}`,
+        severity: "error",
+      });
+      return false;
+    }
+  };
+
+  // 添加牌组预设
+  const handleAddPreset = async () => {
+    const saved = await savePresets([
+      ...presets,
+      { name: newPresetName.trim(), deck_ids: newPresetDecks },
+    ]);
+    if (saved) {
+      setNewPresetName("");
+      setNewPresetDecks([]);
+    }
+  };
+
+  // 牌组ID对应的名称，牌组已删除时显示ID
+  const deckName = (deckId: number) =>
+    decks.find((deck) => deck.deckId === deckId)?.deckName ?? `#${deckId}`;
+
+  // 加载参数历史，失败时只保留原有的历史记录
+  const loadParamsHistory = async () => {
+    try {
+      setParamsHistory(await getFsrsParamsHistory());
+    } catch (error) {
+      console.error("加载参数历史失败:", error);
+    }
+  };
+
+  // 保存手动输入的参数，参数之间用逗号或空白隔开
+  const handleSaveParams = async () => {
+    if (paramsInput === null) return;
+    const params = paramsInput
+      .split(/[\s,，]+/)
+      .filter((text) => text !== "")
+      .map(Number);
+    try {
+      await saveFsrsParams(params);
+      setFsrsParams(params);
+      setParamsInput(null);
+      loadParamsHistory();
+      setSnackbar({
+        open: true,
+        message: "FSRS参数设置成功",
         severity: "success",
       });
+    } catch (error) {
+      setSnackbar({
+        open: true,
+        message: `设置FSRS参数失败：${error}`,
+        severity: "error",
+      });
+    }
+  };
+
+  // 恢复FSRS库的默认参数
+  const handleResetParams = async () => {
+    try {
+      setFsrsParams(await resetFsrsParams());
+      setParamsInput(null);
+      loadParamsHistory();
+      setSnackbar({
+        open: true,
+        message: "已恢复默认参数",
+        severity: "success",
+      });
+    } catch (error) {
+      setSnackbar({
+        open: true,
+        message: "恢复默认参数失败",
+        severity: "error",
+      });
+    }
+  };
+
+  // 恢复历史记录中的参数
+  const handleRevertParams = async (historyId: number) => {
+    try {
+      setFsrsParams(await revertFsrsParams(historyId));
+      loadParamsHistory();
+      setSnackbar({
+        open: true,
+        message: "已恢复历史参数",
+        severity: "success",
+      });
+    } catch (error) {
+      setSnackbar({
+        open: true,
+        message: "恢复历史参数失败",
+        severity: "error",
+      });
+    }
+  };
+
+  // 训练FSRS模型，候选参数更好时后端已自动保存，否则等待用户确认
+  const handleTrainModel = async () => {
+    setTrainingLoading(true);
+    setTrainingProgress({ current: 0, total: 0 });
+    setTrainingResult(null);
+    try {
+      const result = await trainFsrsModel(setTrainingProgress);
+      if (result.skipped === "not_enough_reviews") {
+        setSnackbar({
+          open: true,
+          message: `复习记录不足（${result.dataset.item_count} 条可用），未训练FSRS模型`,
+          severity: "error",
+        });
+      } else if (result.skipped === "cancelled") {
+        setSnackbar({
+          open: true,
+          message: "已取消训练FSRS模型",
+          severity: "success",
+        });
+      } else if (result.applied) {
+        setFsrsParams(result.candidate_params);
+        loadParamsHistory();
+        setSnackbar({
+          open: true,
+          message: `FSRS模型训练成功，已应用更好的参数（${datasetSummary(
+            result
+          )}）`,
+          severity: "success",
+        });
+      } else {
+        setTrainingResult(result);
+      }
     } catch (error) {
       console.error("训练FSRS模型失败:", error);
       setSnackbar({
@@ -83,6 +350,57 @@ function SettingsMain() {
       });
     } finally {
       setTrainingLoading(false);
+      setTrainingProgress(null);
+      loadOptimization();
+    }
+  };
+
+  // 切换训练时是否排除筛选学习和临时抱佛脚的复习
+  const handleExcludeFilteredChange = async (exclude: boolean) => {
+    try {
+      await setExcludeFilteredReviews(exclude);
+      setExcludeFiltered(exclude);
+    } catch (error) {
+      setSnackbar({
+        open: true,
+        message: "保存训练设置失败",
+        severity: "error",
+      });
+    }
+  };
+
+  // 取消正在进行的训练、重新安排或重新计算
+  const handleCancelTraining = async () => {
+    try {
+      await cancelFsrsTraining();
+    } catch (error) {
+      setSnackbar({
+        open: true,
+        message: "取消失败",
+        severity: "error",
+      });
+    }
+  };
+
+  // 确认应用没有自动应用的候选参数
+  const handleApplyCandidate = async () => {
+    if (!trainingResult) return;
+    try {
+      setFsrsParams(await applyTrainedFsrsParams());
+      setTrainingResult(null);
+      loadParamsHistory();
+      loadOptimization();
+      setSnackbar({
+        open: true,
+        message: "已应用训练得到的参数",
+        severity: "success",
+      });
+    } catch (error) {
+      setSnackbar({
+        open: true,
+        message: "应用参数失败",
+        severity: "error",
+      });
     }
   };
 
@@ -92,6 +410,7 @@ function SettingsMain() {
     newValue: number | number[]
   ) => {
     setRetention(newValue as number);
+    setWorkload(null);
   };
 
   // 提交记忆留存率变更
@@ -116,6 +435,109 @@ function SettingsMain() {
     }
   };
 
+  // 模拟当前留存率下未来一年的工作量
+  const handleSimulate = async () => {
+    setSimulating(true);
+    try {
+      const simulation = await simulateRetentionWorkload(retention);
+      setWorkload(simulation.summary);
+    } catch (error) {
+      setSnackbar({
+        open: true,
+        message: "模拟工作量失败",
+        severity: "error",
+      });
+    } finally {
+      setSimulating(false);
+    }
+  };
+
+  // 扫描留存率，推荐每记住一张卡片用时最少的留存率
+  const handleRecommend = async () => {
+    setSimulating(true);
+    try {
+      const result = await recommendDesiredRetention();
+      setRecommendation(result.recommended);
+      if (result.recommended === null) {
+        setSnackbar({
+          open: true,
+          message: "没有足够的卡片用于推荐留存率",
+          severity: "error",
+        });
+      }
+    } catch (error) {
+      setSnackbar({
+        open: true,
+        message: "推荐记忆留存率失败",
+        severity: "error",
+      });
+    } finally {
+      setSimulating(false);
+    }
+  };
+
+  // 应用推荐的留存率
+  const handleApplyRecommendation = async () => {
+    if (recommendation === null) {
+      return;
+    }
+    setRetention(recommendation);
+    setWorkload(null);
+    await handleRetentionChangeCommitted(new Event("change"), recommendation);
+  };
+
+  // 使用当前的参数从复习记录重建所有卡片的记忆状态
+  const handleRecompute = async () => {
+    setRecomputeProgress({ current: 0, total: 0 });
+    try {
+      const summary = await recomputeMemoryStates([], setRecomputeProgress);
+      setSnackbar({
+        open: true,
+        message: summary.cancelled
+          ? "已取消重新计算记忆状态"
+          : `已从复习记录重建 ${summary.from_history} 张卡片，从旧间隔估算 ${summary.from_legacy} 张卡片` +
+            reviewedDuringJobSummary(summary.reviewed_during_job),
+        severity: "success",
+      });
+    } catch (error) {
+      setSnackbar({
+        open: true,
+        message: "重新计算记忆状态失败",
+        severity: "error",
+      });
+    } finally {
+      setRecomputeProgress(null);
+    }
+  };
+
+  // 使用当前的参数和留存率重新安排所有卡片或所选预设中卡片的复习时间
+  const handleReschedule = async () => {
+    setRescheduleProgress({ current: 0, total: 0 });
+    try {
+      const summary = await rescheduleCards(
+        [],
+        setRescheduleProgress,
+        reschedulePreset || undefined
+      );
+      setSnackbar({
+        open: true,
+        message: summary.cancelled
+          ? "已取消重新安排复习时间"
+          : `已重新安排 ${summary.rescheduled} 张卡片，${summary.earlier} 张提前，${summary.later} 张推后` +
+            reviewedDuringJobSummary(summary.reviewed_during_job),
+        severity: "success",
+      });
+    } catch (error) {
+      setSnackbar({
+        open: true,
+        message: `重新安排复习时间失败：${error}`,
+        severity: "error",
+      });
+    } finally {
+      setRescheduleProgress(null);
+    }
+  };
+
   // 关闭提示信息
   const handleCloseSnackbar = () => {
     setSnackbar({ ...snackbar, open: false });
@@ -150,13 +572,45 @@ function SettingsMain() {
                 <Typography variant="body1" sx={{ mb: 1 }}>
                   当前参数值：
                 </Typography>
-                <Typography variant="h6">
-                  {fsrsParams.map((param, index) => (
-                    <span key={index}>
-                      {param.toFixed(6)}{index < fsrsParams.length - 1 ? ", " : ""}
-                    </span>
-                  ))}
-                </Typography>
+                {paramsInput === null ? (
+                  <Typography variant="h6">
+                    {fsrsParams.map((param, index) => (
+                      <span key={index}>
+                        {param.toFixed(6)}{index < fsrsParams.length - 1 ? ", " : ""}
+                      </span>
+                    ))}
+                  </Typography>
+                ) : (
+                  <TextField
+                    value={paramsInput}
+                    onChange={(event) => setParamsInput(event.target.value)}
+                    multiline
+                    fullWidth
+                    helperText="填入 17 个（FSRS-4.5）、19 个（FSRS-5）或 21 个（FSRS-6）参数"
+                  />
+                )}
+                <Box display="flex" justifyContent="flex-end" gap={1} mt={1}>
+                  {paramsInput === null ? (
+                    <Button
+                      size="small"
+                      onClick={() => setParamsInput(fsrsParams.join(", "))}
+                    >
+                      编辑参数
+                    </Button>
+                  ) : (
+                    <>
+                      <Button size="small" onClick={() => setParamsInput(null)}>
+                        取消
+                      </Button>
+                      <Button size="small" variant="contained" onClick={handleSaveParams}>
+                        保存参数
+                      </Button>
+                    </>
+                  )}
+                  <Button size="small" color="secondary" onClick={handleResetParams}>
+                    恢复默认参数
+                  </Button>
+                </Box>
               </Paper>
               <Typography
                 variant="body2"
@@ -168,6 +622,47 @@ function SettingsMain() {
             </Box>
           )}
 
+          <Box display="flex" justifyContent="center" sx={{ mb: 1 }}>
+            <FormControlLabel
+              control={
+                <Switch
+                  checked={excludeFiltered}
+                  onChange={(event) =>
+                    handleExcludeFilteredChange(event.target.checked)
+                  }
+                />
+              }
+              label="训练时排除筛选学习和临时抱佛脚的复习"
+            />
+            {autoOptimize && (
+              <FormControlLabel
+                control={
+                  <Switch
+                    checked={autoOptimize.enabled}
+                    onChange={(event) =>
+                      handleAutoOptimizeChange({
+                        ...autoOptimize,
+                        enabled: event.target.checked,
+                      })
+                    }
+                  />
+                }
+                label={`每新增 ${autoOptimize.review_interval} 条复习或每 ${autoOptimize.day_interval} 天自动训练`}
+              />
+            )}
+          </Box>
+          {lastOptimization && (
+            <Typography
+              variant="body2"
+              color="text.secondary"
+              sx={{ mb: 1, textAlign: "center" }}
+            >
+              上次{lastOptimization.automatic ? "自动" : ""}训练于{" "}
+              {new Date(lastOptimization.trained_at).toLocaleString()}（
+              {lastOptimization.review_count} 条复习记录），
+              {lastOptimization.applied ? "已应用新的参数" : "参数没有改进，未应用"}
+            </Typography>
+          )}
           <Box display="flex" justifyContent="center">
             <Button
               variant="contained"
@@ -182,7 +677,119 @@ function SettingsMain() {
             >
               {trainingLoading ? "训练中..." : "训练模型"}
             </Button>
+            {trainingLoading && (
+              <Button onClick={handleCancelTraining} sx={{ ml: 2 }}>
+                取消训练
+              </Button>
+            )}
+            <Button
+              variant="outlined"
+              onClick={handleRecompute}
+              disabled={recomputeProgress !== null || loading}
+              sx={{ ml: 2 }}
+            >
+              从复习记录重建记忆状态
+            </Button>
+            {recomputeProgress !== null && (
+              <Button onClick={handleCancelTraining} sx={{ ml: 2 }}>
+                取消重建
+              </Button>
+            )}
           </Box>
+          {trainingProgress !== null && (
+            <LinearProgress
+              variant={
+                trainingProgress.total > 0 ? "determinate" : "indeterminate"
+              }
+              value={
+                trainingProgress.total > 0
+                  ? (trainingProgress.current / trainingProgress.total) * 100
+                  : 0
+              }
+              sx={{ mt: 2 }}
+            />
+          )}
+          {trainingResult?.current && trainingResult.candidate && (
+            <Paper elevation={1} sx={{ p: 2, mt: 2, borderRadius: 2 }}>
+              <Typography variant="body2" gutterBottom>
+                训练得到的参数没有比当前参数更好，未自动应用。
+                训练使用了{datasetSummary(trainingResult)}。
+              </Typography>
+              <Typography variant="body2">
+                当前参数：对数损失 {trainingResult.current.log_loss.toFixed(4)}，
+                RMSE {(trainingResult.current.rmse_bins * 100).toFixed(2)}%
+              </Typography>
+              <Typography variant="body2">
+                候选参数：对数损失 {trainingResult.candidate.log_loss.toFixed(4)}，
+                RMSE {(trainingResult.candidate.rmse_bins * 100).toFixed(2)}%
+              </Typography>
+              <Box display="flex" justifyContent="flex-end" gap={1} mt={1}>
+                <Button size="small" onClick={() => setTrainingResult(null)}>
+                  保留当前参数
+                </Button>
+                <Button
+                  size="small"
+                  variant="contained"
+                  onClick={handleApplyCandidate}
+                >
+                  仍然应用
+                </Button>
+              </Box>
+            </Paper>
+          )}
+          {paramsHistory.length > 0 && (
+            <Box sx={{ mt: 3 }}>
+              <Typography variant="subtitle1" gutterBottom>
+                参数历史
+              </Typography>
+              {paramsHistory.map((entry, index) => (
+                <Box
+                  key={entry.history_id}
+                  display="flex"
+                  alignItems="center"
+                  justifyContent="space-between"
+                  sx={{ py: 0.5 }}
+                >
+                  <Typography variant="body2">
+                    {new Date(entry.created_at).toLocaleString()}，
+                    {PARAMS_SOURCE_LABELS[entry.source]}
+                    {entry.reverted_from !== null && "（恢复）"}
+                    {entry.review_count !== null &&
+                      `，${entry.review_count} 条复习记录`}
+                    {entry.metrics &&
+                      `，对数损失 ${entry.metrics.log_loss.toFixed(4)}，RMSE ${(
+                        entry.metrics.rmse_bins * 100
+                      ).toFixed(2)}%`}
+                  </Typography>
+                  {index === 0 ? (
+                    <Typography variant="body2" color="text.secondary">
+                      当前
+                    </Typography>
+                  ) : (
+                    <Button
+                      size="small"
+                      onClick={() => handleRevertParams(entry.history_id)}
+                    >
+                      恢复
+                    </Button>
+                  )}
+                </Box>
+              ))}
+            </Box>
+          )}
+          {recomputeProgress !== null && (
+            <LinearProgress
+              variant={
+                recomputeProgress.total > 0 ? "determinate" : "indeterminate"
+              }
+              value={
+                recomputeProgress.total > 0
+                  ? (recomputeProgress.current / recomputeProgress.total) * 100
+                  : 0
+              }
+              sx={{ mt: 2 }}
+            />
+          )}
         </CardContent>
       </Card>
 
@@ -212,7 +819,7 @@ function SettingsMain() {
                 onChange={handleRetentionChange}
                 onChangeCommitted={handleRetentionChangeCommitted}
                 min={0.6}
-                max={1.0}
+                max={0.99}
                 step={0.01}
                 valueLabelDisplay="auto"
                 valueLabelFormat={(value) => `${(value * 100).toFixed(0)}%`}
@@ -226,11 +833,195 @@ function SettingsMain() {
                   100%
                 </Typography>
               </Box>
+
+              <Box display="flex" justifyContent="center" gap={2} mt={2}>
+                <Button
+                  variant="outlined"
+                  onClick={handleSimulate}
+                  disabled={simulating}
+                >
+                  模拟未来一年的工作量
+                </Button>
+                <Button
+                  variant="outlined"
+                  onClick={handleRecommend}
+                  disabled={simulating}
+                  startIcon={
+                    simulating && <CircularProgress size={20} color="inherit" />
+                  }
+                >
+                  推荐留存率
+                </Button>
+              </Box>
+
+              {workload && (
+                <Paper elevation={1} sx={{ p: 2, mt: 2, borderRadius: 2 }}>
+                  <Typography variant="body2">
+                    留存率 {(workload.desired_retention * 100).toFixed(0)}%
+                    时，平均每天复习 {workload.average_reviews_per_day.toFixed(1)}{" "}
+                    次，一年共需约 {(workload.total_cost_seconds / 3600).toFixed(1)}{" "}
+                    小时，一年后预计记住 {workload.memorized.toFixed(0)} 张卡片
+                    {workload.cost_per_memorized !== null &&
+                      `，每记住一张卡片需约 ${workload.cost_per_memorized.toFixed(
+                        1
+                      )} 秒`}
+                  </Typography>
+                </Paper>
+              )}
+
+              <Box
+                display="flex"
+                justifyContent="center"
+                alignItems="center"
+                mt={2}
+              >
+                <FormControl size="small" sx={{ minWidth: 160, mr: 2 }}>
+                  <InputLabel id="reschedule-preset-label">范围</InputLabel>
+                  <Select
+                    labelId="reschedule-preset-label"
+                    value={reschedulePreset}
+                    onChange={(event) => setReschedulePreset(event.target.value)}
+                    label="范围"
+                    disabled={rescheduleProgress !== null}
+                  >
+                    <MenuItem value="">所有牌组</MenuItem>
+                    {presets.map((preset) => (
+                      <MenuItem key={preset.name} value={preset.name}>
+                        预设：{preset.name}
+                      </MenuItem>
+                    ))}
+                  </Select>
+                </FormControl>
+                <Button
+                  variant="outlined"
+                  color="secondary"
+                  onClick={handleReschedule}
+                  disabled={rescheduleProgress !== null}
+                >
+                  按当前设置重新安排复习时间
+                </Button>
+                {rescheduleProgress !== null && (
+                  <Button onClick={handleCancelTraining} sx={{ ml: 2 }}>
+                    取消
+                  </Button>
+                )}
+              </Box>
+              {rescheduleProgress !== null && (
+                <LinearProgress
+                  variant={
+                    rescheduleProgress.total > 0 ? "determinate" : "indeterminate"
+                  }
+                  value={
+                    rescheduleProgress.total > 0
+                      ? (rescheduleProgress.current / rescheduleProgress.total) *
+                        100
+                      : 0
+                  }
+                  sx={{ mt: 2 }}
+                />
+              )}
+
+              {recommendation !== null && (
+                <Box
+                  display="flex"
+                  justifyContent="center"
+                  alignItems="center"
+                  gap={2}
+                  mt={2}
+                >
+                  <Typography variant="body2">
+                    推荐留存率：{(recommendation * 100).toFixed(0)}%
+                  </Typography>
+                  <Button
+                    size="small"
+                    variant="contained"
+                    onClick={handleApplyRecommendation}
+                    disabled={recommendation === retention}
+                  >
+                    应用
+                  </Button>
+                </Box>
+              )}
             </Box>
           )}
         </CardContent>
       </Card>
 
+      {/* 牌组预设卡片 */}
+      <Card sx={{ mt: 4 }}>
+        <CardHeader title="牌组预设" />
+        <Divider />
+        <CardContent>
+          <Typography variant="body2" color="text.secondary" sx={{ mb: 2 }}>
+            预设是命名的一组牌组，重新安排复习时间时可以只处理预设中的牌组
+          </Typography>
+          {presets.map((preset) => (
+            <Box
+              key={preset.name}
+              display="flex"
+              alignItems="center"
+              flexWrap="wrap"
+              gap={1}
+              mb={1}
+            >
+              <Typography variant="subtitle2" sx={{ mr: 1 }}>
+                {preset.name}
+              </Typography>
+              {preset.deck_ids.map((deckId) => (
+                <Chip key={deckId} size="small" label={deckName(deckId)} />
+              ))}
+              <Button
+                size="small"
+                color="error"
+                onClick={() =>
+                  savePresets(
+                    presets.filter((other) => other.name !== preset.name)
+                  )
+                }
+              >
+                删除
+              </Button>
+            </Box>
+          ))}
+          <Box display="flex" alignItems="center" gap={2} mt={2}>
+            <TextField
+              size="small"
+              label="预设名称"
+              value={newPresetName}
+              onChange={(event) => setNewPresetName(event.target.value)}
+            />
+            <FormControl size="small" sx={{ minWidth: 200 }}>
+              <InputLabel id="preset-decks-label">牌组</InputLabel>
+              <Select
+                labelId="preset-decks-label"
+                multiple
+                value={newPresetDecks}
+                onChange={(event) =>
+                  setNewPresetDecks(event.target.value as number[])
+                }
+                label="牌组"
+                renderValue={(selected) => selected.map(deckName).join("，")}
+              >
+                {decks.map((deck) => (
+                  <MenuItem key={deck.deckId} value={deck.deckId}>
+                    {deck.deckName}
+                  </MenuItem>
+                ))}
+              </Select>
+            </FormControl>
+            <Button
+              variant="outlined"
+              onClick={handleAddPreset}
+              disabled={
+                newPresetName.trim() === "" || newPresetDecks.length === 0
+              }
+            >
+              添加预设
+            </Button>
+          </Box>
+        </CardContent>
+      </Card>
+
       {/* 提示信息 */}
       <Snackbar
         open={snackbar.open}
@@ -1,4 +1,5 @@
 import { invoke } from "@tauri-apps/api/core";
+import { TemplateConversionData } from "@/api/Template";
 
 export interface CardData {
   card_id: number;
@@ -9,6 +10,115 @@ export interface CardData {
   memory_state: [number, number] | null;
   scheduled_days: number;
   last_review: string | null;
+  retrievability: number | null; // 当前可提取性，新卡片为 null
+  days_overdue: number | null; // 逾期天数，负数表示尚未到期，新卡片为 null
+}
+
+/**
+ * 卡片排序方式：按到期时间、可提取性从低到高（最容易遗忘的优先）、逾期天数从多到少
+ */
+export type CardSortOrder = "due" | "retrievability_ascending" | "days_overdue";
+
+/**
+ * 卡片排序方式的显示名称
+ */
+export const cardSortOrderLabels: Record<CardSortOrder, string> = {
+  due: "到期时间",
+  retrievability_ascending: "最容易遗忘优先",
+  days_overdue: "逾期最久优先",
+};
+
+/**
+ * 卡片浏览器的排序列
+ */
+export type CardBrowserSort =
+  | "due"
+  | "created"
+  | "modified"
+  | "deck"
+  | "template"
+  | "stability"
+  | "difficulty"
+  | "retrievability"
+  | "lapses";
+
+/**
+ * 卡片浏览器排序列的显示名称
+ */
+export const cardBrowserSortLabels: Record<CardBrowserSort, string> = {
+  due: "到期时间",
+  created: "添加时间",
+  modified: "修改时间",
+  deck: "牌组",
+  template: "模板",
+  stability: "稳定性",
+  difficulty: "难度",
+  retrievability: "可提取性",
+  lapses: "遗忘次数",
+};
+
+/**
+ * 卡片浏览器的查询条件，筛选列表为空时表示不筛选
+ */
+export interface CardBrowserQuery {
+  template_ids: number[];
+  deck_ids: number[];
+  status_bit_filter: number; // 与 filterCards 相同的状态位，0 表示所有状态
+  sort: CardBrowserSort;
+  descending: boolean;
+  page: number; // 从 0 开始
+  page_size: number;
+  after?: CardBrowserCursor | null; // 设置时从游标卡片之后开始取卡片，忽略 page
+}
+
+/**
+ * 卡片浏览器的翻页游标，由上一页的结果返回
+ */
+export interface CardBrowserCursor {
+  sort_key: number | string | null; // 上一页最后一张卡片的排序值
+  card_id: number;
+  queried_at: string; // 第一页的查询时间，之后的页按同一时间排序
+}
+
+/**
+ * 卡片浏览器列表中的一行，只包含列表显示需要的内容
+ */
+export interface CardBrowserRow {
+  card_id: number;
+  deck_id: number;
+  deck_name: string;
+  template_id: number;
+  template_name: string;
+  sort_field: string; // 卡片的第一个字段
+  due: string;
+  modified_at: string;
+  stability: number | null;
+  difficulty: number | null;
+  retrievability: number | null; // 新卡片为 null
+  days_overdue: number | null; // 新卡片为 null
+  lapses: number; // 第一次复习之后评分为忘记的次数
+}
+
+/**
+ * 卡片浏览器的一页结果
+ */
+export interface CardBrowserPage {
+  rows: CardBrowserRow[];
+  total: number; // 符合筛选条件的卡片总数
+  page: number;
+  page_size: number;
+  next_cursor: CardBrowserCursor | null; // 获取下一页的游标，最后一页为 null
+}
+
+/**
+ * 渲染后的卡片接口，front 和 back 为完整的 HTML，css 为模板样式
+ */
+export interface RenderedCardData {
+  card_id: number;
+  template_id: number;
+  front: string;
+  back: string;
+  css: string;
 }
 
 export interface DeckData {
@@ -44,58 +154,176 @@ export async function fetchLearningCount(
 }
 
 /**
- * 从后端加载卡片
- * @param deckId - 牌组ID，指定从哪个牌组加载卡片
- * @param pageSize - 一次加载的卡片数量，默认为10
- * @param setCardCache - React状态更新函数，用于更新卡片缓存
- * @param setCurrentCardIndex - React状态更新函数，用于重置当前卡片索引
- * @param setIsLoading - React状态更新函数，用于更新加载状态
- * @returns Promise<boolean> - 返回是否成功加载到卡片，true表示成功加载且有卡片，false表示加载失败或无卡片
- * @description 调用后端的get_next_card命令获取指定牌组的下一批待学习卡片
+ * 学习会话中剩余卡片的数量，包括当前展示的卡片
  */
-export async function loadCardsFromBackend(
-  deckId: number,
-  pageSize: number = 10,
-  setCardCache: React.Dispatch<React.SetStateAction<CardData[]>>,
-  setCurrentCardIndex: React.Dispatch<React.SetStateAction<number>>,
-  setIsLoading: React.Dispatch<React.SetStateAction<boolean>>
-) {
+export interface StudyCounts {
+  new_count: number;
+  learning_count: number; // 本次会话中答过且当天需要再次学习的卡片
+  review_count: number;
+}
+
+/**
+ * 学习会话中单个牌组的进度
+ */
+export interface DeckStudyProgress {
+  deck_id: number;
+  counts: StudyCounts; // 该牌组的剩余卡片数量
+  answered: number;
+}
+
+/**
+ * 学习模式：普通学习今天到期的卡片，筛选学习提前学习尚未到期的卡片，临时抱佛脚学习所有学过的卡片
+ */
+export type StudyMode = "normal" | "filtered" | "cram";
+
+/**
+ * 学习会话的进度
+ */
+export interface StudyProgress {
+  session_id: number;
+  mode: StudyMode;
+  deck_ids: number[]; // 会话学习的牌组
+  decks: DeckStudyProgress[]; // 各牌组的进度，顺序与deck_ids一致
+  counts: StudyCounts;
+  answered: number; // 回答次数，当天再次学习的卡片重复计数
+  correct: number; // 评分为困难及以上的回答次数
+  elapsed_ms: number;
+}
+
+/**
+ * 学习会话中当前要学习的卡片
+ */
+export interface StudyCardData {
+  card: CardData;
+  intervals: NextIntervals;
+  progress: StudyProgress;
+}
+
+/**
+ * 开始学习牌组
+ * @param deckIds - 要一起学习的牌组ID列表，为空时学习所有牌组
+ * @param mode - 学习模式，默认为普通学习
+ * @returns Promise<StudyProgress | null> - 返回新学习会话的初始进度，失败时返回null
+ * @description 调用后端的start_study_session命令创建学习会话。普通学习时每个牌组的卡片按该牌组的学习队列选项排序，再在牌组之间轮流学习；
+ * 筛选学习和临时抱佛脚按可提取性从低到高学习，复习记录会标记学习模式
+ */
+export async function startStudySession(
+  deckIds: number[] = [],
+  mode: StudyMode = "normal"
+): Promise<StudyProgress | null> {
   try {
-    setIsLoading(true);
-    const cards = await invoke<CardData[]>("get_next_card", {
-      deckId,
-      pageSize,
+    return await invoke<StudyProgress>("start_study_session", {
+      deckIds,
+      mode,
     });
-    setCardCache(cards);
-    setCurrentCardIndex(0);
-    setIsLoading(false);
-    return cards.length > 0;
   } catch (error) {
-    console.error("加载卡片失败:", error);
-    setIsLoading(false);
+    console.error("开始学习失败:", error);
+    return null;
+  }
+}
+
+/**
+ * 获取可以继续的学习会话
+ * @returns Promise<StudyProgress | null> - 返回最近保存且未打开的学习会话的进度，没有时返回null
+ * @description 调用后端的resumable_study_session命令，用于在应用重启后提示继续上次未完成的学习
+ */
+export async function getResumableStudySession(): Promise<StudyProgress | null> {
+  try {
+    return await invoke<StudyProgress | null>("resumable_study_session");
+  } catch (error) {
+    console.error("获取未完成的学习失败:", error);
+    return null;
+  }
+}
+
+/**
+ * 继续保存的学习会话
+ * @param sessionId - 学习会话ID
+ * @returns Promise<StudyProgress | null> - 返回会话的进度，失败时返回null
+ * @description 调用后端的resume_study_session命令从数据库恢复学习会话，剩余卡片使用最新的卡片状态
+ */
+export async function resumeStudySession(
+  sessionId: number
+): Promise<StudyProgress | null> {
+  try {
+    return await invoke<StudyProgress>("resume_study_session", { sessionId });
+  } catch (error) {
+    console.error("继续学习失败:", error);
+    return null;
+  }
+}
+
+/**
+ * 暂停学习会话
+ * @param sessionId - 学习会话ID
+ * @returns Promise<boolean> - 返回是否成功暂停
+ * @description 调用后端的suspend_study_session命令保存并关闭学习会话，之后可以继续。
+ * 保存失败时会话保持打开
+ */
+export async function suspendStudySession(sessionId: number): Promise<boolean> {
+  try {
+    await invoke("suspend_study_session", { sessionId });
+    return true;
+  } catch (error) {
+    console.error("暂停学习失败:", error);
     return false;
   }
 }
 
 /**
- * 加载卡片的下一个间隔状态
- * @param card - 当前卡片数据
- * @param setNextIntervals - React状态更新函数，用于更新下一个间隔状态
- * @returns Promise<NextIntervals | null> - 返回下一个间隔状态，加载失败时返回null
- * @description 调用后端的load_next_state命令计算卡片在不同评分下的下一个复习间隔，包括again、hard、good和easy四种评分对应的天数
+ * 获取学习会话中当前要学习的卡片
+ * @param sessionId - 学习会话ID
+ * @returns Promise<StudyCardData | null> - 返回卡片、各评分的间隔天数和会话进度，没有剩余卡片时返回null
+ * @description 调用后端的next_study_card命令，未回答前重复调用返回同一张卡片；会话不存在时抛出错误，以便与学习完成区分
  */
-export async function loadNextState(
-  card: CardData,
-  setNextIntervals: React.Dispatch<React.SetStateAction<NextIntervals | null>>
-) {
+export async function nextStudyCard(
+  sessionId: number
+): Promise<StudyCardData | null> {
+  return await invoke<StudyCardData | null>("next_study_card", { sessionId });
+}
+
+/**
+ * 回答学习会话中的卡片
+ * @param sessionId - 学习会话ID
+ * @param cardId - 回答的卡片ID，必须是会话当前展示的卡片
+ * @param rating - 评分，1-4分别对应again、hard、good和easy
+ * @param durationMs - 可选，回答卡片所用的毫秒数，用于统计回答用时
+ * @returns Promise<StudyProgress | null> - 返回回答后的会话进度，失败时返回null
+ * @description 调用后端的answer_study_card命令保存评分，当天需要再次学习的卡片由后端重新加入队列。
+ * 评分已保存但学习会话保存失败时也返回null，此时会话中的卡片已经回答
+ */
+export async function answerStudyCard(
+  sessionId: number,
+  cardId: number,
+  rating: number,
+  durationMs?: number
+): Promise<StudyProgress | null> {
   try {
-    const intervals = await invoke<NextIntervals>("load_next_state", {
-      card,
+    return await invoke<StudyProgress>("answer_study_card", {
+      sessionId,
+      cardId,
+      rating,
+      durationMs: durationMs === undefined ? null : Math.round(durationMs),
     });
-    setNextIntervals(intervals);
-    return intervals;
   } catch (error) {
-    console.error("加载下一状态失败:", error);
+    console.error("提交评分失败:", error);
+    return null;
+  }
+}
+
+/**
+ * 结束学习会话
+ * @param sessionId - 学习会话ID
+ * @returns Promise<StudyProgress | null> - 返回会话结束时的进度，会话不存在时返回null
+ * @description 调用后端的end_study_session命令结束会话并删除保存的会话，也用于放弃未完成的学习
+ */
+export async function endStudySession(
+  sessionId: number
+): Promise<StudyProgress | null> {
+  try {
+    return await invoke<StudyProgress>("end_study_session", { sessionId });
+  } catch (error) {
+    console.error("结束学习失败:", error);
     return null;
   }
 }
@@ -121,40 +349,27 @@ export async function addNewCard(cardData: {
   }
 }
 
-/**
- * 提交卡片评分
- * @param rating - 评分值，表示用户对卡片的评价（通常为0-3的整数，对应again、hard、good、easy）
- * @returns Promise<boolean> - 返回是否成功提交评分，true表示成功，false表示失败
- * @description 调用后端的emit_card_review命令提交用户对当前卡片的评分，用于更新卡片的记忆状态和下次复习时间
- */
-export async function submitCardRating(rating: number) {
-  try {
-    await invoke("emit_card_review", { rating });
-    return true;
-  } catch (error) {
-    console.error("提交评分失败:", error);
-    return false;
-  }
-}
-
 /**
  * 根据筛选条件获取卡片列表
  * @param templateIds - 模板ID列表，用于筛选特定模板的卡片
  * @param deckIds - 牌组ID列表，用于筛选特定牌组的卡片
  * @param statusBitFilter - 状态位过滤器，用于筛选特定学习状态的卡片
+ * @param sortOrder - 卡片排序方式，默认按到期时间
  * @returns Promise<CardData[] | null> - 返回筛选后的卡片列表，筛选失败时返回null
  * @description 调用后端cardedit.rs中的card_filter命令根据模板ID、牌组ID和状态位过滤器筛选卡片
  */
 export async function filterCards(
   templateIds: number[] = [],
   deckIds: number[] = [],
-  statusBitFilter: number = 0
+  statusBitFilter: number = 0,
+  sortOrder: CardSortOrder = "due"
 ): Promise<CardData[] | null> {
   try {
     const cards = await invoke<CardData[]>("card_filter", {
       templateIds,
       deckIds,
       statusBitFilter,
+      sortOrder,
     });
     return cards;
   } catch (error) {
@@ -163,6 +378,39 @@ export async function filterCards(
   }
 }
 
+/**
+ * 分页浏览卡片
+ * @param query - 筛选条件、排序方式和分页参数
+ * @returns Promise<CardBrowserPage | null> - 返回当前页的卡片列表和符合条件的卡片总数，失败时返回null
+ * @description 调用后端cardedit.rs中的card_browser_page命令，排序和分页在数据库中完成。
+ * 逐页向后翻页时传入上一页返回的next_cursor，不需要跳过前面的卡片
+ */
+export async function browseCards(
+  query: CardBrowserQuery
+): Promise<CardBrowserPage | null> {
+  try {
+    return await invoke<CardBrowserPage>("card_browser_page", { query });
+  } catch (error) {
+    console.error("浏览卡片失败:", error);
+    return null;
+  }
+}
+
+/**
+ * 获取单张卡片的完整内容
+ * @param cardId - 卡片ID
+ * @returns Promise<CardData | null> - 返回卡片的全部字段和当前可提取性，失败时返回null
+ * @description 调用后端cardedit.rs中的card_detail命令，用于在卡片浏览器中预览和编辑选中的卡片
+ */
+export async function getCardDetail(cardId: number): Promise<CardData | null> {
+  try {
+    return await invoke<CardData>("card_detail", { cardId });
+  } catch (error) {
+    console.error("获取卡片失败:", error);
+    return null;
+  }
+}
+
 /**
  * 更新卡片字段内容
  * @param cardId - 卡片ID，指定要更新的卡片
@@ -203,3 +451,46 @@ export async function deleteCard(cardId: number): Promise<boolean> {
     return false;
   }
 }
+
+/**
+ * 更换卡片模板
+ * @param cardIds - 卡片ID列表，所选卡片必须使用相同的模板
+ * @param conversion - 模板转换信息，包含目标模板ID和源模板字段到目标模板字段的映射
+ * @returns Promise<number | null> - 返回被转换的卡片数量，转换失败时返回null
+ * @description 调用后端cardedit.rs中的change_card_template命令转换卡片模板，卡片的记忆状态和复习记录保持不变
+ */
+export async function changeCardTemplate(
+  cardIds: number[],
+  conversion: TemplateConversionData
+): Promise<number | null> {
+  try {
+    const convertedCount = await invoke<number>("change_card_template", {
+      cardIds,
+      conversion,
+    });
+    return convertedCount;
+  } catch (error) {
+    console.error("更换卡片模板失败:", error);
+    return null;
+  }
+}
+
+/**
+ * 渲染卡片
+ * @param cardId - 卡片ID，指定要渲染的卡片
+ * @returns Promise<RenderedCardData | null> - 返回渲染后的正面、背面HTML和模板CSS，渲染失败时返回null
+ * @description 调用后端cardmemo.rs中的render_card命令，按卡片所属模板的格式生成HTML
+ */
+export async function renderCard(
+  cardId: number
+): Promise<RenderedCardData | null> {
+  try {
+    const rendered = await invoke<RenderedCardData>("render_card", {
+      cardId,
+    });
+    return rendered;
+  } catch (error) {
+    console.error("渲染卡片失败:", error);
+    return null;
+  }
+}
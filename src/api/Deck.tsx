@@ -42,6 +42,24 @@ export async function fetchDecks() {
   return [];
 }
 
+/**
+ * 学习会话包含的牌组名称
+ * @param deckIds - 会话学习的牌组ID列表
+ * @param decks - 所有牌组
+ * @returns string - 单个牌组时为牌组名称，包含所有牌组时为"全部牌组"，否则为以顿号分隔的牌组名称
+ */
+export function studyDecksName(deckIds: number[], decks: DeckData[]) {
+  if (deckIds.length > 1 && deckIds.length === decks.length) {
+    return "全部牌组";
+  }
+  return deckIds
+    .map(
+      (deckId) =>
+        decks.find((deck) => deck.deckId === deckId)?.deckName ?? "未知牌组"
+    )
+    .join("、");
+}
+
 /**
  * 删除卡组
  * @param deckId - 要删除的卡组ID
@@ -67,3 +85,85 @@ export async function createDeck(deckName: string) {
     return false;
   }
 }
+
+/**
+ * 新卡片顺序：添加顺序、随机、按模板分组
+ */
+export type NewCardOrder = "added" | "random" | "template";
+
+/**
+ * 复习顺序：到期时间、可提取性从低到高、随机、按牌组分组
+ */
+export type ReviewOrder = "due" | "retrievability_ascending" | "random" | "deck";
+
+/**
+ * 新卡片与复习卡片的混合方式：新卡片在前、新卡片在后、穿插
+ */
+export type NewReviewMix = "before" | "after" | "interleaved";
+
+/**
+ * 牌组的学习队列选项
+ */
+export interface QueueOptions {
+  new_card_order: NewCardOrder;
+  review_order: ReviewOrder;
+  new_review_mix: NewReviewMix;
+  new_cards_per_day: number | null; // 每天新卡片上限，为null时不限制
+  max_reviews_per_day: number | null; // 每天复习上限，为null时不限制
+}
+
+export const newCardOrderLabels: Record<NewCardOrder, string> = {
+  added: "添加顺序",
+  random: "随机",
+  template: "按模板",
+};
+
+export const reviewOrderLabels: Record<ReviewOrder, string> = {
+  due: "到期时间",
+  retrievability_ascending: "最容易遗忘优先",
+  random: "随机",
+  deck: "按牌组集中学习",
+};
+
+export const newReviewMixLabels: Record<NewReviewMix, string> = {
+  before: "新卡片在前",
+  after: "新卡片在后",
+  interleaved: "穿插",
+};
+
+/**
+ * 获取牌组的学习队列选项
+ * @param deckId - 牌组ID
+ * @returns Promise<QueueOptions | null> - 返回队列选项，获取失败时返回null
+ * @description 调用后端的deck_queue_options命令获取牌组的新卡片顺序、复习顺序、混合方式和每天的学习上限
+ */
+export async function getDeckQueueOptions(
+  deckId: number
+): Promise<QueueOptions | null> {
+  try {
+    return await invoke<QueueOptions>("deck_queue_options", { deckId });
+  } catch (error) {
+    console.error("获取学习队列选项失败:", error);
+    return null;
+  }
+}
+
+/**
+ * 设置牌组的学习队列选项
+ * @param deckId - 牌组ID
+ * @param options - 新的队列选项
+ * @returns Promise<boolean> - 返回是否设置成功
+ * @description 调用后端的set_deck_queue_options命令保存牌组的队列选项，之后获取的卡片按新选项排序
+ */
+export async function setDeckQueueOptions(
+  deckId: number,
+  options: QueueOptions
+): Promise<boolean> {
+  try {
+    await invoke("set_deck_queue_options", { deckId, options });
+    return true;
+  } catch (error) {
+    console.error("设置学习队列选项失败:", error);
+    return false;
+  }
+}
@@ -0,0 +1,87 @@
+import { invoke, convertFileSrc } from "@tauri-apps/api/core";
+
+export interface MissingMediaData {
+  file_name: string;
+  card_ids: number[];
+}
+
+export interface MediaCheckReportData {
+  missing: MissingMediaData[];
+  unused: string[];
+  deleted: number;
+}
+
+/**
+ * 生成字段中引用媒体文件的文本
+ * @param fileName - 媒体文件名
+ * @returns string - 形如 [media:文件名] 的引用，可以插入任意字段中
+ */
+export function mediaReference(fileName: string): string {
+  return `[media:${fileName}]`;
+}
+
+/**
+ * 获取媒体文件的访问地址
+ * @param fileName - 媒体文件名
+ * @returns string - 可直接用于 img、audio 等标签 src 属性的地址
+ * @description 后端通过 media 协议提供媒体文件夹中的文件
+ */
+export function mediaUrl(fileName: string): string {
+  return convertFileSrc(fileName, "media");
+}
+
+/**
+ * 导入媒体文件
+ * @param file - 用户选择的文件
+ * @returns Promise<string | null> - 返回保存后的媒体文件名，导入失败时返回null
+ * @description 调用后端media.rs中的import_media命令，按内容哈希保存文件，内容相同的文件只保存一份
+ */
+export async function importMedia(file: File): Promise<string | null> {
+  try {
+    const data = Array.from(new Uint8Array(await file.arrayBuffer()));
+    const fileName = await invoke<string>("import_media", {
+      fileName: file.name,
+      data,
+    });
+    return fileName;
+  } catch (error) {
+    console.error("导入媒体文件失败:", error);
+    return null;
+  }
+}
+
+/**
+ * 从本地路径导入媒体文件
+ * @param path - 本地文件路径
+ * @returns Promise<string | null> - 返回保存后的媒体文件名，导入失败时返回null
+ * @description 调用后端media.rs中的import_media_file命令读取并保存指定路径的文件
+ */
+export async function importMediaFile(path: string): Promise<string | null> {
+  try {
+    const fileName = await invoke<string>("import_media_file", { path });
+    return fileName;
+  } catch (error) {
+    console.error("导入媒体文件失败:", error);
+    return null;
+  }
+}
+
+/**
+ * 检查媒体文件
+ * @param deleteUnused - 是否删除未被任何卡片引用的媒体文件
+ * @returns Promise<MediaCheckReportData | null> - 返回缺失和未使用的媒体文件，检查失败时返回null
+ * @description 调用后端media.rs中的check_media命令对比卡片引用和媒体文件夹中的文件
+ */
+export async function checkMedia(
+  deleteUnused: boolean = false
+): Promise<MediaCheckReportData | null> {
+  try {
+    const report = await invoke<MediaCheckReportData>("check_media", {
+      deleteUnused,
+    });
+    return report;
+  } catch (error) {
+    console.error("检查媒体文件失败:", error);
+    return null;
+  }
+}
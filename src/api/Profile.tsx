@@ -0,0 +1,115 @@
+import { invoke } from "@tauri-apps/api/core";
+
+/**
+ * 配置文件接口，每个配置文件拥有独立的数据库、设置和媒体文件夹
+ */
+export interface ProfileData {
+  id: string;
+  name: string;
+  created_at: string;
+}
+
+/**
+ * 获取所有配置文件
+ * @returns Promise<ProfileData[]> - 返回所有配置文件，获取失败时返回空数组
+ * @description 调用后端profile.rs中的list_profiles命令
+ */
+export async function listProfiles(): Promise<ProfileData[]> {
+  try {
+    const profiles = await invoke<ProfileData[]>("list_profiles");
+    return profiles;
+  } catch (error) {
+    console.error("获取配置文件列表失败:", error);
+    return [];
+  }
+}
+
+/**
+ * 获取当前使用的配置文件
+ * @returns Promise<ProfileData | null> - 返回当前配置文件，获取失败时返回null
+ * @description 调用后端profile.rs中的current_profile命令
+ */
+export async function getCurrentProfile(): Promise<ProfileData | null> {
+  try {
+    const profile = await invoke<ProfileData>("current_profile");
+    return profile;
+  } catch (error) {
+    console.error("获取当前配置文件失败:", error);
+    return null;
+  }
+}
+
+/**
+ * 创建配置文件
+ * @param name - 配置文件名称，不能与已有配置文件重名
+ * @returns Promise<{success: boolean, message: string, profile?: ProfileData}> - 返回创建结果
+ * @description 调用后端profile.rs中的create_profile命令，新配置文件的数据库在第一次切换时创建
+ */
+export async function createProfile(
+  name: string
+): Promise<{ success: boolean; message: string; profile?: ProfileData }> {
+  try {
+    const profile = await invoke<ProfileData>("create_profile", { name });
+    return { success: true, message: `配置文件 "${name}" 创建成功！`, profile };
+  } catch (error) {
+    console.error("创建配置文件失败:", error);
+    return { success: false, message: `${error}` };
+  }
+}
+
+/**
+ * 切换配置文件
+ * @param profileId - 目标配置文件ID
+ * @returns Promise<{success: boolean, message: string}> - 返回切换结果
+ * @description 调用后端profile.rs中的switch_profile命令，后端无需重启即可切换数据库，
+ *              切换成功后前端缓存的牌组、卡片和模板数据需要重新加载
+ */
+export async function switchProfile(
+  profileId: string
+): Promise<{ success: boolean; message: string }> {
+  try {
+    const profile = await invoke<ProfileData>("switch_profile", { profileId });
+    return { success: true, message: `已切换到配置文件 "${profile.name}"` };
+  } catch (error) {
+    console.error("切换配置文件失败:", error);
+    return { success: false, message: `${error}` };
+  }
+}
+
+/**
+ * 重命名配置文件
+ * @param profileId - 配置文件ID
+ * @param name - 新名称，不能与其他配置文件重名
+ * @returns Promise<{success: boolean, message: string}> - 返回重命名结果
+ * @description 调用后端profile.rs中的rename_profile命令
+ */
+export async function renameProfile(
+  profileId: string,
+  name: string
+): Promise<{ success: boolean; message: string }> {
+  try {
+    await invoke<ProfileData>("rename_profile", { profileId, name });
+    return { success: true, message: "配置文件已重命名" };
+  } catch (error) {
+    console.error("重命名配置文件失败:", error);
+    return { success: false, message: `${error}` };
+  }
+}
+
+/**
+ * 删除配置文件
+ * @param profileId - 配置文件ID，不能是当前使用的配置文件
+ * @returns Promise<{success: boolean, message: string}> - 返回删除结果
+ * @description 调用后端profile.rs中的delete_profile命令，配置文件的数据库、设置和媒体文件夹会一并删除
+ */
+export async function deleteProfile(
+  profileId: string
+): Promise<{ success: boolean; message: string }> {
+  try {
+    await invoke("delete_profile", { profileId });
+    return { success: true, message: "配置文件已删除" };
+  } catch (error) {
+    console.error("删除配置文件失败:", error);
+    return { success: false, message: `${error}` };
+  }
+}
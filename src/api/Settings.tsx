@@ -1,9 +1,10 @@
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 
 /**
  * 获取当前的FSRS模型参数
  *
- * @returns 返回当前的FSRS参数数组
+ * @returns 返回当前的FSRS参数数组，FSRS-4.5为17个，FSRS-5为19个，FSRS-6为21个
  */
 export async function getFsrsParams(): Promise<number[]> {
   try {
@@ -15,25 +16,173 @@ export async function getFsrsParams(): Promise<number[]> {
   }
 }
 
+/**
+ * 设置FSRS模型参数
+ *
+ * @param params 新的参数，数量必须为17、19或21，每个参数需在FSRS的取值范围内
+ * @description 参数不合法时后端返回第一个不合法参数的错误信息，保存后记录到参数历史
+ */
+export async function setFsrsParams(params: number[]): Promise<void> {
+  try {
+    await invoke("set_fsrs_params", { params });
+  } catch (error) {
+    console.error("设置FSRS参数失败:", error);
+    throw error;
+  }
+}
+
+/**
+ * 恢复FSRS库的默认参数
+ *
+ * @returns 返回默认参数
+ */
+export async function resetFsrsParams(): Promise<number[]> {
+  try {
+    return await invoke<number[]>("reset_fsrs_params");
+  } catch (error) {
+    console.error("恢复默认参数失败:", error);
+    throw error;
+  }
+}
+
+export interface ParameterMetrics {
+  log_loss: number;
+  rmse_bins: number;
+}
+
+/**
+ * 训练使用的数据
+ * first_day、last_day 为使用的复习记录的本地日期范围（YYYY-MM-DD），没有可用的复习记录时为null
+ */
+export interface TrainingDataset {
+  item_count: number; // 复习序列数量
+  card_count: number;
+  review_count: number;
+  first_day: string | null;
+  last_day: string | null;
+}
+
+/**
+ * 没有训练的原因
+ * not_enough_reviews：复习记录不足；cancelled：训练被取消
+ */
+export type TrainingSkipReason = "not_enough_reviews" | "cancelled";
+
+export interface TrainingResult {
+  current_params: number[];
+  candidate_params: number[];
+  current: ParameterMetrics | null; // 没有训练时为null
+  candidate: ParameterMetrics | null;
+  improved: boolean;
+  applied: boolean;
+  dataset: TrainingDataset;
+  skipped: TrainingSkipReason | null;
+}
+
 /**
  * 训练FSRS模型参数
  *
- * @returns 返回优化后的FSRS参数数组
+ * @param onProgress 可选，进度回调，参数为已训练和总共的样本数量
+ * @returns 返回当前参数和候选参数及其评估结果，候选参数更好时已自动保存
+ * @description 训练在后台进行，可以通过cancelFsrsTraining取消，没有训练时skipped说明原因
  */
-export async function trainFsrsModel(): Promise<number[]> {
+export async function trainFsrsModel(
+  onProgress?: (progress: JobProgress) => void
+): Promise<TrainingResult> {
+  const unlisten = onProgress
+    ? await listen<JobProgress>("training-progress", (event) =>
+        onProgress(event.payload)
+      )
+    : undefined;
   try {
-    const optimizedParams = await invoke<number[]>("train_fsrs_model");
-    return optimizedParams;
+    return await invoke<TrainingResult>("train_fsrs_model");
   } catch (error) {
     console.error("训练FSRS模型失败:", error);
     throw error;
+  } finally {
+    unlisten?.();
+  }
+}
+
+/**
+ * 取消FSRS模型训练、重新安排复习时间或重新计算记忆状态
+ *
+ * @returns 返回是否有正在进行的任务
+ * @description 三者同一时间只能进行一个，被取消的重新计算不会修改任何卡片
+ */
+export async function cancelFsrsTraining(): Promise<boolean> {
+  try {
+    return await invoke<boolean>("cancel_fsrs_training");
+  } catch (error) {
+    console.error("取消FSRS模型训练失败:", error);
+    throw error;
+  }
+}
+
+/**
+ * 应用最近一次训练得到的FSRS模型参数
+ *
+ * @returns 返回应用的参数
+ * @description 候选参数保存在后端最近一次训练的记录中，校验后保存，并将复习数量和评估结果记录到参数历史
+ */
+export async function applyTrainedFsrsParams(): Promise<number[]> {
+  try {
+    return await invoke<number[]>("apply_trained_fsrs_params");
+  } catch (error) {
+    console.error("保存FSRS参数失败:", error);
+    throw error;
+  }
+}
+
+/**
+ * 参数来源
+ * default：FSRS默认参数；trained：根据复习记录训练；manual：手动设置或来源未知
+ */
+export type ParamsSource = "default" | "trained" | "manual";
+
+export interface ParamsHistoryEntry {
+  history_id: number;
+  params: number[];
+  source: ParamsSource;
+  created_at: string;
+  review_count: number | null; // 训练时的复习数量
+  metrics: ParameterMetrics | null;
+  reverted_from: number | null; // 恢复的历史记录ID
+}
+
+/**
+ * 获取FSRS参数历史记录
+ *
+ * @returns 返回按时间从新到旧排序的参数历史，第一条为当前使用的参数
+ */
+export async function getFsrsParamsHistory(): Promise<ParamsHistoryEntry[]> {
+  try {
+    return await invoke<ParamsHistoryEntry[]>("get_fsrs_params_history");
+  } catch (error) {
+    console.error("获取FSRS参数历史失败:", error);
+    throw error;
+  }
+}
+
+/**
+ * 恢复历史记录中的FSRS模型参数
+ *
+ * @param historyId 需要恢复的历史记录ID
+ * @returns 返回恢复后的参数
+ */
+export async function revertFsrsParams(historyId: number): Promise<number[]> {
+  try {
+    return await invoke<number[]>("revert_fsrs_params", { historyId });
+  } catch (error) {
+    console.error("恢复FSRS参数失败:", error);
+    throw error;
   }
 }
 
 /**
  * 获取当前的记忆留存率
  *
- * @returns 返回当前的记忆留存率（0.6-1.0之间的浮点数）
+ * @returns 返回当前的记忆留存率（0.6-0.99之间的浮点数）
  */
 export async function getDesiredRetention(): Promise<number> {
   try {
@@ -48,12 +197,12 @@ export async function getDesiredRetention(): Promise<number> {
 /**
  * 设置记忆留存率
  *
- * @param retention 新的记忆留存率值（0.6-1.0之间的浮点数）
+ * @param retention 新的记忆留存率值（0.6-0.99之间的浮点数），设置后立即保存到配置文件
  */
 export async function setDesiredRetention(retention: number): Promise<void> {
   try {
-    if (retention < 0.6 || retention > 1.0) {
-      throw new Error("记忆留存率必须在0.6到1.0之间");
+    if (retention < 0.6 || retention > 0.99) {
+      throw new Error("记忆留存率必须在0.6到0.99之间");
     }
     await invoke("set_desired_retention", { retention });
   } catch (error) {
@@ -61,3 +210,328 @@ export async function setDesiredRetention(retention: number): Promise<void> {
     throw error;
   }
 }
+
+/**
+ * 获取训练FSRS模型时是否排除筛选学习和临时抱佛脚的复习
+ *
+ * @returns 返回是否排除
+ */
+export async function getExcludeFilteredReviews(): Promise<boolean> {
+  try {
+    return await invoke<boolean>("get_exclude_filtered_reviews");
+  } catch (error) {
+    console.error("获取训练设置失败:", error);
+    throw error;
+  }
+}
+
+/**
+ * 设置训练FSRS模型时是否排除筛选学习和临时抱佛脚的复习
+ *
+ * @param exclude 是否排除，设置后立即保存到配置文件
+ */
+export async function setExcludeFilteredReviews(
+  exclude: boolean
+): Promise<void> {
+  try {
+    await invoke("set_exclude_filtered_reviews", { exclude });
+  } catch (error) {
+    console.error("设置训练设置失败:", error);
+    throw error;
+  }
+}
+
+/**
+ * 工作量模拟选项
+ * deck_ids 为空时模拟全部牌组；new_cards_per_day 省略时使用最近30天平均每天学习的新卡片数量；
+ * max_reviews_per_day 省略时不限制每天的复习数量
+ */
+export interface SimulationOptions {
+  deck_ids?: number[];
+  days?: number;
+  new_cards_per_day?: number | null;
+  max_reviews_per_day?: number | null;
+}
+
+export interface WorkloadDay {
+  date: string;
+  reviews: number;
+  new_cards: number;
+  cost_seconds: number;
+  memorized: number;
+}
+
+export interface WorkloadSummary {
+  desired_retention: number;
+  total_reviews: number;
+  total_new_cards: number;
+  average_reviews_per_day: number;
+  total_cost_seconds: number;
+  memorized: number;
+  cost_per_memorized: number | null;
+}
+
+export interface WorkloadSimulation {
+  summary: WorkloadSummary;
+  days: WorkloadDay[];
+}
+
+export interface RetentionRecommendation {
+  recommended: number | null;
+  points: WorkloadSummary[];
+}
+
+export interface AutoOptimize {
+  enabled: boolean;
+  review_interval: number; // 距上次训练新增的复习数量
+  day_interval: number; // 距上次训练经过的天数
+}
+
+export interface OptimizationRun {
+  trained_at: string;
+  review_count: number;
+  automatic: boolean;
+  applied: boolean;
+  candidate_params: number[];
+  candidate: ParameterMetrics | null; // 候选参数的评估结果
+  dataset_review_count: number; // 训练使用的复习数量
+}
+
+/**
+ * 获取自动重新训练FSRS模型的设置
+ *
+ * @returns 返回是否开启和重新训练的间隔
+ */
+export async function getAutoOptimize(): Promise<AutoOptimize> {
+  try {
+    return await invoke<AutoOptimize>("get_auto_optimize");
+  } catch (error) {
+    console.error("获取自动训练设置失败:", error);
+    throw error;
+  }
+}
+
+/**
+ * 设置自动重新训练FSRS模型
+ *
+ * @param settings 新的设置，间隔必须大于0，设置后立即保存到配置文件
+ * @description 开启后每次启动时检查，新增的复习数量或经过的天数达到间隔时在后台训练，评估更好时才应用
+ */
+export async function setAutoOptimize(settings: AutoOptimize): Promise<void> {
+  try {
+    await invoke("set_auto_optimize", { settings });
+  } catch (error) {
+    console.error("设置自动训练失败:", error);
+    throw error;
+  }
+}
+
+export interface DeckPreset {
+  name: string;
+  deck_ids: number[];
+}
+
+/**
+ * 获取牌组预设
+ *
+ * @returns 返回所有牌组预设的名称和其中的牌组ID
+ */
+export async function getDeckPresets(): Promise<DeckPreset[]> {
+  try {
+    return await invoke<DeckPreset[]>("get_deck_presets");
+  } catch (error) {
+    console.error("获取牌组预设失败:", error);
+    throw error;
+  }
+}
+
+/**
+ * 设置牌组预设
+ *
+ * @param presets 全部牌组预设，名称不能为空或重复，设置后立即保存到配置文件
+ * @description 重新安排复习时间和重新计算记忆状态时可以按预设选择牌组
+ */
+export async function setDeckPresets(presets: DeckPreset[]): Promise<void> {
+  try {
+    await invoke("set_deck_presets", { presets });
+  } catch (error) {
+    console.error("设置牌组预设失败:", error);
+    throw error;
+  }
+}
+
+/**
+ * 获取加载设置时发现的问题
+ *
+ * @returns 返回被重置为默认值的配置项说明，设置有效时返回空数组
+ * @description 设置文件无效时后端会将其备份为 settings.json.bak，并只重置无效的配置项
+ */
+export async function getConfigWarnings(): Promise<string[]> {
+  try {
+    return await invoke<string[]>("get_config_warnings");
+  } catch (error) {
+    console.error("获取设置问题失败:", error);
+    throw error;
+  }
+}
+
+/**
+ * 获取最近一次完成的FSRS模型训练
+ *
+ * @returns 返回训练时间、复习数量和是否应用了新的参数，从未训练过时返回null
+ */
+export async function getLastOptimization(): Promise<OptimizationRun | null> {
+  try {
+    return await invoke<OptimizationRun | null>("get_last_optimization");
+  } catch (error) {
+    console.error("获取训练记录失败:", error);
+    throw error;
+  }
+}
+
+/**
+ * 监听自动重新训练完成
+ *
+ * @param callback 自动训练完成时调用，参数为训练结果
+ * @returns 返回取消监听的函数
+ */
+export async function onAutoOptimizeFinished(
+  callback: (result: TrainingResult) => void
+): Promise<() => void> {
+  return listen<TrainingResult>("auto-optimize-finished", (event) =>
+    callback(event.payload)
+  );
+}
+
+/**
+ * 模拟给定记忆留存率下未来每天的工作量
+ *
+ * @param retention 记忆留存率
+ * @param options 模拟选项，默认模拟全部牌组未来365天
+ * @returns 返回每天的复习次数、预计用时和预计记住的卡片数量
+ */
+export async function simulateRetentionWorkload(
+  retention: number,
+  options: SimulationOptions = {}
+): Promise<WorkloadSimulation> {
+  try {
+    return await invoke<WorkloadSimulation>("simulate_retention_workload", {
+      options,
+      retention,
+    });
+  } catch (error) {
+    console.error("模拟工作量失败:", error);
+    throw error;
+  }
+}
+
+/**
+ * 推荐记忆留存率
+ *
+ * @param options 模拟选项，默认模拟全部牌组未来365天
+ * @returns 返回70%到95%之间各留存率的预计工作量，以及每记住一张卡片用时最少的留存率
+ */
+export async function recommendDesiredRetention(
+  options: SimulationOptions = {}
+): Promise<RetentionRecommendation> {
+  try {
+    return await invoke<RetentionRecommendation>(
+      "recommend_desired_retention",
+      { options }
+    );
+  } catch (error) {
+    console.error("推荐记忆留存率失败:", error);
+    throw error;
+  }
+}
+
+export interface JobProgress {
+  current: number;
+  total: number;
+}
+
+export interface RescheduleSummary {
+  total: number;
+  rescheduled: number;
+  earlier: number;
+  later: number;
+  unchanged: number;
+  skipped: number;
+  reviewed_during_job: number; // 计算期间被复习而保持不变的卡片数量
+  cancelled: boolean; // 被取消时为true，此时没有修改任何卡片
+}
+
+/**
+ * 重新安排复习时间
+ *
+ * @param deckIds 需要重新安排的牌组ID列表，为空时重新安排所有牌组
+ * @param onProgress 可选，进度回调，参数为已处理和总共的卡片数量
+ * @param preset 可选，预设名称，指定时重新安排预设中的牌组，此时 deckIds 必须为空
+ * @returns 返回重新安排的卡片数量，以及到期时间提前和推后的卡片数量
+ * @description 修改FSRS参数或记忆留存率后，用当前设置重新计算卡片的记忆状态和到期时间
+ */
+export async function rescheduleCards(
+  deckIds: number[] = [],
+  onProgress?: (progress: JobProgress) => void,
+  preset?: string
+): Promise<RescheduleSummary> {
+  const unlisten = onProgress
+    ? await listen<JobProgress>("reschedule-progress", (event) =>
+        onProgress(event.payload)
+      )
+    : undefined;
+  try {
+    // 可以通过 cancelFsrsTraining 取消
+    return await invoke<RescheduleSummary>("reschedule_all_cards", {
+      deckIds,
+      preset: preset ?? null,
+    });
+  } catch (error) {
+    console.error("重新安排复习时间失败:", error);
+    throw error;
+  } finally {
+    unlisten?.();
+  }
+}
+
+export interface RecomputeSummary {
+  total: number;
+  from_history: number;
+  from_legacy: number;
+  skipped: number;
+  reviewed_during_job: number; // 计算期间被复习而保持不变的卡片数量
+  cancelled: boolean; // 被取消时为true，此时没有修改任何卡片
+}
+
+/**
+ * 从复习记录重新计算记忆状态
+ *
+ * @param deckIds 需要处理的牌组ID列表，为空时处理所有牌组
+ * @param onProgress 可选，进度回调，参数为已处理和总共的卡片数量
+ * @param preset 可选，预设名称，指定时处理预设中的牌组，此时 deckIds 必须为空
+ * @returns 返回从复习记录重建和从旧间隔估算记忆状态的卡片数量
+ * @description 用当前的FSRS参数重放复习记录重建卡片的稳定性和难度，到期时间保持不变
+ */
+export async function recomputeMemoryStates(
+  deckIds: number[] = [],
+  onProgress?: (progress: JobProgress) => void,
+  preset?: string
+): Promise<RecomputeSummary> {
+  const unlisten = onProgress
+    ? await listen<JobProgress>("recompute-progress", (event) =>
+        onProgress(event.payload)
+      )
+    : undefined;
+  try {
+    // 可以通过 cancelFsrsTraining 取消
+    return await invoke<RecomputeSummary>("recompute_card_memory_states", {
+      deckIds,
+      preset: preset ?? null,
+    });
+  } catch (error) {
+    console.error("重新计算记忆状态失败:", error);
+    throw error;
+  } finally {
+    unlisten?.();
+  }
+}
@@ -0,0 +1,202 @@
+import { invoke } from "@tauri-apps/api/core";
+
+/**
+ * 统计筛选条件
+ * deck_ids 为空时统计全部牌组，start、end 为本地日期（YYYY-MM-DD），包含首尾两天，省略时不限制
+ */
+export interface StatisticsFilter {
+  deck_ids: number[];
+  start?: string | null;
+  end?: string | null;
+}
+
+/**
+ * 留存率统计的汇总周期，周从周一开始
+ */
+export type StatisticsPeriod = "day" | "week" | "month";
+
+export interface DailyReviewCount {
+  date: string;
+  again: number;
+  hard: number;
+  good: number;
+  easy: number;
+}
+
+export interface PeriodRetention {
+  period: string; // 周期第一天的日期，按月汇总时为 YYYY-MM
+  reviews: number;
+  passed: number;
+  retention: number;
+}
+
+export interface DayCount {
+  date: string;
+  count: number;
+}
+
+export interface DueForecast {
+  overdue: number; // 预测范围开始之前到期的卡片数量
+  days: DayCount[];
+}
+
+export interface HistogramBin {
+  start: number;
+  end: number | null; // 最后一个区间没有上界
+  count: number;
+}
+
+export interface Distribution {
+  bins: HistogramBin[];
+  count: number;
+  average: number | null;
+}
+
+export interface MemoryDistributions {
+  stability: Distribution;
+  difficulty: Distribution;
+  retrievability: Distribution;
+}
+
+export interface CardStateCounts {
+  new: number;
+  learning: number;
+  young: number;
+  mature: number;
+  total: number;
+}
+
+export interface DailyAnswerTime {
+  date: string;
+  count: number;
+  average_ms: number;
+}
+
+export interface AnswerTimeStats {
+  review_count: number;
+  total_ms: number;
+  average_ms: number | null;
+  days: DailyAnswerTime[];
+}
+
+/**
+ * 将日期格式化为后端使用的本地日期字符串
+ * @param date - 日期对象
+ * @returns string - YYYY-MM-DD 格式的本地日期
+ */
+export function toLocalDateString(date: Date): string {
+  const month = `${date.getMonth() + 1}`.padStart(2, "0");
+  const day = `${date.getDate()}`.padStart(2, "0");
+  return `${date.getFullYear()}-${month}-${day}`;
+}
+
+/**
+ * 获取每日复习数量
+ * @param filter - 统计筛选条件
+ * @returns Promise<DailyReviewCount[]> - 每天各评分的复习次数，没有复习的日期不返回，获取失败时返回空数组
+ * @description 调用后端statistics.rs中的review_statistics命令
+ */
+export async function getReviewStatistics(
+  filter: StatisticsFilter
+): Promise<DailyReviewCount[]> {
+  try {
+    return await invoke<DailyReviewCount[]>("review_statistics", { filter });
+  } catch (error) {
+    console.error("获取复习统计失败:", error);
+    return [];
+  }
+}
+
+/**
+ * 获取真实留存率
+ * @param filter - 统计筛选条件
+ * @param period - 汇总周期
+ * @returns Promise<PeriodRetention[]> - 每个周期的留存率，获取失败时返回空数组
+ * @description 调用后端statistics.rs中的retention_statistics命令，只统计距上次复习至少一天的复习
+ */
+export async function getRetentionStatistics(
+  filter: StatisticsFilter,
+  period: StatisticsPeriod
+): Promise<PeriodRetention[]> {
+  try {
+    return await invoke<PeriodRetention[]>("retention_statistics", {
+      filter,
+      period,
+    });
+  } catch (error) {
+    console.error("获取留存率统计失败:", error);
+    return [];
+  }
+}
+
+/**
+ * 获取到期预测
+ * @param filter - 统计筛选条件，时间范围为预测的日期范围，默认从今天开始预测30天
+ * @returns Promise<DueForecast | null> - 每天到期的卡片数量，获取失败时返回null
+ * @description 调用后端statistics.rs中的due_forecast命令
+ */
+export async function getDueForecast(
+  filter: StatisticsFilter
+): Promise<DueForecast | null> {
+  try {
+    return await invoke<DueForecast>("due_forecast", { filter });
+  } catch (error) {
+    console.error("获取到期预测失败:", error);
+    return null;
+  }
+}
+
+/**
+ * 获取稳定性、难度和可提取性分布
+ * @param filter - 统计筛选条件，时间范围按最后复习时间筛选
+ * @returns Promise<MemoryDistributions | null> - 记忆状态分布，获取失败时返回null
+ * @description 调用后端statistics.rs中的memory_distributions命令
+ */
+export async function getMemoryDistributions(
+  filter: StatisticsFilter
+): Promise<MemoryDistributions | null> {
+  try {
+    return await invoke<MemoryDistributions>("memory_distributions", {
+      filter,
+    });
+  } catch (error) {
+    console.error("获取记忆状态分布失败:", error);
+    return null;
+  }
+}
+
+/**
+ * 获取各状态的卡片数量
+ * @param filter - 统计筛选条件，指定时间范围时只统计最后复习时间在范围内的卡片
+ * @returns Promise<CardStateCounts | null> - 各状态的卡片数量，获取失败时返回null
+ * @description 调用后端statistics.rs中的card_state_statistics命令
+ */
+export async function getCardStateStatistics(
+  filter: StatisticsFilter
+): Promise<CardStateCounts | null> {
+  try {
+    return await invoke<CardStateCounts>("card_state_statistics", { filter });
+  } catch (error) {
+    console.error("获取卡片状态统计失败:", error);
+    return null;
+  }
+}
+
+/**
+ * 获取回答用时统计
+ * @param filter - 统计筛选条件
+ * @returns Promise<AnswerTimeStats | null> - 平均回答用时和每日用时，获取失败时返回null
+ * @description 调用后端statistics.rs中的answer_time_statistics命令，只统计记录了用时的复习
+ */
+export async function getAnswerTimeStatistics(
+  filter: StatisticsFilter
+): Promise<AnswerTimeStats | null> {
+  try {
+    return await invoke<AnswerTimeStats>("answer_time_statistics", {
+      filter,
+    });
+  } catch (error) {
+    console.error("获取回答用时统计失败:", error);
+    return null;
+  }
+}
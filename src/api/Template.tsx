@@ -6,11 +6,54 @@ export interface TemplateData {
   template_fields: [string, boolean][];
 }
 
+/**
+ * 模板字段类型：纯文本、富文本（HTML）、Markdown、选项列表、整数、媒体文件引用、填空
+ */
+export type FieldType =
+  | "text"
+  | "html"
+  | "markdown"
+  | "choice"
+  | "integer"
+  | "media"
+  | "cloze";
+
 export interface TemplateFieldData {
   field_id: number;
   template_id: number;
   name: string;
   is_front: boolean;
+  field_type: FieldType;
+  required: boolean;
+  pattern: string | null;
+}
+
+/**
+ * 模板使用情况接口
+ */
+export interface TemplateUsageData {
+  template_id: number;
+  card_count: number;
+  deck_count: number;
+}
+
+/**
+ * 模板转换接口，field_mapping 的键为源模板字段ID，值为目标模板字段ID
+ */
+export interface TemplateConversionData {
+  target_template_id: number;
+  field_mapping: Record<number, number>;
+}
+
+/**
+ * 模板卡片格式接口，格式中可以使用 {{字段}}、{{#字段}}…{{/字段}}、{{^字段}}…{{/字段}}，
+ * 背面格式可以使用 {{FrontSide}} 引用正面内容
+ */
+export interface TemplateFormatData {
+  template_id: number;
+  front_format: string;
+  back_format: string;
+  css: string;
 }
 
 /**
@@ -24,9 +67,11 @@ export interface TemplateConfigData {
  * 模板信息接口
  */
 export interface TemplateInfoData {
+  templateId: number;
   templateName: string;
   className: string;
   importPath: string;
+  options: Record<string, unknown>;
 }
 
 /**
@@ -68,10 +113,80 @@ export async function getTemplateFields(
   }
 }
 
+/**
+ * 更新模板字段的类型和校验约束
+ * @param templateId - 模板ID
+ * @param fieldId - 字段ID
+ * @param fieldType - 字段类型
+ * @param required - 字段是否必填
+ * @param pattern - 字段内容需要匹配的正则表达式（可选），为空时不做格式校验
+ * @returns Promise<{success: boolean, message: string}> - 返回更新结果，失败时message为后端返回的校验信息
+ * @description 调用后端cardedit.rs中的set_template_field命令更新字段约束，之后添加或修改卡片时按这些约束校验
+ */
+export async function setTemplateField(
+  templateId: number,
+  fieldId: number,
+  fieldType: FieldType,
+  required: boolean,
+  pattern?: string
+): Promise<{ success: boolean; message: string }> {
+  try {
+    await invoke("set_template_field", {
+      templateId,
+      fieldId,
+      fieldType,
+      required,
+      pattern: pattern ?? null,
+    });
+    return { success: true, message: "字段设置已更新" };
+  } catch (error) {
+    console.error("更新模板字段失败:", error);
+    return { success: false, message: `${error}` };
+  }
+}
+
+/**
+ * 获取模板的卡片格式
+ * @param templateId - 模板ID
+ * @returns Promise<TemplateFormatData | null> - 返回模板的正面、背面格式与CSS，获取失败时返回null
+ * @description 调用后端cardedit.rs中的template_format命令，未设置格式的模板返回根据字段生成的默认格式
+ */
+export async function getTemplateFormat(
+  templateId: number
+): Promise<TemplateFormatData | null> {
+  try {
+    const format = await invoke<TemplateFormatData>("template_format", {
+      templateId,
+    });
+    return format;
+  } catch (error) {
+    console.error("获取模板格式失败:", error);
+    return null;
+  }
+}
+
+/**
+ * 设置模板的卡片格式
+ * @param format - 模板卡片格式，包含模板ID、正面格式、背面格式和CSS
+ * @returns Promise<{success: boolean, message: string}> - 返回设置结果，格式有误时message为具体的错误信息
+ * @description 调用后端cardedit.rs中的set_template_format命令，后端会检查格式语法和引用的字段
+ */
+export async function setTemplateFormat(
+  format: TemplateFormatData
+): Promise<{ success: boolean; message: string }> {
+  try {
+    await invoke("set_template_format", { format });
+    return { success: true, message: "模板格式已更新" };
+  } catch (error) {
+    console.error("更新模板格式失败:", error);
+    return { success: false, message: `${error}` };
+  }
+}
+
 /**
  * 获取模板配置信息
  * @returns Promise<TemplateConfigData> - 返回模板配置信息
- * @description 调用后端cardedit.rs中的get_template_config命令获取模板配置信息
+ * @description 调用后端cardedit.rs中的get_template_config命令从数据库获取每个模板的渲染配置
  */
 export async function getTemplateConfig(): Promise<TemplateConfigData> {
   try {
@@ -112,29 +227,20 @@ export async function loadTemplate(
  * @param templateFields - 模板字段列表，每个字段包含名称和是否为正面
  * @param className - 模板类名，用于在TemplateFactory中创建模板实例
  * @param importPath - 模板导入路径，用于在TemplateFactory中导入模板类
- * @param fileContent - 模板文件内容（可选），如果提供则后端会保存文件
+ * @param options - 模板渲染选项（可选），随渲染配置一起保存
  * @returns Promise<{success: boolean, message: string, templateId?: number}> - 返回添加结果
- * @description 1. 调用后端add_template_config API更新模板配置
- *              2. 调用后端add_template API将模板数据写入数据库
- *              3. 如果提供了fileContent，后端会将文件保存到templates目录
+ * @description 1. 调用后端add_template API将模板数据写入数据库
+ *              2. 调用后端add_template_config API按模板ID保存渲染配置
  */
 export async function addTemplate(
   templateName: string,
   templateFields: [string, boolean][],
   className: string,
   importPath: string,
-  fileContent?: string
+  options?: Record<string, unknown>
 ): Promise<{ success: boolean; message: string; templateId?: number }> {
   try {
-    // 1. 调用后端add_template_config API更新模板配置
-    await invoke("add_template_config", {
-      templateName,
-      className,
-      importPath,
-      fileContent, // 传递文件内容给后端
-    });
-
-    // 2. 调用后端add_template API将模板数据写入数据库
+    // 1. 调用后端add_template API将模板数据写入数据库
     const template = {
       template_id: 0, // 后端会自动分配ID
       template_name: templateName,
@@ -143,6 +249,14 @@ export async function addTemplate(
 
     const templateId = await invoke<number>("add_template", { template });
 
+    // 2. 调用后端add_template_config API保存模板渲染配置
+    await invoke("add_template_config", {
+      templateId,
+      className,
+      importPath,
+      options: options ?? null,
+    });
+
     return {
       success: true,
       message: `模板 "${templateName}" 添加成功！`,
@@ -156,3 +270,53 @@ export async function addTemplate(
     };
   }
 }
+
+/**
+ * 获取模板的使用情况
+ * @param templateId - 模板ID
+ * @returns Promise<TemplateUsageData | null> - 返回使用该模板的卡片数量和牌组数量，查询失败时返回null
+ * @description 调用后端cardedit.rs中的template_usage命令统计模板的使用情况
+ */
+export async function getTemplateUsage(
+  templateId: number
+): Promise<TemplateUsageData | null> {
+  try {
+    const usage = await invoke<TemplateUsageData>("template_usage", {
+      templateId,
+    });
+    return usage;
+  } catch (error) {
+    console.error("获取模板使用情况失败:", error);
+    return null;
+  }
+}
+
+/**
+ * 删除模板
+ * @param templateId - 要删除的模板ID
+ * @param conversion - 模板转换信息（可选），模板仍被卡片使用时必须提供，用于将这些卡片转换到目标模板
+ * @returns Promise<{success: boolean, message: string, convertedCount?: number}> - 返回删除结果
+ * @description 调用后端cardedit.rs中的delete_template命令删除模板，并同步清理模板配置
+ */
+export async function deleteTemplate(
+  templateId: number,
+  conversion?: TemplateConversionData
+): Promise<{ success: boolean; message: string; convertedCount?: number }> {
+  try {
+    const convertedCount = await invoke<number>("delete_template", {
+      templateId,
+      conversion: conversion ?? null,
+    });
+    return {
+      success: true,
+      message: "模板删除成功！",
+      convertedCount,
+    };
+  } catch (error) {
+    console.error("删除模板失败:", error);
+    return {
+      success: false,
+      message: `删除模板失败: ${error}`,
+    };
+  }
+}